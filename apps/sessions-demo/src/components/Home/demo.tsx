@@ -20,7 +20,13 @@ import { useTrade } from "./use-trade";
 import type { Transaction } from "./use-transaction-log";
 import { useTransactionLog } from "./use-transaction-log";
 
-export const Demo = ({ faucetAvailable }: { faucetAvailable: boolean }) => {
+export const Demo = ({
+  domain,
+  faucetAvailable,
+}: {
+  domain: string | undefined;
+  faucetAvailable: boolean;
+}) => {
   const connection = useConnection();
   const { appendTransaction, transactions } = useTransactionLog();
   const sessionState = useSession();
@@ -59,6 +65,7 @@ export const Demo = ({ faucetAvailable }: { faucetAvailable: boolean }) => {
             <TradeButton
               amount={0.5}
               appendTransaction={appendTransaction}
+              domain={domain}
               mint={NATIVE_MINT}
               sessionState={sessionState}
             />
@@ -128,11 +135,13 @@ const TradeButton = ({
   amount,
   sessionState,
   appendTransaction,
+  domain,
   mint,
 }: {
   amount: number;
   sessionState: EstablishedSessionState;
   appendTransaction: (tx: Transaction) => void;
+  domain: string | undefined;
   mint: PublicKey;
 }) => {
   const { state, execute } = useTrade(
@@ -140,6 +149,7 @@ const TradeButton = ({
     appendTransaction,
     amount,
     mint,
+    domain,
   );
   return (
     <Button isPending={state.type === AsyncStateType.Running} onClick={execute}>
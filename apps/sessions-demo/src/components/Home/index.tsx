@@ -1,4 +1,9 @@
-import { FAUCET_KEY } from "../../config/server";
+import { FAUCET_KEY, PROVIDER_CONFIG } from "../../config/server";
 import { Demo } from "./demo";
 
-export const Home = () => <Demo faucetAvailable={FAUCET_KEY !== undefined} />;
+export const Home = () => (
+  <Demo
+    domain={PROVIDER_CONFIG.domain}
+    faucetAvailable={FAUCET_KEY !== undefined}
+  />
+);
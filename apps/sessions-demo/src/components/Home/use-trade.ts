@@ -1,7 +1,10 @@
 import type { Wallet } from "@coral-xyz/anchor";
 import { AnchorProvider, BN } from "@coral-xyz/anchor";
 import { ExampleProgram } from "@fogo/sessions-idls";
-import { TransactionResultType } from "@fogo/sessions-sdk";
+import {
+  getDomainRecordAddress,
+  TransactionResultType,
+} from "@fogo/sessions-sdk";
 import type { EstablishedSessionState } from "@fogo/sessions-sdk-react";
 import { useConnection } from "@fogo/sessions-sdk-react";
 import {
@@ -19,6 +22,7 @@ export const useTrade = (
   appendTransaction: (tx: Transaction) => void,
   amount: number,
   mint: PublicKey,
+  domain: string | undefined,
 ) => {
   const connection = useConnection();
   const doTrade = useCallback(async () => {
@@ -42,6 +46,10 @@ export const useTrade = (
         ).methods
           .exampleTransfer(new BN(amount * Math.pow(10, decimals)))
           .accountsPartial({
+            // The session was started for the configured domain, or for the origin of the page if there is none
+            domainRecord: getDomainRecordAddress(
+              domain ?? globalThis.location.origin,
+            ),
             mint,
             signerOrSession: sessionState.sessionPublicKey,
             sink: sinkAta,
@@ -59,7 +67,7 @@ export const useTrade = (
     });
 
     return result;
-  }, [connection, sessionState, appendTransaction, amount, mint]);
+  }, [connection, sessionState, appendTransaction, amount, mint, domain]);
 
   return useAsync(doTrade);
 };
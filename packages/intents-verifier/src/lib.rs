@@ -0,0 +1,98 @@
+//! Off-chain verification of the intents accepted by the Fogo Sessions programs.
+//!
+//! Given the ed25519 instruction of a transaction, these functions decode the intent with the same parsers as the programs and run the checks that don't depend on on-chain state, so that bad intents can be rejected before paying for a simulation.
+//! The signature itself is not checked here, it's verified by the ed25519 precompile when the transaction executes.
+use nom::error::{Error, ErrorKind};
+use nom::Err;
+use solana_intents::{Intent, IntentError};
+use solana_program::instruction::Instruction;
+
+mod session;
+mod transfer;
+
+pub use session::verify_session_intent;
+pub use transfer::verify_transfer_intent;
+
+/// The reason why a program would reject an intent
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum Rejection {
+    #[error("The instruction is not an ed25519 instruction")]
+    IncorrectInstructionProgramId,
+    #[error("The ed25519 instruction's header is incorrect")]
+    SignatureVerificationUnexpectedHeader,
+    #[error("The ed25519 instruction could not be deserialized: {0}")]
+    DeserializeFailed(String),
+    #[error("The intent doesn't start with the expected prefix")]
+    WrongPrefix,
+    #[error("The intent version is missing or not supported")]
+    UnsupportedVersion,
+    #[error("The domain `{0}` is not a valid domain")]
+    InvalidDomain(String),
+    #[error("The key `{0}` is reserved and can't be used as an extra key")]
+    ReservedExtraKey(String),
+    #[error("The extra key `{0}` appears more than once")]
+    DuplicateExtraKey(String),
+    #[error("The intent could not be parsed at line `{0}`")]
+    Malformed(String),
+    #[error("The intent is for chain `{found}` but this chain is `{expected}`")]
+    ChainIdMismatch { expected: String, found: String },
+    #[error(
+        "The session expires at {expiration}, after the latest allowed expiration {max_expiration}"
+    )]
+    SessionTooLong {
+        expiration: i64,
+        max_expiration: i64,
+    },
+    #[error("Rate-limited tokens are not supported in this session version")]
+    RateLimitNotSupported,
+    #[error("Domain-scoped sessions are not supported in this session version")]
+    DomainScopeNotSupported,
+}
+
+/// Decodes the signer and the text of the intent of an ed25519 instruction, leaving the parsing to the caller
+fn load_raw_intent(instruction: Instruction) -> Result<Intent<Vec<u8>>, Rejection> {
+    Intent::try_from(instruction).map_err(|err| match err {
+        // Only returned when loading from the instructions sysvar
+        IntentError::NoIntentMessageInstruction(err) => {
+            Rejection::DeserializeFailed(err.to_string())
+        }
+        IntentError::IncorrectInstructionProgramId => Rejection::IncorrectInstructionProgramId,
+        IntentError::SignatureVerificationUnexpectedHeader => {
+            Rejection::SignatureVerificationUnexpectedHeader
+        }
+        IntentError::ParseFailedError(infallible) => match infallible {},
+        IntentError::DeserializeFailedError(err) => Rejection::DeserializeFailed(err.to_string()),
+    })
+}
+
+/// Returns the offset in `message` where the parser failed and the kind of parser that failed
+fn parse_failure(message: &[u8], err: &Err<Error<Vec<u8>>>) -> (usize, ErrorKind) {
+    match err {
+        Err::Error(Error { input, code }) | Err::Failure(Error { input, code }) => {
+            (message.len().saturating_sub(input.len()), *code)
+        }
+        Err::Incomplete(_) => (message.len(), ErrorKind::Eof),
+    }
+}
+
+/// Returns the line of `message` starting at `offset`
+fn line_at(message: &[u8], offset: usize) -> String {
+    let line = message
+        .get(offset..)
+        .unwrap_or_default()
+        .split(|byte| *byte == b'\n')
+        .next()
+        .unwrap_or_default();
+    String::from_utf8_lossy(line).trim_end().to_string()
+}
+
+/// Rejections that apply to the parse failures of any intent
+fn common_parse_rejection(message: &[u8], offset: usize, kind: ErrorKind) -> Option<Rejection> {
+    if offset == 0 {
+        Some(Rejection::WrongPrefix)
+    } else if kind != ErrorKind::CrLf && line_at(message, offset).starts_with("version:") {
+        Some(Rejection::UnsupportedVersion)
+    } else {
+        None
+    }
+}
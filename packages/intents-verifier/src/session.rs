@@ -0,0 +1,346 @@
+use crate::{common_parse_rejection, line_at, load_raw_intent, parse_failure, Rejection};
+use chain_id::ChainId;
+use fogo_sessions_sdk::session::intent::{
+    is_reserved_key, DOMAIN_PROGRAMS_VALUE, DOMAIN_SCOPE_MINOR_VERSION, MESSAGE_PREFIX,
+    PROGRAMS_KEY, RATE_LIMITS_MINOR_VERSION, RESERVED_KEYS, SUPPORTED_MINOR_VERSIONS,
+};
+use nom::{
+    bytes::complete::tag,
+    character::complete::line_ending,
+    combinator::{map_opt, opt},
+    error::{Error, ErrorKind},
+    multi::{count, many0},
+    Err, Parser,
+};
+use session_manager::clock::MAX_SESSION_DURATION;
+use session_manager::message::{Message, Programs, Tokens};
+use solana_intents::{key_value, tag_key_value, Intent, Version};
+use solana_program::instruction::Instruction;
+use std::collections::HashSet;
+
+/// Verifies that `start_session` would accept the intent of `instruction` on the chain of the chain id account `chain_id` at the unix timestamp `now`.
+/// The checks that depend on accounts (the session key, the token accounts and the domain record) are left to the caller.
+pub fn verify_session_intent(
+    instruction: Instruction,
+    chain_id: &ChainId,
+    now: i64,
+) -> Result<Intent<Message>, Rejection> {
+    let Intent { signer, message } = load_raw_intent(instruction)?;
+    let message =
+        Message::try_from(message.clone()).map_err(|err| parse_rejection(&message, &err))?;
+
+    if !chain_id.matches(&message.chain_id) {
+        return Err(Rejection::ChainIdMismatch {
+            expected: chain_id.chain_id.clone(),
+            found: message.chain_id,
+        });
+    }
+
+    let expiration = message.expires.timestamp();
+    let max_expiration = now.saturating_add(MAX_SESSION_DURATION);
+    if expiration > max_expiration {
+        return Err(Rejection::SessionTooLong {
+            expiration,
+            max_expiration,
+        });
+    }
+
+    if !SUPPORTED_MINOR_VERSIONS.contains(&message.version.minor) {
+        return Err(Rejection::UnsupportedVersion);
+    }
+    if message.version.minor < RATE_LIMITS_MINOR_VERSION
+        && matches!(&message.tokens, Tokens::Specific(tokens) if tokens.iter().any(|(_, _, period)| period.is_some()))
+    {
+        return Err(Rejection::RateLimitNotSupported);
+    }
+    if message.version.minor < DOMAIN_SCOPE_MINOR_VERSION && message.programs == Programs::Domain {
+        return Err(Rejection::DomainScopeNotSupported);
+    }
+
+    Ok(Intent { signer, message })
+}
+
+fn parse_rejection(message: &[u8], err: &Err<Error<Vec<u8>>>) -> Rejection {
+    let (offset, kind) = parse_failure(message, err);
+    if let Some(rejection) = common_parse_rejection(message, offset, kind) {
+        return rejection;
+    }
+
+    let line = line_at(message, offset);
+    match kind {
+        ErrorKind::MapRes if line.starts_with("domain:") => {
+            Rejection::InvalidDomain(line.trim_start_matches("domain:").trim_start().to_string())
+        }
+        ErrorKind::MapOpt => match extras_offset(message) {
+            Some((extras_offset, minor)) if extras_offset == offset => {
+                extra_key_rejection(message, offset, minor).unwrap_or(Rejection::Malformed(line))
+            }
+            _ => Rejection::Malformed(line),
+        },
+        _ => Rejection::Malformed(line),
+    }
+}
+
+/// Returns the offset of the extra keys, which follow the keys of every session intent, and the minor version of the intent
+fn extras_offset(message: &[u8]) -> Option<(usize, u8)> {
+    let (rest, (_, _, version, _, _)) = (
+        tag(MESSAGE_PREFIX),
+        line_ending,
+        tag_key_value::<_, Version, Error<&[u8]>, _>("version"),
+        // Every other reserved key but the optional `programs` is required
+        count(
+            key_value::<_, String, Error<&[u8]>>,
+            RESERVED_KEYS.len() - 2,
+        ),
+        opt(map_opt(
+            tag_key_value::<_, String, _, _>(PROGRAMS_KEY),
+            |programs| (programs == DOMAIN_PROGRAMS_VALUE).then_some(()),
+        )),
+    )
+        .parse(message)
+        .ok()?;
+    Some((message.len().saturating_sub(rest.len()), version.minor))
+}
+
+fn extra_key_rejection(message: &[u8], offset: usize, minor: u8) -> Option<Rejection> {
+    let (_, extra) = many0(key_value::<_, String, Error<&[u8]>>)
+        .parse(message.get(offset..)?)
+        .ok()?;
+    let mut keys = HashSet::new();
+    extra.into_iter().find_map(|(key, _)| {
+        let key = String::from_utf8_lossy(key).into_owned();
+        if is_reserved_key(&key, minor) {
+            Some(Rejection::ReservedExtraKey(key))
+        } else if !keys.insert(key.clone()) {
+            Some(Rejection::DuplicateExtraKey(key))
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_id::ChainIdDetails;
+    use chrono::{DateTime, Duration};
+    use fogo_sessions_sdk::client::ed25519_instruction;
+    use fogo_sessions_sdk::client::message::{Message as IntentMessage, Period, Token};
+    use solana_program::pubkey::Pubkey;
+
+    const CHAIN_ID: &str = "fogo-testnet";
+    const NUMERIC_CHAIN_ID: u64 = 1;
+    const NOW: i64 = 1_750_000_000;
+
+    fn chain_id() -> ChainId {
+        ChainId {
+            chain_id: CHAIN_ID.to_string(),
+            details: ChainIdDetails {
+                numeric_id: NUMERIC_CHAIN_ID,
+                authority: Pubkey::new_unique(),
+            },
+        }
+    }
+
+    fn intent_message(minor: u8, expires_in: Duration) -> IntentMessage {
+        let expires = DateTime::from_timestamp(NOW, 0).unwrap() + expires_in;
+        IntentMessage::new(
+            minor,
+            CHAIN_ID,
+            "https://app.xyz",
+            expires.to_rfc3339(),
+            Pubkey::new_unique(),
+        )
+    }
+
+    fn verify(signer: &Pubkey, message: &str) -> Result<Intent<Message>, Rejection> {
+        verify_session_intent(
+            ed25519_instruction(signer, &[0; 64], message.as_bytes()),
+            &chain_id(),
+            NOW,
+        )
+    }
+
+    #[test]
+    fn test_verify() {
+        let signer = Pubkey::new_unique();
+        let message = intent_message(6, Duration::hours(1))
+            .with_rate_limited_token(Token::Symbol("USDC".to_string()), "10", Period::Day)
+            .with_domain_scope()
+            .with_extra("referrer", "app");
+
+        let intent = verify(&signer, &message.to_string()).unwrap();
+
+        assert_eq!(intent.signer, signer);
+        assert_eq!(intent.message.session_key, message.session_key);
+        assert_eq!(intent.message.expires.timestamp(), NOW + 3600);
+        assert_eq!(intent.message.programs, Programs::Domain);
+    }
+
+    #[test]
+    fn test_instruction_rejections() {
+        let mut instruction = ed25519_instruction(&Pubkey::new_unique(), &[0; 64], b"");
+        instruction.program_id = Pubkey::new_unique();
+        assert_eq!(
+            verify_session_intent(instruction, &chain_id(), NOW).err(),
+            Some(Rejection::IncorrectInstructionProgramId)
+        );
+    }
+
+    #[test]
+    fn test_parse_rejections() {
+        let signer = Pubkey::new_unique();
+        let message = intent_message(6, Duration::hours(1));
+
+        assert_eq!(
+            verify(
+                &signer,
+                &message.to_string().replace("Fogo Sessions", "Fogo")
+            )
+            .err(),
+            Some(Rejection::WrongPrefix)
+        );
+        assert_eq!(
+            verify(
+                &signer,
+                &message.to_string().replace("version: 0.6", "version: 1.6")
+            )
+            .err(),
+            Some(Rejection::UnsupportedVersion)
+        );
+        assert_eq!(
+            verify(
+                &signer,
+                &message
+                    .to_string()
+                    .replace("https://app.xyz", "https://app.xyz/path")
+            )
+            .err(),
+            Some(Rejection::InvalidDomain("https://app.xyz/path".to_string()))
+        );
+        assert_eq!(
+            verify(
+                &signer,
+                &message.clone().with_extra("domain", "evil.com").to_string()
+            )
+            .err(),
+            Some(Rejection::ReservedExtraKey("domain".to_string()))
+        );
+        assert_eq!(
+            verify(
+                &signer,
+                &message.clone().with_extra("programs", "swap").to_string()
+            )
+            .err(),
+            Some(Rejection::ReservedExtraKey("programs".to_string()))
+        );
+        // `programs` is an extra key before version 0.5
+        assert_eq!(
+            verify(
+                &signer,
+                &intent_message(4, Duration::hours(1))
+                    .with_extra("programs", "swap")
+                    .to_string()
+            )
+            .unwrap()
+            .message
+            .extra
+            .get("programs")
+            .map(String::as_str),
+            Some("swap")
+        );
+        assert_eq!(
+            verify(
+                &signer,
+                &message
+                    .clone()
+                    .with_extra("referrer", "a")
+                    .with_extra("referrer", "b")
+                    .to_string()
+            )
+            .err(),
+            Some(Rejection::DuplicateExtraKey("referrer".to_string()))
+        );
+        assert_eq!(
+            verify(
+                &signer,
+                &message
+                    .to_string()
+                    .replace(&message.session_key.to_string(), "not-a-key")
+            )
+            .err(),
+            Some(Rejection::Malformed("session_key: not-a-key".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_message_rejections() {
+        let signer = Pubkey::new_unique();
+
+        assert_eq!(
+            verify(
+                &signer,
+                &intent_message(6, Duration::hours(1))
+                    .to_string()
+                    .replace(CHAIN_ID, "fogo-mainnet")
+            )
+            .err(),
+            Some(Rejection::ChainIdMismatch {
+                expected: CHAIN_ID.to_string(),
+                found: "fogo-mainnet".to_string()
+            })
+        );
+        // The chain can also be designated by its numeric id
+        assert!(verify(
+            &signer,
+            &intent_message(6, Duration::hours(1))
+                .to_string()
+                .replace(CHAIN_ID, &NUMERIC_CHAIN_ID.to_string())
+        )
+        .is_ok());
+        assert_eq!(
+            verify(
+                &signer,
+                &intent_message(6, Duration::hours(1))
+                    .to_string()
+                    .replace(CHAIN_ID, "2")
+            )
+            .err(),
+            Some(Rejection::ChainIdMismatch {
+                expected: CHAIN_ID.to_string(),
+                found: "2".to_string()
+            })
+        );
+        assert_eq!(
+            verify(&signer, &intent_message(6, Duration::days(9)).to_string()).err(),
+            Some(Rejection::SessionTooLong {
+                expiration: NOW + 9 * 24 * 60 * 60,
+                max_expiration: NOW + MAX_SESSION_DURATION
+            })
+        );
+        assert_eq!(
+            verify(&signer, &intent_message(7, Duration::hours(1)).to_string()).err(),
+            Some(Rejection::UnsupportedVersion)
+        );
+        assert_eq!(
+            verify(
+                &signer,
+                &intent_message(5, Duration::hours(1))
+                    .with_rate_limited_token(Token::Symbol("USDC".to_string()), "10", Period::Day)
+                    .to_string()
+            )
+            .err(),
+            Some(Rejection::RateLimitNotSupported)
+        );
+        assert_eq!(
+            verify(
+                &signer,
+                &intent_message(4, Duration::hours(1))
+                    .with_domain_scope()
+                    .to_string()
+            )
+            .err(),
+            Some(Rejection::DomainScopeNotSupported)
+        );
+    }
+}
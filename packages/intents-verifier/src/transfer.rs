@@ -0,0 +1,103 @@
+use crate::{common_parse_rejection, line_at, load_raw_intent, parse_failure, Rejection};
+use chain_id::ChainId;
+use intent_transfer::intrachain::message::Message;
+use solana_intents::Intent;
+use solana_program::instruction::Instruction;
+
+/// Verifies that `send_tokens` would accept the intent of `instruction` on the chain of the chain id account `chain_id`.
+/// The checks that depend on accounts (the nonce, the token accounts and the fee configuration) are left to the caller.
+pub fn verify_transfer_intent(
+    instruction: Instruction,
+    chain_id: &ChainId,
+) -> Result<Intent<Message>, Rejection> {
+    let Intent { signer, message } = load_raw_intent(instruction)?;
+    let message = Message::try_from(message.clone()).map_err(|err| {
+        let (offset, kind) = parse_failure(&message, &err);
+        common_parse_rejection(&message, offset, kind)
+            .unwrap_or_else(|| Rejection::Malformed(line_at(&message, offset)))
+    })?;
+
+    if !chain_id.matches(&message.chain_id) {
+        return Err(Rejection::ChainIdMismatch {
+            expected: chain_id.chain_id.clone(),
+            found: message.chain_id,
+        });
+    }
+
+    Ok(Intent { signer, message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_id::ChainIdDetails;
+    use fogo_sessions_sdk::client::ed25519_instruction;
+    use solana_program::pubkey::Pubkey;
+
+    fn chain_id() -> ChainId {
+        ChainId {
+            chain_id: "fogo-testnet".to_string(),
+            details: ChainIdDetails {
+                numeric_id: 1,
+                authority: Pubkey::new_unique(),
+            },
+        }
+    }
+
+    fn transfer_message(version: &str, chain_id: &str) -> String {
+        format!(
+            "Fogo Transfer:\nSigning this intent will transfer the tokens as described below.\n\nversion: {version}\nchain_id: {chain_id}\ntoken: FOGO\namount: 42.676\nrecipient: {}\nfee_token: USDC\nfee_amount: 0.001\nnonce: 1\n",
+            Pubkey::new_unique()
+        )
+    }
+
+    fn verify(message: &str) -> Result<Intent<Message>, Rejection> {
+        verify_transfer_intent(
+            ed25519_instruction(&Pubkey::new_unique(), &[0; 64], message.as_bytes()),
+            &chain_id(),
+        )
+    }
+
+    #[test]
+    fn test_verify() {
+        let signer = Pubkey::new_unique();
+        let intent = verify_transfer_intent(
+            ed25519_instruction(
+                &signer,
+                &[0; 64],
+                transfer_message("0.2", "fogo-testnet").as_bytes(),
+            ),
+            &chain_id(),
+        )
+        .unwrap();
+
+        assert_eq!(intent.signer, signer);
+        assert_eq!(intent.message.nonce, 1);
+        // The chain can also be designated by its numeric id
+        assert!(verify(&transfer_message("0.2", "1")).is_ok());
+    }
+
+    #[test]
+    fn test_rejections() {
+        assert_eq!(
+            verify(&transfer_message("0.2", "fogo-testnet").replace("Transfer", "Sessions")).err(),
+            Some(Rejection::WrongPrefix)
+        );
+        assert_eq!(
+            verify(&transfer_message("0.1", "fogo-testnet")).err(),
+            Some(Rejection::UnsupportedVersion)
+        );
+        assert_eq!(
+            verify(&transfer_message("0.2", "fogo-testnet").replace("nonce: 1", "nonce: one"))
+                .err(),
+            Some(Rejection::Malformed("nonce: one".to_string()))
+        );
+        assert_eq!(
+            verify(&transfer_message("0.2", "fogo-mainnet")).err(),
+            Some(Rejection::ChainIdMismatch {
+                expected: "fogo-testnet".to_string(),
+                found: "fogo-mainnet".to_string()
+            })
+        );
+    }
+}
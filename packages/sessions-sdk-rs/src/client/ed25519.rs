@@ -0,0 +1,65 @@
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+const ED25519_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("Ed25519SigVerify111111111111111111111111111");
+
+/// The header of an ed25519 instruction with a single signature: number of signatures, padding and 7 offsets
+const HEADER_LEN: u16 = 16;
+const PUBLIC_KEY_OFFSET: u16 = HEADER_LEN;
+const SIGNATURE_OFFSET: u16 = PUBLIC_KEY_OFFSET + 32;
+const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+/// The instruction index meaning "this instruction" in the ed25519 offsets
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Builds the ed25519 precompile instruction verifying that `signer` signed `message`, this is how the session manager receives intents.
+/// # Panics
+/// Panics if `message` is longer than `u16::MAX - 112` bytes, intents are much shorter than that.
+pub fn ed25519_instruction(signer: &Pubkey, signature: &[u8; 64], message: &[u8]) -> Instruction {
+    let message_len = u16::try_from(message.len())
+        .ok()
+        .filter(|len| len.checked_add(MESSAGE_OFFSET).is_some())
+        .expect("The message is too long to fit in an ed25519 instruction");
+
+    let mut data = Vec::with_capacity(usize::from(MESSAGE_OFFSET) + message.len());
+    data.push(1); // number of signatures
+    data.push(0); // padding
+    data.extend_from_slice(&SIGNATURE_OFFSET.to_le_bytes());
+    data.extend_from_slice(&CURRENT_INSTRUCTION.to_le_bytes());
+    data.extend_from_slice(&PUBLIC_KEY_OFFSET.to_le_bytes());
+    data.extend_from_slice(&CURRENT_INSTRUCTION.to_le_bytes());
+    data.extend_from_slice(&MESSAGE_OFFSET.to_le_bytes());
+    data.extend_from_slice(&message_len.to_le_bytes());
+    data.extend_from_slice(&CURRENT_INSTRUCTION.to_le_bytes());
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ED25519_PROGRAM_ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ed25519_instruction_layout() {
+        let signer = Pubkey::new_unique();
+        let signature = [7; 64];
+        let message = b"Fogo Sessions:";
+
+        let instruction = ed25519_instruction(&signer, &signature, message);
+
+        let mut expected = vec![
+            1, 0, 48, 0, 255, 255, 16, 0, 255, 255, 112, 0, 14, 0, 255, 255,
+        ];
+        expected.extend_from_slice(signer.as_ref());
+        expected.extend_from_slice(&signature);
+        expected.extend_from_slice(message);
+        assert_eq!(instruction.program_id, ED25519_PROGRAM_ID);
+        assert_eq!(instruction.data, expected);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::session::intent::{
+    DOMAIN_PROGRAMS_VALUE, MESSAGE_PREFIX, RATE_LIMIT_SEPARATOR, TOKENLESS_PERMISSIONS_VALUE,
+    UNLIMITED_TOKEN_PERMISSIONS_VALUE,
+};
+use crate::session::MAJOR;
+use solana_program::pubkey::Pubkey;
+use std::fmt::{self, Display, Formatter};
+
+/// How a token is designated in the tokens section of the intent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// The symbol in the token's metadata account, e.g. `USDC`
+    Symbol(String),
+    Mint(Pubkey),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Symbol(symbol) => write!(f, "{symbol}"),
+            Token::Mint(mint) => write!(f, "{mint}"),
+        }
+    }
+}
+
+/// The period of a rate-limited token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Minute,
+    Hour,
+    Day,
+    Week,
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Period::Minute => write!(f, "minute"),
+            Period::Hour => write!(f, "hour"),
+            Period::Day => write!(f, "day"),
+            Period::Week => write!(f, "week"),
+        }
+    }
+}
+
+/// A line of the tokens section of the intent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenLimit {
+    pub token: Token,
+    /// The amount in UI units, e.g. `10.5`
+    pub amount: String,
+    /// If set, `amount` can be spent in every period instead of once for the lifetime of the session. Rate limits require version `0.6` or later.
+    pub period: Option<Period>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tokens {
+    /// The session can spend up to the given amounts of the given tokens, no tokens at all if empty
+    Specific(Vec<TokenLimit>),
+    /// The session can spend any amount of any token
+    All,
+}
+
+/// The intent to start a session, rendered with `Display` in exactly the format the session manager program parses.
+/// The builder doesn't validate its inputs: extra keys must be snake_case and not reserved for the version (see `is_reserved_key`), and no value may contain a line break.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// The minor version of the session to start, the major version is always `MAJOR`
+    pub minor: u8,
+    pub chain_id: String,
+    pub domain: String,
+    /// The expiration of the session in RFC 3339 format, e.g. `2025-07-17T17:30:15.033Z`
+    pub expires: String,
+    pub session_key: Pubkey,
+    pub tokens: Tokens,
+    /// If set, the session can interact with programs added to the domain after it starts. Domain-scoped sessions require version `0.4` or later.
+    pub domain_scoped: bool,
+    pub extra: Vec<(String, String)>,
+}
+
+impl Message {
+    /// Creates the intent of a session that can't spend any tokens
+    pub fn new(
+        minor: u8,
+        chain_id: impl Into<String>,
+        domain: impl Into<String>,
+        expires: impl Into<String>,
+        session_key: Pubkey,
+    ) -> Self {
+        Self {
+            minor,
+            chain_id: chain_id.into(),
+            domain: domain.into(),
+            expires: expires.into(),
+            session_key,
+            tokens: Tokens::Specific(vec![]),
+            domain_scoped: false,
+            extra: vec![],
+        }
+    }
+
+    /// Allows the session to spend up to `amount` of `token` for its whole lifetime
+    pub fn with_token(self, token: Token, amount: impl Into<String>) -> Self {
+        self.with_token_limit(TokenLimit {
+            token,
+            amount: amount.into(),
+            period: None,
+        })
+    }
+
+    /// Allows the session to spend up to `amount` of `token` in every `period`
+    pub fn with_rate_limited_token(
+        self,
+        token: Token,
+        amount: impl Into<String>,
+        period: Period,
+    ) -> Self {
+        self.with_token_limit(TokenLimit {
+            token,
+            amount: amount.into(),
+            period: Some(period),
+        })
+    }
+
+    fn with_token_limit(mut self, token_limit: TokenLimit) -> Self {
+        match &mut self.tokens {
+            Tokens::Specific(token_limits) => token_limits.push(token_limit),
+            Tokens::All => self.tokens = Tokens::Specific(vec![token_limit]),
+        }
+        self
+    }
+
+    /// Allows the session to spend any amount of any token
+    pub fn with_unlimited_tokens(mut self) -> Self {
+        self.tokens = Tokens::All;
+        self
+    }
+
+    /// Allows the session to interact with any program of the domain, including programs added after the session starts.
+    /// The session manager program only accepts it from minor version `DOMAIN_SCOPE_MINOR_VERSION`.
+    pub fn with_domain_scope(mut self) -> Self {
+        self.domain_scoped = true;
+        self
+    }
+
+    pub fn with_extra(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.push((key.into(), value.into()));
+        self
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{MESSAGE_PREFIX}")?;
+        writeln!(f, "version: {MAJOR}.{}", self.minor)?;
+        writeln!(f, "chain_id: {}", self.chain_id)?;
+        writeln!(f, "domain: {}", self.domain)?;
+        writeln!(f, "expires: {}", self.expires)?;
+        writeln!(f, "session_key: {}", self.session_key)?;
+        match &self.tokens {
+            Tokens::All => write!(f, "tokens: {UNLIMITED_TOKEN_PERMISSIONS_VALUE}")?,
+            Tokens::Specific(token_limits) if token_limits.is_empty() => {
+                write!(f, "tokens: {TOKENLESS_PERMISSIONS_VALUE}")?
+            }
+            Tokens::Specific(token_limits) => {
+                write!(f, "tokens:")?;
+                for TokenLimit {
+                    token,
+                    amount,
+                    period,
+                } in token_limits
+                {
+                    write!(f, "\n-{token}: {amount}")?;
+                    if let Some(period) = period {
+                        write!(f, "{RATE_LIMIT_SEPARATOR}{period}")?;
+                    }
+                }
+            }
+        }
+        if self.domain_scoped {
+            write!(f, "\nprograms: {DOMAIN_PROGRAMS_VALUE}")?;
+        }
+        for (key, value) in &self.extra {
+            write!(f, "\n{key}: {value}")?;
+        }
+        Ok(())
+    }
+}
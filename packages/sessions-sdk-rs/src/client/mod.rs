@@ -0,0 +1,245 @@
+//! Builders for the instructions of the session manager program, meant to be used by off-chain code creating and managing sessions on behalf of users.
+
+use crate::domain_registry::get_domain_record_address;
+use crate::session::user_sessions::get_user_sessions_address;
+use crate::session::{SESSION_MANAGER_ID, SESSION_SETTER};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+mod ed25519;
+pub mod message;
+
+pub use ed25519::ed25519_instruction;
+
+/// The legacy token program
+pub const SPL_TOKEN_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xPWC5ST5VAF8L8knL");
+const TOKEN_METADATA_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+const CHAIN_ID_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("Cha1RcWkdcF1dmGuTui53JmSnVCacCc2Kx2SY7zSFhaN");
+const CHAIN_ID_SEED: &[u8] = b"chain_id";
+
+const START_SESSION_DISCRIMINATOR: u8 = 0;
+const REVOKE_SESSION_DISCRIMINATOR: u8 = 1;
+const CLOSE_SESSION_DISCRIMINATOR: u8 = 2;
+const ROTATE_SESSION_KEY_DISCRIMINATOR: u8 = 8;
+
+/// How a token is designated in the tokens section of the intent, together with the mint it resolves to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolOrMint {
+    /// The token is designated by the symbol in its metadata account, e.g. `-USDC: 10`
+    Symbol(Pubkey),
+    /// The token is designated by its mint address, e.g. `-EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v: 10`
+    Mint(Pubkey),
+}
+
+impl SymbolOrMint {
+    pub fn mint(&self) -> &Pubkey {
+        match self {
+            SymbolOrMint::Symbol(mint) | SymbolOrMint::Mint(mint) => mint,
+        }
+    }
+}
+
+/// A token in the tokens section of the intent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionToken {
+    pub symbol_or_mint: SymbolOrMint,
+    /// The token program that owns the mint, either the legacy token program or Token-2022
+    pub token_program: Pubkey,
+}
+
+/// Returns the associated token account of `user` for `mint`
+pub fn get_associated_token_address(
+    user: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[user.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+fn get_metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// The session manager uses the legacy token program as its main token program, Token-2022 needs to be appended to the remaining accounts if any token uses it
+fn other_token_programs<'a>(token_programs: impl Iterator<Item = &'a Pubkey>) -> Vec<AccountMeta> {
+    let mut other_token_programs: Vec<Pubkey> = vec![];
+    for token_program in token_programs {
+        if *token_program != SPL_TOKEN_PROGRAM_ID && !other_token_programs.contains(token_program) {
+            other_token_programs.push(*token_program);
+        }
+    }
+    other_token_programs
+        .into_iter()
+        .map(|token_program| AccountMeta::new_readonly(token_program, false))
+        .collect()
+}
+
+/// The remaining accounts of each token of the tokens section of an intent: the user associated token account, the mint and, if the token is designated by its symbol, its metadata account
+fn token_accounts(user: &Pubkey, tokens: &[SessionToken]) -> Vec<AccountMeta> {
+    let mut accounts = vec![];
+    for token in tokens {
+        let mint = token.symbol_or_mint.mint();
+        accounts.push(AccountMeta::new(
+            get_associated_token_address(user, mint, &token.token_program),
+            false,
+        ));
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+        if let SymbolOrMint::Symbol(_) = token.symbol_or_mint {
+            accounts.push(AccountMeta::new_readonly(get_metadata_address(mint), false));
+        }
+    }
+    accounts
+}
+
+/// Builds the instructions to start a session: the ed25519 instruction verifying the intent and the `start_session` instruction, in this order.
+/// `message` is the intent signed by `user` and `signature` its signature. `domain` and `tokens` need to match the domain and the tokens section of the intent, in the same order.
+/// The transaction needs to be signed by `sponsor` and `session`.
+pub fn start_session(
+    sponsor: &Pubkey,
+    session: &Pubkey,
+    user: &Pubkey,
+    domain: &str,
+    tokens: &[SessionToken],
+    message: &[u8],
+    signature: &[u8; 64],
+) -> Vec<Instruction> {
+    let mut accounts = vec![
+        AccountMeta::new(*sponsor, true),
+        AccountMeta::new_readonly(
+            Pubkey::find_program_address(&[CHAIN_ID_SEED], &CHAIN_ID_PROGRAM_ID).0,
+            false,
+        ),
+        AccountMeta::new(*session, true),
+        AccountMeta::new_readonly(sysvar::instructions::ID, false),
+        AccountMeta::new_readonly(get_domain_record_address(domain), false),
+        AccountMeta::new_readonly(SESSION_SETTER, false),
+        AccountMeta::new_readonly(SPL_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        // The user sessions account is optional, it needs to be the first remaining account
+        AccountMeta::new(get_user_sessions_address(user), false),
+    ];
+    accounts.extend(token_accounts(user, tokens));
+    accounts.extend(other_token_programs(
+        tokens.iter().map(|token| &token.token_program),
+    ));
+
+    vec![
+        ed25519_instruction(user, signature, message),
+        Instruction {
+            program_id: SESSION_MANAGER_ID,
+            accounts,
+            data: vec![START_SESSION_DISCRIMINATOR],
+        },
+    ]
+}
+
+/// Builds the instructions to move a live session to a new session key: the ed25519 instruction verifying the intent and the `rotate_session_key` instruction, in this order.
+/// `message` is an intent for `new_session` signed by `user` and `signature` its signature. `domain` and `tokens` need to match the domain and the tokens section of the intent, which designates the mints in the `authorized_tokens` section of the session account in the same order.
+/// The transaction needs to be signed by `sponsor` and `new_session`.
+#[allow(clippy::too_many_arguments)]
+pub fn rotate_session_key(
+    sponsor: &Pubkey,
+    session: &Pubkey,
+    new_session: &Pubkey,
+    user: &Pubkey,
+    domain: &str,
+    tokens: &[SessionToken],
+    message: &[u8],
+    signature: &[u8; 64],
+) -> Vec<Instruction> {
+    let mut accounts = vec![
+        AccountMeta::new(*sponsor, true),
+        AccountMeta::new_readonly(
+            Pubkey::find_program_address(&[CHAIN_ID_SEED], &CHAIN_ID_PROGRAM_ID).0,
+            false,
+        ),
+        AccountMeta::new(*session, false),
+        AccountMeta::new(*new_session, true),
+        AccountMeta::new(get_user_sessions_address(user), false),
+        AccountMeta::new_readonly(sysvar::instructions::ID, false),
+        AccountMeta::new_readonly(get_domain_record_address(domain), false),
+        AccountMeta::new_readonly(SESSION_SETTER, false),
+        AccountMeta::new_readonly(SPL_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+    accounts.extend(token_accounts(user, tokens));
+    accounts.extend(other_token_programs(
+        tokens.iter().map(|token| &token.token_program),
+    ));
+
+    vec![
+        ed25519_instruction(user, signature, message),
+        Instruction {
+            program_id: SESSION_MANAGER_ID,
+            accounts,
+            data: vec![ROTATE_SESSION_KEY_DISCRIMINATOR],
+        },
+    ]
+}
+
+/// Builds the instruction to revoke a session, the transaction needs to be signed by the session key
+pub fn revoke_session(session: &Pubkey, sponsor: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: SESSION_MANAGER_ID,
+        accounts: vec![
+            AccountMeta::new(*session, true),
+            AccountMeta::new(*sponsor, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: vec![REVOKE_SESSION_DISCRIMINATOR],
+    }
+}
+
+/// Builds the instruction to close an expired session and refund its rent to `sponsor`.
+/// `tokens` are the mints in the `authorized_tokens` section of the session account, in the same order, together with the token program that owns them.
+pub fn close_session(
+    session: &Pubkey,
+    sponsor: &Pubkey,
+    user: &Pubkey,
+    tokens: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*session, false),
+        AccountMeta::new(*sponsor, false),
+        AccountMeta::new_readonly(SESSION_SETTER, false),
+        AccountMeta::new_readonly(SPL_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        // The user sessions account is optional, it needs to be the first remaining account
+        AccountMeta::new(get_user_sessions_address(user), false),
+    ];
+    accounts.extend(tokens.iter().map(|(mint, token_program)| {
+        AccountMeta::new(
+            get_associated_token_address(user, mint, token_program),
+            false,
+        )
+    }));
+    accounts.extend(other_token_programs(
+        tokens.iter().map(|(_, token_program)| token_program),
+    ));
+
+    Instruction {
+        program_id: SESSION_MANAGER_ID,
+        accounts,
+        data: vec![CLOSE_SESSION_DISCRIMINATOR],
+    }
+}
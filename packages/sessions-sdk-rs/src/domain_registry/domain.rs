@@ -0,0 +1,125 @@
+use thiserror::Error;
+
+/// The longest host allowed in a domain, as in DNS
+const MAX_HOST_LEN: usize = 253;
+/// The longest label allowed in the host of a domain, as in DNS
+const MAX_LABEL_LEN: usize = 63;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum DomainError {
+    #[error("The domain must start with http:// or https://")]
+    UnsupportedScheme,
+    #[error("The domain can't contain credentials, a path, a query or a fragment")]
+    UnexpectedComponent,
+    #[error("The host of the domain must be a lowercase ASCII hostname, internationalized hostnames need to be punycode-encoded")]
+    InvalidHost,
+    #[error("The port of the domain must be a number between 1 and 65535")]
+    InvalidPort,
+}
+
+/// Checks that `domain` is a web origin (`scheme://host[:port]`) and returns its canonical form, which identifies its domain record.
+/// The canonical form has a lowercase scheme and host, no trailing slash and no port if it's the default port of the scheme, like the origin of a web page.
+/// Hosts need to be ASCII, so internationalized domain names need to be punycode-encoded. IPv6 hosts aren't supported.
+pub fn normalize_domain(domain: &str) -> Result<String, DomainError> {
+    let (scheme, rest) = domain
+        .split_once("://")
+        .ok_or(DomainError::UnsupportedScheme)?;
+    let scheme = scheme.to_ascii_lowercase();
+    let default_port = match scheme.as_str() {
+        "https" => 443,
+        "http" => 80,
+        _ => return Err(DomainError::UnsupportedScheme),
+    };
+
+    let authority = rest.strip_suffix('/').unwrap_or(rest);
+    if authority.contains(['/', '?', '#', '@']) {
+        return Err(DomainError::UnexpectedComponent);
+    }
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, Some(parse_port(port)?)),
+        None => (authority, None),
+    };
+    let host = host.to_ascii_lowercase();
+    if !is_valid_host(&host) {
+        return Err(DomainError::InvalidHost);
+    }
+
+    Ok(match port {
+        Some(port) if port != default_port => format!("{scheme}://{host}:{port}"),
+        _ => format!("{scheme}://{host}"),
+    })
+}
+
+fn parse_port(port: &str) -> Result<u16, DomainError> {
+    if port.is_empty() || !port.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(DomainError::InvalidPort);
+    }
+    port.parse::<u16>()
+        .ok()
+        .filter(|port| *port != 0)
+        .ok_or(DomainError::InvalidPort)
+}
+
+fn is_valid_host(host: &str) -> bool {
+    host.len() <= MAX_HOST_LEN
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= MAX_LABEL_LEN
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_domain() {
+        for (domain, normalized) in [
+            ("https://app.xyz", "https://app.xyz"),
+            ("https://app.xyz/", "https://app.xyz"),
+            ("HTTPS://App.XYZ", "https://app.xyz"),
+            ("https://app.xyz:443", "https://app.xyz"),
+            ("https://app.xyz:8443", "https://app.xyz:8443"),
+            ("http://localhost:3000", "http://localhost:3000"),
+            ("http://localhost:80/", "http://localhost"),
+            ("http://127.0.0.1:8080", "http://127.0.0.1:8080"),
+            ("https://xn--mnchen-3ya.de", "https://xn--mnchen-3ya.de"),
+        ] {
+            assert_eq!(normalize_domain(domain), Ok(normalized.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_normalize_invalid_domain() {
+        for (domain, error) in [
+            ("app.xyz", DomainError::UnsupportedScheme),
+            ("ftp://app.xyz", DomainError::UnsupportedScheme),
+            (" https://app.xyz", DomainError::UnsupportedScheme),
+            ("https://app.xyz/path", DomainError::UnexpectedComponent),
+            ("https://app.xyz//", DomainError::UnexpectedComponent),
+            ("https://app.xyz?query", DomainError::UnexpectedComponent),
+            ("https://app.xyz#fragment", DomainError::UnexpectedComponent),
+            ("https://user@app.xyz", DomainError::UnexpectedComponent),
+            ("https://", DomainError::InvalidHost),
+            ("https://app .xyz", DomainError::InvalidHost),
+            ("https://app.xyz ", DomainError::InvalidHost),
+            ("https://app.xyz.", DomainError::InvalidHost),
+            ("https://-app.xyz", DomainError::InvalidHost),
+            ("https://app_1.xyz", DomainError::InvalidHost),
+            ("https://münchen.de", DomainError::InvalidHost),
+            ("https://[::1]:3000", DomainError::InvalidHost),
+            ("https://app.xyz:", DomainError::InvalidPort),
+            ("https://app.xyz:0", DomainError::InvalidPort),
+            ("https://app.xyz:65536", DomainError::InvalidPort),
+            ("https://app.xyz:+80", DomainError::InvalidPort),
+            ("https://app.xyz:80:80", DomainError::InvalidHost),
+        ] {
+            assert_eq!(normalize_domain(domain), Err(error), "{domain}");
+        }
+    }
+}
@@ -1,13 +1,125 @@
+use crate::domain_registry::domain::normalize_domain;
+use crate::error::SessionError;
+use crate::session::{AuthorizedProgram, DomainHash};
+use solana_program::account_info::AccountInfo;
 use solana_program::hash::hashv;
 use solana_program::pubkey::Pubkey;
+use std::io::{Error, ErrorKind, Read};
 
-const DOMAIN_REGISTRY_PROGRAM_ID: Pubkey =
+#[cfg(not(feature = "anchor"))]
+use borsh::{BorshDeserialize, BorshSerialize};
+
+#[cfg(feature = "anchor")]
+use anchor_lang::prelude::{
+    AnchorDeserialize as BorshDeserialize, AnchorSerialize as BorshSerialize,
+};
+
+pub mod domain;
+
+/// The program ID of the domain registry program
+pub const DOMAIN_REGISTRY_PROGRAM_ID: Pubkey =
     solana_program::pubkey!("DomaLfEueNY6JrQSEFjuXeUDiohFmSrFeTNTPamS2yog");
 const DOMAIN_RECORD_SEED: &[u8] = b"domain-record";
+const CONFIG_SEED: &[u8] = b"config";
+const PROGRAM_DOMAINS_SEED: &[u8] = b"program-domains";
+/// The size of a `DomainProgram` entry of a domain record: the program ID followed by its signer PDA
+const DOMAIN_PROGRAM_LEN: usize = 64;
+
+/// Domain records with a header start with this discriminator, followed by the length of the header as a little-endian `u32`, the borsh-serialized `DomainRecordHeader` and the `DomainProgram` entries.
+/// Domain records created before headers existed are a bare array of `DomainProgram` entries.
+pub const DOMAIN_RECORD_HEADER_DISCRIMINATOR: [u8; 8] = [92, 194, 234, 129, 71, 32, 52, 179];
+
+/// The settings of a domain, stored in the header of its domain record.
+/// New fields are only appended and must decode from zero bytes to their default value, headers written before a field existed are padded with zeros when decoded.
+#[derive(Debug, Clone, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct DomainRecordHeader {
+    /// The minimum duration of the sessions of this domain, in seconds
+    pub min_session_duration: Option<i64>,
+    /// The maximum duration of the sessions of this domain, in seconds. The session manager's global maximum still applies.
+    pub max_session_duration: Option<i64>,
+    /// The key that can manage the programs and settings of this domain, in addition to the registry authority
+    pub admin: Option<Pubkey>,
+    /// The key proposed as the next admin of this domain, it becomes the admin once it accepts
+    pub pending_admin: Option<Pubkey>,
+    /// What wallets can show about the app of this domain, provided by the domain admin or the registry authority
+    pub metadata: Option<DomainMetadata>,
+    /// Whether the registry authority has checked `metadata`, changing the metadata clears it unless the registry authority does it
+    pub verified: bool,
+    /// The programs that were removed from this domain. Sessions that authorized them before their removal can't use them anymore, unless they are added back.
+    pub removed_programs: Vec<Pubkey>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct DomainMetadata {
+    /// The name of the app
+    pub display_name: String,
+    /// The URI of the icon of the app
+    pub icon_uri: Option<String>,
+    /// How to reach the team behind the app, for example an email address
+    pub contact: Option<String>,
+}
+
+impl DomainRecordHeader {
+    /// Returns the metadata of the domain only if the registry authority has verified it
+    pub fn verified_metadata(&self) -> Option<&DomainMetadata> {
+        self.metadata.as_ref().filter(|_| self.verified)
+    }
 
-fn get_seeds(domain_name: &str) -> Vec<Vec<u8>> {
-    let hash = hashv(&[domain_name.as_bytes()]);
-    let seeds = [DOMAIN_RECORD_SEED, hash.as_ref()];
+    /// Returns whether `program_id` was removed from this domain since it was last added
+    pub fn is_program_removed(&self, program_id: &Pubkey) -> bool {
+        self.removed_programs.contains(program_id)
+    }
+}
+
+impl DomainRecordHeader {
+    /// Returns the bytes that precede the `DomainProgram` entries in a domain record with this header
+    pub fn to_prefix(&self) -> Vec<u8> {
+        let mut header = vec![];
+        self.serialize(&mut header)
+            .expect("Serializing to a vector doesn't fail");
+        let mut prefix = DOMAIN_RECORD_HEADER_DISCRIMINATOR.to_vec();
+        prefix.extend(
+            u32::try_from(header.len())
+                .expect("The header is smaller than an account")
+                .to_le_bytes(),
+        );
+        prefix.extend(header);
+        prefix
+    }
+}
+
+/// Splits the data of a domain record into its header and the bytes of its `DomainProgram` entries. Domain records without a header have the default header.
+pub fn split_domain_record(data: &[u8]) -> std::io::Result<(DomainRecordHeader, &[u8])> {
+    let Some(rest) = data.strip_prefix(DOMAIN_RECORD_HEADER_DISCRIMINATOR.as_slice()) else {
+        return Ok((DomainRecordHeader::default(), data));
+    };
+    let header_len = rest
+        .get(..4)
+        .and_then(|header_len| header_len.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or_else(|| Error::from(ErrorKind::UnexpectedEof))?;
+    let header_len =
+        usize::try_from(header_len).map_err(|_| Error::from(ErrorKind::InvalidData))?;
+    let rest = rest.get(4..).unwrap_or_default();
+    let (Some(header), Some(programs)) = (rest.get(..header_len), rest.get(header_len..)) else {
+        return Err(ErrorKind::UnexpectedEof.into());
+    };
+    let header = DomainRecordHeader::deserialize_reader(&mut header.chain(std::io::repeat(0)))?;
+    Ok((header, programs))
+}
+
+/// Returns the hash of `domain_name` that sessions store and PDAs of the domain are derived from, after normalizing it like the domain registry does.
+/// Domains that can't be normalized are hashed as they are.
+pub fn get_domain_hash(domain_name: &str) -> DomainHash {
+    match normalize_domain(domain_name) {
+        Ok(domain_name) => hashv(&[domain_name.as_bytes()]),
+        Err(_) => hashv(&[domain_name.as_bytes()]),
+    }
+    .to_bytes()
+}
+
+fn get_seeds(domain_hash: &DomainHash) -> Vec<Vec<u8>> {
+    let seeds = [DOMAIN_RECORD_SEED, domain_hash.as_ref()];
     let bump = Pubkey::find_program_address(&seeds, &DOMAIN_REGISTRY_PROGRAM_ID).1;
     let mut result = vec![];
     result.extend(seeds.iter().map(|seed| seed.to_vec()));
@@ -15,8 +127,10 @@ fn get_seeds(domain_name: &str) -> Vec<Vec<u8>> {
     result
 }
 
+/// Returns the address of the domain record of `domain_name`, after normalizing it like the domain registry does.
+/// Domains that can't be normalized are hashed as they are, the domain registry never creates records for them.
 pub fn get_domain_record_address(domain_name: &str) -> Pubkey {
-    let seeds = get_seeds(domain_name);
+    let seeds = get_seeds(&get_domain_hash(domain_name));
     Pubkey::create_program_address(
         &seeds
             .iter()
@@ -26,3 +140,379 @@ pub fn get_domain_record_address(domain_name: &str) -> Pubkey {
     )
     .expect("We pre-computed the bump so this address should be off-curve")
 }
+
+/// Returns the address of the domain record of the domain whose hash is `domain_hash`, as stored in sessions
+pub fn get_domain_record_address_from_hash(domain_hash: &DomainHash) -> Pubkey {
+    Pubkey::find_program_address(
+        &[DOMAIN_RECORD_SEED, domain_hash],
+        &DOMAIN_REGISTRY_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Returns the header of `domain_record`, or `None` if the domain record hasn't been created yet or can't be decoded.
+/// This doesn't check the address of `domain_record`.
+pub fn get_domain_record_header(domain_record: &AccountInfo) -> Option<DomainRecordHeader> {
+    if domain_record.owner != &DOMAIN_REGISTRY_PROGRAM_ID {
+        return None;
+    }
+    split_domain_record(&domain_record.data.borrow())
+        .ok()
+        .map(|(header, _)| header)
+}
+
+/// Returns the programs currently registered in `domain_record`. Domain records that haven't been created yet or can't be decoded don't have any programs.
+/// This doesn't check the address of `domain_record`.
+pub fn get_domain_programs(domain_record: &AccountInfo) -> Vec<AuthorizedProgram> {
+    if domain_record.owner != &DOMAIN_REGISTRY_PROGRAM_ID {
+        return vec![];
+    }
+    DomainRecord::try_deserialize(&domain_record.data.borrow())
+        .map(|domain_record| domain_record.programs)
+        .unwrap_or_default()
+}
+
+/// Returns whether `program_id` is currently registered in `domain_record`.
+/// This doesn't check the address of `domain_record`.
+pub fn is_program_registered(domain_record: &AccountInfo, program_id: &Pubkey) -> bool {
+    get_domain_programs(domain_record)
+        .iter()
+        .any(|program| program.program_id == *program_id)
+}
+
+/// The decoded contents of a domain record
+#[derive(Debug, Clone, Default)]
+pub struct DomainRecord {
+    pub header: DomainRecordHeader,
+    /// The programs registered for the domain, in the order of the domain record
+    pub programs: Vec<AuthorizedProgram>,
+}
+
+impl DomainRecord {
+    /// Tries to deserialize a domain record. This should only be used after checking that the account is owned by the domain registry program.
+    pub fn try_deserialize(data: &[u8]) -> Result<Self, SessionError> {
+        let (header, programs) =
+            split_domain_record(data).map_err(|_| SessionError::InvalidAccountData)?;
+        let programs = programs.chunks_exact(DOMAIN_PROGRAM_LEN);
+        if !programs.remainder().is_empty() {
+            return Err(SessionError::InvalidAccountData);
+        }
+        Ok(Self {
+            header,
+            programs: programs
+                .map(|domain_program| {
+                    let (program_id, signer_pda) = domain_program.split_at(DOMAIN_PROGRAM_LEN / 2);
+                    AuthorizedProgram {
+                        program_id: Pubkey::new_from_array(
+                            program_id
+                                .try_into()
+                                .expect("program_id is a 32-byte slice"),
+                        ),
+                        signer_pda: Pubkey::new_from_array(
+                            signer_pda
+                                .try_into()
+                                .expect("signer_pda is a 32-byte slice"),
+                        ),
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    pub fn is_program_registered(&self, program_id: &Pubkey) -> bool {
+        self.programs
+            .iter()
+            .any(|program| program.program_id == *program_id)
+    }
+}
+
+/// Returns the address of the account that indexes the domains whose domain record contains `program_id`
+pub fn get_program_domains_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[PROGRAM_DOMAINS_SEED, program_id.as_ref()],
+        &DOMAIN_REGISTRY_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Decodes the account at `get_program_domains_address`, a bare array of the hashes of the domains of a program, in no particular order.
+/// This should only be used after checking that the account is owned by the domain registry program.
+/// Programs added to a domain before the index existed are only indexed under it once `index_program` is called.
+pub fn deserialize_program_domains(data: &[u8]) -> Result<Vec<DomainHash>, SessionError> {
+    let domain_hashes = data.chunks_exact(std::mem::size_of::<DomainHash>());
+    if !domain_hashes.remainder().is_empty() {
+        return Err(SessionError::InvalidAccountData);
+    }
+    Ok(domain_hashes
+        .map(|domain_hash| {
+            domain_hash
+                .try_into()
+                .expect("domain_hash is a 32-byte slice")
+        })
+        .collect())
+}
+
+/// Returns the address of the config account of the domain registry
+pub fn get_config_address() -> Pubkey {
+    Pubkey::find_program_address(&[CONFIG_SEED], &DOMAIN_REGISTRY_PROGRAM_ID).0
+}
+
+/// The config account of the domain registry
+#[derive(Debug, Clone, PartialEq, BorshDeserialize)]
+pub struct Config {
+    /// The key that manages the registry
+    pub authority: Pubkey,
+    /// The key proposed as the next authority, it becomes the authority once it accepts
+    pub pending_authority: Option<Pubkey>,
+}
+
+impl Config {
+    const DISCRIMINATOR: [u8; 8] = [155, 12, 170, 224, 30, 250, 204, 130];
+
+    /// Tries to deserialize the config account. This should only be used after checking that the account is owned by the domain registry program.
+    /// Config accounts created before `pending_authority` existed don't have a pending authority.
+    pub fn try_deserialize(data: &[u8]) -> Result<Self, SessionError> {
+        let config = data
+            .strip_prefix(Self::DISCRIMINATOR.as_slice())
+            .ok_or(SessionError::InvalidAccountDiscriminator)?;
+        Config::deserialize_reader(&mut config.chain(std::io::repeat(0)))
+            .map_err(|_| SessionError::InvalidAccountData)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_domain_programs() {
+        let domain = "https://app.xyz";
+        let address = get_domain_record_address(domain);
+        assert_eq!(
+            get_domain_record_address_from_hash(&hashv(&[domain.as_bytes()]).to_bytes()),
+            address
+        );
+        assert_eq!(get_domain_record_address("HTTPS://App.xyz/"), address);
+        assert_eq!(
+            get_domain_hash("HTTPS://App.xyz/"),
+            hashv(&[domain.as_bytes()]).to_bytes()
+        );
+
+        let programs = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut data = programs
+            .iter()
+            .flat_map(|program_id| [program_id.to_bytes(), Pubkey::new_unique().to_bytes()])
+            .flatten()
+            .collect::<Vec<u8>>();
+        let mut lamports = 0;
+        let domain_record = AccountInfo::new(
+            &address,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &DOMAIN_REGISTRY_PROGRAM_ID,
+            false,
+            0,
+        );
+        assert!(get_domain_programs(&domain_record)
+            .iter()
+            .map(|program| program.program_id)
+            .eq(programs));
+
+        let not_created = AccountInfo {
+            owner: &solana_program::system_program::ID,
+            ..domain_record
+        };
+        assert!(get_domain_programs(&not_created).is_empty());
+    }
+
+    #[test]
+    fn test_split_domain_record() {
+        let programs = [7u8; 2 * DOMAIN_PROGRAM_LEN];
+        assert_eq!(
+            split_domain_record(&programs).unwrap(),
+            (DomainRecordHeader::default(), programs.as_slice())
+        );
+
+        let header = DomainRecordHeader {
+            min_session_duration: Some(60),
+            max_session_duration: Some(3600),
+            admin: Some(Pubkey::new_unique()),
+            pending_admin: Some(Pubkey::new_unique()),
+            metadata: Some(DomainMetadata {
+                display_name: "App".to_string(),
+                icon_uri: Some("https://app.xyz/icon.png".to_string()),
+                contact: None,
+            }),
+            verified: true,
+            removed_programs: vec![Pubkey::new_unique()],
+        };
+        let data = [header.to_prefix().as_slice(), &programs].concat();
+        assert_eq!(
+            split_domain_record(&data).unwrap(),
+            (header, programs.as_slice())
+        );
+
+        // A header written before `max_session_duration` existed
+        let data = [
+            DOMAIN_RECORD_HEADER_DISCRIMINATOR.as_slice(),
+            &9u32.to_le_bytes(),
+            &[1],
+            &60i64.to_le_bytes(),
+            &programs,
+        ]
+        .concat();
+        assert_eq!(
+            split_domain_record(&data).unwrap(),
+            (
+                DomainRecordHeader {
+                    min_session_duration: Some(60),
+                    max_session_duration: None,
+                    admin: None,
+                    pending_admin: None,
+                    metadata: None,
+                    verified: false,
+                    removed_programs: vec![],
+                },
+                programs.as_slice()
+            )
+        );
+
+        let truncated = DOMAIN_RECORD_HEADER_DISCRIMINATOR
+            .iter()
+            .chain(&100u32.to_le_bytes())
+            .copied()
+            .collect::<Vec<u8>>();
+        assert!(split_domain_record(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_get_domain_record_header() {
+        let address = get_domain_record_address("https://app.xyz");
+        let metadata = DomainMetadata {
+            display_name: "App".to_string(),
+            icon_uri: None,
+            contact: Some("team@app.xyz".to_string()),
+        };
+        let mut header = DomainRecordHeader {
+            metadata: Some(metadata.clone()),
+            ..DomainRecordHeader::default()
+        };
+        let mut data = header.to_prefix();
+        let mut lamports = 0;
+        let domain_record = AccountInfo::new(
+            &address,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &DOMAIN_REGISTRY_PROGRAM_ID,
+            false,
+            0,
+        );
+        assert_eq!(
+            get_domain_record_header(&domain_record),
+            Some(header.clone())
+        );
+        assert_eq!(header.verified_metadata(), None);
+
+        header.verified = true;
+        assert_eq!(header.verified_metadata(), Some(&metadata));
+
+        let removed_program = Pubkey::new_unique();
+        header.removed_programs.push(removed_program);
+        assert!(header.is_program_removed(&removed_program));
+        assert!(!header.is_program_removed(&Pubkey::new_unique()));
+
+        let not_created = AccountInfo {
+            owner: &solana_program::system_program::ID,
+            ..domain_record
+        };
+        assert_eq!(get_domain_record_header(&not_created), None);
+    }
+
+    #[test]
+    fn test_deserialize_domain_record() {
+        let programs = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let entries = programs
+            .iter()
+            .flat_map(|program_id| [program_id.to_bytes(), Pubkey::new_unique().to_bytes()])
+            .flatten()
+            .collect::<Vec<u8>>();
+        let header = DomainRecordHeader {
+            max_session_duration: Some(3600),
+            ..DomainRecordHeader::default()
+        };
+
+        let legacy = DomainRecord::try_deserialize(&entries).unwrap();
+        assert_eq!(legacy.header, DomainRecordHeader::default());
+        assert!(legacy.is_program_registered(&programs[1]));
+
+        let domain_record =
+            DomainRecord::try_deserialize(&[header.to_prefix(), entries.clone()].concat()).unwrap();
+        assert_eq!(domain_record.header, header);
+        assert!(domain_record
+            .programs
+            .iter()
+            .map(|program| program.program_id)
+            .eq(programs));
+        assert!(!domain_record.is_program_registered(&Pubkey::new_unique()));
+
+        assert!(matches!(
+            DomainRecord::try_deserialize(entries.get(1..).unwrap()),
+            Err(SessionError::InvalidAccountData)
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_program_domains() {
+        let domain_hashes = [
+            hashv(&[b"https://app.xyz"]).to_bytes(),
+            hashv(&[b"https://other.xyz"]).to_bytes(),
+        ];
+        assert_eq!(
+            deserialize_program_domains(&domain_hashes.concat()).unwrap(),
+            domain_hashes
+        );
+        assert!(deserialize_program_domains(&[]).unwrap().is_empty());
+        assert!(matches!(
+            deserialize_program_domains(&[0; 33]),
+            Err(SessionError::InvalidAccountData)
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_config() {
+        let authority = Pubkey::new_unique();
+        let pending_authority = Pubkey::new_unique();
+        let legacy = [Config::DISCRIMINATOR.as_slice(), authority.as_ref()].concat();
+        assert_eq!(
+            Config::try_deserialize(&legacy).unwrap(),
+            Config {
+                authority,
+                pending_authority: None,
+            }
+        );
+
+        let config = [
+            Config::DISCRIMINATOR.as_slice(),
+            authority.as_ref(),
+            &[1],
+            pending_authority.as_ref(),
+        ]
+        .concat();
+        assert_eq!(
+            Config::try_deserialize(&config).unwrap(),
+            Config {
+                authority,
+                pending_authority: Some(pending_authority),
+            }
+        );
+
+        assert!(matches!(
+            Config::try_deserialize(config.get(1..).unwrap()),
+            Err(SessionError::InvalidAccountDiscriminator)
+        ));
+    }
+}
@@ -25,6 +25,10 @@ pub enum SessionError {
     Revoked,
     #[error("A session can only send rent to its user account when closing a token account")]
     TokenCloseAccountWrongDestination,
+    #[error("This session is scoped to its domain and requires the domain record account")]
+    DomainRecordRequired,
+    #[error("The domain record provided is not the domain record of the session's domain")]
+    DomainRecordMismatch,
 }
 
 impl From<SessionError> for u32 {
@@ -41,6 +45,8 @@ impl From<SessionError> for u32 {
             SessionError::LimitsExceeded => 4_000_000_008,
             SessionError::Revoked => 4_000_000_009,
             SessionError::TokenCloseAccountWrongDestination => 4_000_000_010,
+            SessionError::DomainRecordRequired => 4_000_000_011,
+            SessionError::DomainRecordMismatch => 4_000_000_012,
         }
     }
 }
@@ -1,3 +1,5 @@
+#[cfg(feature = "client")]
+pub mod client;
 pub mod domain_registry;
 pub mod error;
 pub mod intent_transfer;
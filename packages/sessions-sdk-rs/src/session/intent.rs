@@ -0,0 +1,40 @@
+//! The text of the intents parsed by the session manager program. Clients building intents should use `client::message::Message` rather than these constants directly.
+
+use std::ops::RangeInclusive;
+
+/// The first lines of the intent to start a session
+pub const MESSAGE_PREFIX: &str = "Fogo Sessions:\nSigning this intent will allow this app to interact with your on-chain balances. Please make sure you trust this app and the domain in the message matches the domain of the current web application.\n";
+/// The first lines of the intent to revoke a session with the user's wallet
+pub const REVOCATION_MESSAGE_PREFIX: &str = "Fogo Sessions:\nSigning this intent will revoke the session key below. Any app using this session key will no longer be able to interact with your on-chain balances.\n";
+/// The value of the tokens section of sessions that can spend any amount of any token
+pub const UNLIMITED_TOKEN_PERMISSIONS_VALUE: &str = "this app may spend any amount of any token";
+/// The value of the tokens section of sessions that can't spend any tokens
+pub const TOKENLESS_PERMISSIONS_VALUE: &str = "this app may not spend any tokens";
+/// The value of the optional programs section of sessions that can interact with any program registered for their domain, including programs registered after the session starts
+pub const DOMAIN_PROGRAMS_VALUE: &str =
+    "this app may interact with any program of this domain, including programs added later";
+/// The separator between the amount and the period of a rate-limited token, e.g. `-USDC: 10 per hour`
+pub const RATE_LIMIT_SEPARATOR: &str = " per ";
+/// The minor versions of the intents `start_session` can create a session for, with the same version
+pub const SUPPORTED_MINOR_VERSIONS: RangeInclusive<u8> = 1..=6;
+/// The first minor version with rate-limited tokens, earlier versions would turn them into lifetime limits
+pub const RATE_LIMITS_MINOR_VERSION: u8 = 6;
+/// The key of the optional programs section of the intent
+pub const PROGRAMS_KEY: &str = "programs";
+/// The first minor version with the programs section and domain-scoped sessions, earlier versions can use `programs` as an extra key
+pub const DOMAIN_SCOPE_MINOR_VERSION: u8 = 5;
+/// The keys of the intent that can't be used as extra keys, `programs` is only reserved from `DOMAIN_SCOPE_MINOR_VERSION` onwards, see `is_reserved_key`
+pub const RESERVED_KEYS: [&str; 7] = [
+    "version",
+    "chain_id",
+    "domain",
+    "expires",
+    "session_key",
+    "tokens",
+    PROGRAMS_KEY,
+];
+
+/// Returns whether `key` can't be used as an extra key in an intent of minor version `minor`
+pub fn is_reserved_key(key: &str, minor: u8) -> bool {
+    RESERVED_KEYS.contains(&key) && (key != PROGRAMS_KEY || minor >= DOMAIN_SCOPE_MINOR_VERSION)
+}
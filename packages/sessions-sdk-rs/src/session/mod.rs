@@ -17,6 +17,11 @@ use anchor_lang::{
     AccountDeserialize, AnchorDeserialize, AnchorSerialize, Discriminator,
 };
 
+#[cfg(not(feature = "system-program"))]
+use crate::domain_registry::{
+    get_domain_programs, get_domain_record_address_from_hash, split_domain_record,
+    DOMAIN_REGISTRY_PROGRAM_ID,
+};
 use crate::error::SessionError;
 
 #[cfg(feature = "token-program")]
@@ -25,10 +30,18 @@ pub mod token_program;
 #[cfg(feature = "system-program")]
 pub mod system_program;
 
+pub mod intent;
+pub mod rate_limit;
+pub mod user_sessions;
+
 /// The program ID of the session manager program
 pub const SESSION_MANAGER_ID: Pubkey =
     solana_program::pubkey!("SesswvJ7puvAgpyqp7N8HnjNnvpnS8447tKNF3sPgbC");
 
+/// The PDA of the session manager program that delegates the user's token accounts to session keys
+pub const SESSION_SETTER: Pubkey =
+    solana_program::pubkey!("akbpBKqNWBiZn3ejes3ejieJ5t3vqEhoq1ZzLBG7jQo");
+
 /// The current major version of the `Session` structure
 pub const MAJOR: u8 = 0;
 /// The current minor version of the `Session` structure
@@ -71,6 +84,8 @@ mod session_info {
         V2(V2),
         V3(V3),
         V4(V4),
+        V5(V5),
+        V6(V6),
     }
 }
 pub use session_info::SessionInfo;
@@ -115,6 +130,30 @@ mod v4 {
 
 pub use v4::V4;
 
+#[allow(dead_code)]
+mod v5 {
+    use super::*;
+    #[derive(Debug, Clone, BorshDeserialize, BorshSerialize, BorshSchema)]
+    pub enum V5 {
+        Revoked(RevokedSessionInfo),
+        Active(ActiveSessionInfoWithDomainHashAndNativeAllowance),
+    }
+}
+
+pub use v5::V5;
+
+#[allow(dead_code)]
+mod v6 {
+    use super::*;
+    #[derive(Debug, Clone, BorshDeserialize, BorshSerialize, BorshSchema)]
+    pub enum V6 {
+        Revoked(RevokedSessionInfo),
+        Active(ActiveSessionInfoWithRateLimits),
+    }
+}
+
+pub use v6::V6;
+
 #[derive(Debug, Clone, BorshDeserialize, BorshSerialize, BorshSchema)]
 pub struct RevokedSessionInfo {
     /// The user who started this session
@@ -156,8 +195,74 @@ impl AsRef<ActiveSessionInfo<AuthorizedTokensWithMints>> for ActiveSessionInfoWi
     }
 }
 
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct ActiveSessionInfoWithDomainHashAndNativeAllowance {
+    /// The sha256 hash of the domain name for this session
+    pub domain_hash: DomainHash,
+    /// The amount of native tokens (in lamports) the session key is allowed to move out of the user's wallet via the system program.
+    /// This field needs to come before `active_session_info` so that the system program can read and update it without deserializing the rest of the session.
+    pub native_allowance: NativeAllowance,
+    pub active_session_info: ActiveSessionInfo<AuthorizedTokensWithMints>,
+}
+
+impl AsRef<ActiveSessionInfo<AuthorizedTokensWithMints>>
+    for ActiveSessionInfoWithDomainHashAndNativeAllowance
+{
+    fn as_ref(&self) -> &ActiveSessionInfo<AuthorizedTokensWithMints> {
+        &self.active_session_info
+    }
+}
+
+#[derive(Debug, Clone, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct ActiveSessionInfoWithRateLimits {
+    /// The sha256 hash of the domain name for this session
+    pub domain_hash: DomainHash,
+    /// The amount of native tokens (in lamports) the session key is allowed to move out of the user's wallet via the system program.
+    /// This field needs to come before `active_session_info` so that the system program can read and update it without deserializing the rest of the session.
+    pub native_allowance: NativeAllowance,
+    pub active_session_info: ActiveSessionInfo<AuthorizedTokensWithMints>,
+    /// The accounting record of the tokens whose spend limit applies per period rather than for the lifetime of the session.
+    /// The system program doesn't need it, so it is skipped to keep the compute cost of deserializing a session constant.
+    #[cfg_attr(feature = "system-program", borsh_skip)]
+    pub rate_limits: Vec<RateLimit>,
+}
+
+impl AsRef<ActiveSessionInfo<AuthorizedTokensWithMints>> for ActiveSessionInfoWithRateLimits {
+    fn as_ref(&self) -> &ActiveSessionInfo<AuthorizedTokensWithMints> {
+        &self.active_session_info
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct RateLimit {
+    /// The mint of the rate-limited token
+    pub mint: Pubkey,
+    /// The amount of tokens the session key is allowed to spend in each period, this is the `delegated_amount` of the token account at the start of each period
+    pub amount_per_period: u64,
+    /// The length of a period in seconds
+    pub period: i64,
+    /// The start of the current period, the delegation can be refreshed once `current_period_start + period` has passed
+    pub current_period_start: UnixTimestamp,
+}
+
 pub type DomainHash = [u8; HASH_BYTES];
 
+///This module is a hack because the BorshSchema macro generates dead code for `NativeAllowance` in this version of borsh, but we don't want to disable dead_code globally.
+/// More info: https://github.com/near/borsh-rs/issues/111"
+#[allow(dead_code)]
+mod native_allowance {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+    pub enum NativeAllowance {
+        /// The remaining amount of lamports the session can spend
+        Limited(u64),
+        Unlimited,
+    }
+}
+
+pub use native_allowance::NativeAllowance;
+
 pub trait IsAuthorizedTokens:
     Debug + Clone + BorshDeserialize + BorshSerialize + BorshSchema
 {
@@ -173,6 +278,8 @@ mod authorized_programs {
     pub enum AuthorizedPrograms {
         Specific(Vec<AuthorizedProgram>),
         All,
+        /// The programs registered for the session's domain at the time of use, read from the domain record which callers have to provide. Only sessions with a domain hash can be domain-scoped.
+        Domain,
     }
 }
 
@@ -274,6 +381,14 @@ impl Session {
                 V4::Revoked(_) => Err(SessionError::Revoked),
                 V4::Active(session) => Ok(&session.domain_hash),
             },
+            SessionInfo::V5(session) => match session {
+                V5::Revoked(_) => Err(SessionError::Revoked),
+                V5::Active(session) => Ok(&session.domain_hash),
+            },
+            SessionInfo::V6(session) => match session {
+                V6::Revoked(_) => Err(SessionError::Revoked),
+                V6::Active(session) => Ok(&session.domain_hash),
+            },
         }
     }
 
@@ -292,6 +407,14 @@ impl Session {
                 V4::Revoked(session) => Ok(session.expiration),
                 V4::Active(session) => Ok(session.as_ref().expiration),
             },
+            SessionInfo::V5(session) => match session {
+                V5::Revoked(session) => Ok(session.expiration),
+                V5::Active(session) => Ok(session.as_ref().expiration),
+            },
+            SessionInfo::V6(session) => match session {
+                V6::Revoked(session) => Ok(session.expiration),
+                V6::Active(session) => Ok(session.as_ref().expiration),
+            },
             SessionInfo::Invalid => Err(SessionError::InvalidAccountVersion),
         }
     }
@@ -311,6 +434,14 @@ impl Session {
                 V4::Revoked(session) => Ok(&session.user),
                 V4::Active(session) => Ok(&session.as_ref().user),
             },
+            SessionInfo::V5(session) => match session {
+                V5::Revoked(session) => Ok(&session.user),
+                V5::Active(session) => Ok(&session.as_ref().user),
+            },
+            SessionInfo::V6(session) => match session {
+                V6::Revoked(session) => Ok(&session.user),
+                V6::Active(session) => Ok(&session.as_ref().user),
+            },
             SessionInfo::Invalid => Err(SessionError::InvalidAccountVersion),
         }
     }
@@ -339,10 +470,14 @@ impl Session {
             SessionInfo::V1(_)
             | SessionInfo::V2(V2::Active(_))
             | SessionInfo::V3(V3::Active(_))
-            | SessionInfo::V4(V4::Active(_)) => Ok(()),
+            | SessionInfo::V4(V4::Active(_))
+            | SessionInfo::V5(V5::Active(_))
+            | SessionInfo::V6(V6::Active(_)) => Ok(()),
             SessionInfo::V2(V2::Revoked(_))
             | SessionInfo::V3(V3::Revoked(_))
-            | SessionInfo::V4(V4::Revoked(_)) => Err(SessionError::Revoked),
+            | SessionInfo::V4(V4::Revoked(_))
+            | SessionInfo::V5(V5::Revoked(_))
+            | SessionInfo::V6(V6::Revoked(_)) => Err(SessionError::Revoked),
             SessionInfo::Invalid => Err(SessionError::InvalidAccountVersion),
         }
     }
@@ -386,6 +521,24 @@ impl Session {
         }
     }
 
+    /// Same as `extract_user_from_signer_or_session`, but also supports domain-scoped sessions by reading the programs currently registered in `domain_record`, and rejects programs that were removed from the domain
+    pub fn extract_user_from_signer_or_session_with_domain_record(
+        info: &AccountInfo,
+        program_id: &Pubkey,
+        domain_record: &AccountInfo,
+    ) -> Result<Pubkey, SessionError> {
+        if !info.is_signer {
+            return Err(SessionError::MissingRequiredSignature);
+        }
+
+        if info.owner == &SESSION_MANAGER_ID {
+            let session = Self::try_deserialize(&mut info.data.borrow_mut().as_ref())?;
+            session.get_user_checked_with_domain_record(program_id, domain_record)
+        } else {
+            Ok(*info.key)
+        }
+    }
+
     fn authorized_programs(&self) -> Result<&AuthorizedPrograms, SessionError> {
         match &self.session_info {
             SessionInfo::V1(session) => Ok(&session.authorized_programs),
@@ -401,6 +554,14 @@ impl Session {
                 V4::Revoked(_) => Err(SessionError::Revoked),
                 V4::Active(session) => Ok(&session.as_ref().authorized_programs),
             },
+            SessionInfo::V5(session) => match session {
+                V5::Revoked(_) => Err(SessionError::Revoked),
+                V5::Active(session) => Ok(&session.as_ref().authorized_programs),
+            },
+            SessionInfo::V6(session) => match session {
+                V6::Revoked(_) => Err(SessionError::Revoked),
+                V6::Active(session) => Ok(&session.as_ref().authorized_programs),
+            },
             SessionInfo::Invalid => Err(SessionError::InvalidAccountVersion),
         }
     }
@@ -420,27 +581,102 @@ impl Session {
                 V4::Revoked(_) => Err(SessionError::Revoked),
                 V4::Active(session) => Ok(&session.as_ref().extra),
             },
+            SessionInfo::V5(session) => match session {
+                V5::Revoked(_) => Err(SessionError::Revoked),
+                V5::Active(session) => Ok(&session.as_ref().extra),
+            },
+            SessionInfo::V6(session) => match session {
+                V6::Revoked(_) => Err(SessionError::Revoked),
+                V6::Active(session) => Ok(&session.as_ref().extra),
+            },
             SessionInfo::Invalid => Err(SessionError::InvalidAccountVersion),
         }
     }
 
-    fn check_authorized_program(&self, program_id: &Pubkey) -> Result<(), SessionError> {
+    fn check_authorized_program(
+        &self,
+        program_id: &Pubkey,
+        domain_record: Option<&AccountInfo>,
+    ) -> Result<(), SessionError> {
         match self.authorized_programs()? {
             AuthorizedPrograms::Specific(ref programs) => {
                 programs
                     .iter()
                     .find(|authorized_program| authorized_program.program_id == *program_id)
                     .ok_or(SessionError::UnauthorizedProgram)?;
+                if let Some(domain_record) = domain_record {
+                    self.check_program_not_removed(program_id, domain_record)?;
+                }
             }
             AuthorizedPrograms::All => {}
+            AuthorizedPrograms::Domain => {
+                let domain_record = domain_record.ok_or(SessionError::DomainRecordRequired)?;
+                get_domain_programs(self.check_domain_record(domain_record)?)
+                    .iter()
+                    .find(|authorized_program| authorized_program.program_id == *program_id)
+                    .ok_or(SessionError::UnauthorizedProgram)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sessions with a domain (V4 onwards) keep the programs of their domain at the time they started, `domain_record` is used to reject the programs that were removed from the domain since.
+    /// Domain records that haven't been created yet or don't have a header have nothing removed, but domain records whose header can't be decoded are rejected.
+    fn check_program_not_removed(
+        &self,
+        program_id: &Pubkey,
+        domain_record: &AccountInfo,
+    ) -> Result<(), SessionError> {
+        if !matches!(
+            self.session_info,
+            SessionInfo::V4(_) | SessionInfo::V5(_) | SessionInfo::V6(_)
+        ) {
+            return Ok(());
+        }
+        let domain_record = self.check_domain_record(domain_record)?;
+        if domain_record.owner != &DOMAIN_REGISTRY_PROGRAM_ID {
+            return Ok(());
+        }
+        let (header, _) = split_domain_record(&domain_record.data.borrow())
+            .map_err(|_| SessionError::InvalidAccountData)?;
+        if header.is_program_removed(program_id) {
+            return Err(SessionError::UnauthorizedProgram);
         }
         Ok(())
     }
 
+    fn domain_record_address(&self) -> Result<Pubkey, SessionError> {
+        Ok(get_domain_record_address_from_hash(self.domain_hash()?))
+    }
+
+    /// Checks that `domain_record` is the domain record of the session's domain
+    fn check_domain_record<'a, 'info>(
+        &self,
+        domain_record: &'a AccountInfo<'info>,
+    ) -> Result<&'a AccountInfo<'info>, SessionError> {
+        if *domain_record.key != self.domain_record_address()? {
+            return Err(SessionError::DomainRecordMismatch);
+        }
+        Ok(domain_record)
+    }
+
     /// This function checks that a session is live and authorized to interact with program `program_id` and returns the public key of the user who started the session
+    /// Domain-scoped sessions fail with `SessionError::DomainRecordRequired`, since checking them needs the domain record, use `get_user_checked_with_domain_record` to support them.
     pub fn get_user_checked(&self, program_id: &Pubkey) -> Result<Pubkey, SessionError> {
         self.check_is_live_and_unrevoked()?;
-        self.check_authorized_program(program_id)?;
+        self.check_authorized_program(program_id, None)?;
+        Ok(*self.user()?)
+    }
+
+    /// Same as `get_user_checked`, but domain-scoped sessions are authorized against the programs currently registered in `domain_record`, which must be the domain record of the session's domain.
+    /// Programs that were removed from the domain after the session started are rejected too.
+    pub fn get_user_checked_with_domain_record(
+        &self,
+        program_id: &Pubkey,
+        domain_record: &AccountInfo,
+    ) -> Result<Pubkey, SessionError> {
+        self.check_is_live_and_unrevoked()?;
+        self.check_authorized_program(program_id, Some(domain_record))?;
         Ok(*self.user()?)
     }
 
@@ -450,3 +686,237 @@ impl Session {
         Ok(self.extra()?.get(key))
     }
 }
+
+#[cfg(all(test, not(feature = "system-program")))]
+pub(crate) mod tests {
+    use super::*;
+    use crate::domain_registry::{
+        get_domain_hash, get_domain_record_address, DomainRecordHeader,
+        DOMAIN_RECORD_HEADER_DISCRIMINATOR,
+    };
+    use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+    use std::sync::Once;
+
+    pub const DOMAIN: &str = "https://app.xyz";
+
+    struct ClockStubs;
+
+    impl SyscallStubs for ClockStubs {
+        #[allow(unsafe_code)]
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            // Safety: `Clock::get` passes a pointer to a `Clock`
+            unsafe { std::ptr::write_unaligned(var_addr.cast::<Clock>(), Clock::default()) };
+            solana_program::entrypoint::SUCCESS
+        }
+    }
+
+    /// Makes `Clock::get` return the default clock, which is not available outside of the runtime otherwise
+    pub fn set_default_clock() {
+        static SET_DEFAULT_CLOCK: Once = Once::new();
+        SET_DEFAULT_CLOCK.call_once(|| {
+            set_syscall_stubs(Box::new(ClockStubs));
+        });
+    }
+
+    /// A live V4 session of `user` for `DOMAIN`
+    pub fn v4_session(user: Pubkey, authorized_programs: AuthorizedPrograms) -> Session {
+        Session {
+            #[cfg(not(feature = "anchor"))]
+            discriminator: Session::DISCRIMINATOR,
+            sponsor: Pubkey::new_unique(),
+            major: 0,
+            session_info: SessionInfo::V4(V4::Active(ActiveSessionInfoWithDomainHash {
+                domain_hash: get_domain_hash(DOMAIN),
+                active_session_info: ActiveSessionInfo {
+                    user,
+                    expiration: 100,
+                    authorized_programs,
+                    authorized_tokens: AuthorizedTokensWithMints::Specific(vec![]),
+                    extra: HashMap::new().into(),
+                },
+            })),
+        }
+    }
+
+    /// A live V5 session of `user` for `DOMAIN`, the first version that can be domain-scoped
+    pub fn v5_session(user: Pubkey, authorized_programs: AuthorizedPrograms) -> Session {
+        Session {
+            #[cfg(not(feature = "anchor"))]
+            discriminator: Session::DISCRIMINATOR,
+            sponsor: Pubkey::new_unique(),
+            major: 0,
+            session_info: SessionInfo::V5(V5::Active(
+                ActiveSessionInfoWithDomainHashAndNativeAllowance {
+                    domain_hash: get_domain_hash(DOMAIN),
+                    native_allowance: NativeAllowance::Unlimited,
+                    active_session_info: ActiveSessionInfo {
+                        user,
+                        expiration: 100,
+                        authorized_programs,
+                        authorized_tokens: AuthorizedTokensWithMints::Specific(vec![]),
+                        extra: HashMap::new().into(),
+                    },
+                },
+            )),
+        }
+    }
+
+    pub fn authorized_program() -> AuthorizedProgram {
+        AuthorizedProgram {
+            program_id: Pubkey::new_unique(),
+            signer_pda: Pubkey::new_unique(),
+        }
+    }
+
+    /// The data of a domain record with `header` and `programs`
+    pub fn domain_record_data(
+        header: &DomainRecordHeader,
+        programs: &[AuthorizedProgram],
+    ) -> Vec<u8> {
+        let mut data = header.to_prefix();
+        for program in programs {
+            data.extend(program.program_id.to_bytes());
+            data.extend(program.signer_pda.to_bytes());
+        }
+        data
+    }
+
+    pub fn account<'a>(
+        key: &'a Pubkey,
+        is_signer: bool,
+        owner: &'a Pubkey,
+        lamports: &'a mut u64,
+        data: &'a mut [u8],
+    ) -> AccountInfo<'a> {
+        AccountInfo::new(key, is_signer, false, lamports, data, owner, false, 0)
+    }
+
+    #[test]
+    fn test_get_user_checked_rejects_removed_programs() {
+        set_default_clock();
+        let user = Pubkey::new_unique();
+        let program = authorized_program();
+        let session = v4_session(user, AuthorizedPrograms::Specific(vec![program.clone()]));
+        let address = get_domain_record_address(DOMAIN);
+        let (mut lamports, mut removed_lamports) = (0, 0);
+        let mut data = domain_record_data(&DomainRecordHeader::default(), &[]);
+        let mut removed_data = domain_record_data(
+            &DomainRecordHeader {
+                removed_programs: vec![program.program_id],
+                ..DomainRecordHeader::default()
+            },
+            &[],
+        );
+        let domain_record = account(
+            &address,
+            false,
+            &DOMAIN_REGISTRY_PROGRAM_ID,
+            &mut lamports,
+            &mut data,
+        );
+        let removed_domain_record = account(
+            &address,
+            false,
+            &DOMAIN_REGISTRY_PROGRAM_ID,
+            &mut removed_lamports,
+            &mut removed_data,
+        );
+
+        assert_eq!(
+            session
+                .get_user_checked_with_domain_record(&program.program_id, &domain_record)
+                .unwrap(),
+            user
+        );
+        assert!(matches!(
+            session.get_user_checked_with_domain_record(&Pubkey::new_unique(), &domain_record),
+            Err(SessionError::UnauthorizedProgram)
+        ));
+        assert!(matches!(
+            session
+                .get_user_checked_with_domain_record(&program.program_id, &removed_domain_record),
+            Err(SessionError::UnauthorizedProgram)
+        ));
+        let other_address = get_domain_record_address("https://other.xyz");
+        assert!(matches!(
+            session.get_user_checked_with_domain_record(
+                &program.program_id,
+                &AccountInfo {
+                    key: &other_address,
+                    ..domain_record
+                }
+            ),
+            Err(SessionError::DomainRecordMismatch)
+        ));
+        // Domain records whose header can't be decoded don't let removed programs through
+        let mut corrupt_lamports = 0;
+        let mut corrupt_data = [
+            DOMAIN_RECORD_HEADER_DISCRIMINATOR.as_slice(),
+            &100u32.to_le_bytes(),
+        ]
+        .concat();
+        let corrupt_domain_record = account(
+            &address,
+            false,
+            &DOMAIN_REGISTRY_PROGRAM_ID,
+            &mut corrupt_lamports,
+            &mut corrupt_data,
+        );
+        assert!(matches!(
+            session
+                .get_user_checked_with_domain_record(&program.program_id, &corrupt_domain_record),
+            Err(SessionError::InvalidAccountData)
+        ));
+        // Domain records that haven't been created yet have nothing removed
+        assert_eq!(
+            session
+                .get_user_checked_with_domain_record(
+                    &program.program_id,
+                    &AccountInfo {
+                        owner: &solana_program::system_program::ID,
+                        ..removed_domain_record.clone()
+                    }
+                )
+                .unwrap(),
+            user
+        );
+        // Without the domain record, only the programs the session authorized are checked
+        assert_eq!(session.get_user_checked(&program.program_id).unwrap(), user);
+    }
+
+    #[test]
+    fn test_get_user_checked_domain_programs() {
+        set_default_clock();
+        let user = Pubkey::new_unique();
+        let program = authorized_program();
+        let session = v5_session(user, AuthorizedPrograms::Domain);
+        let address = get_domain_record_address(DOMAIN);
+        let mut lamports = 0;
+        let mut data = domain_record_data(
+            &DomainRecordHeader::default(),
+            std::slice::from_ref(&program),
+        );
+        let domain_record = account(
+            &address,
+            false,
+            &DOMAIN_REGISTRY_PROGRAM_ID,
+            &mut lamports,
+            &mut data,
+        );
+
+        assert_eq!(
+            session
+                .get_user_checked_with_domain_record(&program.program_id, &domain_record)
+                .unwrap(),
+            user
+        );
+        assert!(matches!(
+            session.get_user_checked_with_domain_record(&Pubkey::new_unique(), &domain_record),
+            Err(SessionError::UnauthorizedProgram)
+        ));
+        assert!(matches!(
+            session.get_user_checked(&program.program_id),
+            Err(SessionError::DomainRecordRequired)
+        ));
+    }
+}
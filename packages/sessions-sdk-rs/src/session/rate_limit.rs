@@ -0,0 +1,67 @@
+use crate::session::{RateLimit, UnixTimestamp, SESSION_MANAGER_ID, SESSION_SETTER};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+impl RateLimit {
+    /// Returns the start of the period containing `now`, or `None` if the current period hasn't elapsed yet
+    pub fn next_period_start(&self, now: UnixTimestamp) -> Option<UnixTimestamp> {
+        let elapsed_periods = now
+            .checked_sub(self.current_period_start)?
+            .checked_div(self.period)?;
+        if elapsed_periods <= 0 {
+            return None;
+        }
+        self.current_period_start
+            .checked_add(elapsed_periods.checked_mul(self.period)?)
+    }
+}
+
+/// The discriminator of the session manager's `refresh_rate_limit` instruction
+const REFRESH_RATE_LIMIT_DISCRIMINATOR: u8 = 6;
+
+/// Builds the instruction that refreshes the delegation of a rate-limited token of a session.
+/// Programs that spend rate-limited tokens on behalf of a session should invoke this instruction (it requires no signers) before spending. Once the current period of the rate limit has elapsed, the session manager resets the delegation of `user_token_account` to the amount allowed per period, otherwise the instruction is a no-op.
+/// `user_token_account` is the associated token account of the user for `mint` and `token_program_id` is the token program that owns `mint`.
+pub fn refresh_rate_limit(
+    session: &Pubkey,
+    user_token_account: &Pubkey,
+    mint: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: SESSION_MANAGER_ID,
+        accounts: vec![
+            AccountMeta::new(*session, false),
+            AccountMeta::new_readonly(SESSION_SETTER, false),
+            AccountMeta::new(*user_token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*token_program_id, false),
+        ],
+        data: vec![REFRESH_RATE_LIMIT_DISCRIMINATOR],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_limit(current_period_start: UnixTimestamp) -> RateLimit {
+        RateLimit {
+            mint: Pubkey::new_unique(),
+            amount_per_period: 10,
+            period: 3600,
+            current_period_start,
+        }
+    }
+
+    #[test]
+    fn test_next_period_start() {
+        assert_eq!(rate_limit(1_000).next_period_start(1_000), None);
+        assert_eq!(rate_limit(1_000).next_period_start(4_599), None);
+        assert_eq!(rate_limit(1_000).next_period_start(4_600), Some(4_600));
+        assert_eq!(rate_limit(1_000).next_period_start(12_000), Some(11_800));
+        assert_eq!(rate_limit(1_000).next_period_start(999), None);
+    }
+}
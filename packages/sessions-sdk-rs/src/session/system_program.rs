@@ -1,8 +1,16 @@
 use crate::session::IsAuthorizedTokens;
+use crate::session::NativeAllowance;
 use crate::session::Session;
 use crate::session::SessionError;
+use crate::session::SessionInfo;
 use crate::session::UnixTimestamp;
+use crate::session::V2;
+use crate::session::V3;
+use crate::session::V4;
+use crate::session::V5;
+use crate::session::V6;
 use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::hash::HASH_BYTES;
 use solana_program::pubkey::Pubkey;
 use solana_program::sysvar::clock::Clock;
 
@@ -18,15 +26,79 @@ pub struct ActiveSessionInfo<T: IsAuthorizedTokens> {
     pub _phantom_data: std::marker::PhantomData<T>,
 }
 
+/// The offset of `native_allowance` in an active V5 or V6 session account: discriminator, sponsor, major, `SessionInfo` variant, `V5`/`V6` variant and domain hash
+const NATIVE_ALLOWANCE_OFFSET: usize = 8 + 32 + 1 + 1 + 1 + HASH_BYTES;
+
 impl Session {
     /// This function is meant to replace `Session::get_user_checked` in the context of the system program.
     /// It doesn't check if the session is authorized to interact with the system program, this is because a session is always authorized to interact with the system program,
     /// since all sessions can do via the system program is wrap tokens for the user.
     /// We need pass the clock as an argument here because 'Clock::get' can't be called in a native program.
+    /// This function can't decrement the session's native allowance, so it rejects sessions with a limited native allowance. The system program must use `Session::spend_native_allowance_system_program` to move lamports on behalf of those sessions.
     /// THIS FUNCTION SHOULD ONLY BE CALLED IN THE SYSTEM PROGRAM, DO NOT USE THIS FUNCTION IN SBF PROGRAMS and use `Session::extract_user_from_signer_or_session` or `Session::get_user_checked` instead.
     pub fn get_user_checked_system_program(&self, clock: &Clock) -> Result<Pubkey, SessionError> {
         self.check_is_live_and_unrevoked_with_clock(clock)?;
-        Ok(*self.user()?)
+        match self.native_allowance()? {
+            NativeAllowance::Limited(_) => Err(SessionError::LimitsExceeded),
+            NativeAllowance::Unlimited => Ok(*self.user()?),
+        }
+    }
+
+    /// This function is meant to be called by the system program when a session moves `lamports` out of the user's wallet.
+    /// The system program lives outside of this repository, so the native allowance is only enforced as long as it calls this function rather than `Session::get_user_checked_system_program`.
+    /// It checks that the session is live and that its native allowance covers `lamports`, then decrements the allowance in place in `session_data` and returns the public key of the user.
+    /// Sessions prior to V5 don't have a native allowance and can move any amount.
+    /// THIS FUNCTION SHOULD ONLY BE CALLED IN THE SYSTEM PROGRAM, the caller is responsible for persisting `session_data` to the session account.
+    pub fn spend_native_allowance_system_program(
+        session_data: &mut [u8],
+        clock: &Clock,
+        lamports: u64,
+    ) -> Result<Pubkey, SessionError> {
+        let session = Self::try_deserialize(&mut &*session_data)?;
+        session.check_is_live_and_unrevoked_with_clock(clock)?;
+        let user = *session.user()?;
+        match session.native_allowance()? {
+            NativeAllowance::Limited(remaining) => {
+                let remaining = remaining
+                    .checked_sub(lamports)
+                    .ok_or(SessionError::LimitsExceeded)?;
+                let mut dst = session_data
+                    .get_mut(NATIVE_ALLOWANCE_OFFSET..)
+                    .ok_or(SessionError::InvalidAccountData)?;
+                NativeAllowance::Limited(remaining)
+                    .serialize(&mut dst)
+                    .map_err(|_| SessionError::InvalidAccountData)?;
+            }
+            NativeAllowance::Unlimited => {}
+        }
+        Ok(user)
+    }
+
+    fn native_allowance(&self) -> Result<NativeAllowance, SessionError> {
+        match &self.session_info {
+            SessionInfo::V1(_) => Ok(NativeAllowance::Unlimited),
+            SessionInfo::V2(session) => match session {
+                V2::Revoked(_) => Err(SessionError::Revoked),
+                V2::Active(_) => Ok(NativeAllowance::Unlimited),
+            },
+            SessionInfo::V3(session) => match session {
+                V3::Revoked(_) => Err(SessionError::Revoked),
+                V3::Active(_) => Ok(NativeAllowance::Unlimited),
+            },
+            SessionInfo::V4(session) => match session {
+                V4::Revoked(_) => Err(SessionError::Revoked),
+                V4::Active(_) => Ok(NativeAllowance::Unlimited),
+            },
+            SessionInfo::V5(session) => match session {
+                V5::Revoked(_) => Err(SessionError::Revoked),
+                V5::Active(session) => Ok(session.native_allowance.clone()),
+            },
+            SessionInfo::V6(session) => match session {
+                V6::Revoked(_) => Err(SessionError::Revoked),
+                V6::Active(session) => Ok(session.native_allowance.clone()),
+            },
+            SessionInfo::Invalid => Err(SessionError::InvalidAccountVersion),
+        }
     }
 
     fn check_is_live_and_unrevoked_with_clock(&self, clock: &Clock) -> Result<(), SessionError> {
@@ -44,3 +116,114 @@ impl Session {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::ActiveSessionInfoWithDomainHashAndNativeAllowance;
+    use std::marker::PhantomData;
+
+    fn serialize_v5_session(native_allowance: NativeAllowance) -> (Pubkey, Vec<u8>) {
+        let user = Pubkey::new_unique();
+        let session = Session {
+            discriminator: Session::DISCRIMINATOR,
+            sponsor: Pubkey::new_unique(),
+            major: 0,
+            session_info: SessionInfo::V5(V5::Active(
+                ActiveSessionInfoWithDomainHashAndNativeAllowance {
+                    domain_hash: [7; HASH_BYTES],
+                    native_allowance,
+                    active_session_info: ActiveSessionInfo {
+                        user,
+                        expiration: 100,
+                        _phantom_data: PhantomData,
+                    },
+                },
+            )),
+        };
+        (user, borsh::to_vec(&session).unwrap())
+    }
+
+    fn clock(unix_timestamp: UnixTimestamp) -> Clock {
+        Clock {
+            unix_timestamp,
+            ..Clock::default()
+        }
+    }
+
+    #[test]
+    fn test_spend_native_allowance_decrements_in_place() {
+        let (user, mut data) = serialize_v5_session(NativeAllowance::Limited(1_000));
+
+        assert_eq!(
+            Session::spend_native_allowance_system_program(&mut data, &clock(50), 400).unwrap(),
+            user
+        );
+        assert_eq!(
+            Session::try_deserialize(&mut data.as_slice())
+                .unwrap()
+                .native_allowance()
+                .unwrap(),
+            NativeAllowance::Limited(600)
+        );
+        assert!(matches!(
+            Session::spend_native_allowance_system_program(&mut data, &clock(50), 601),
+            Err(SessionError::LimitsExceeded)
+        ));
+    }
+
+    #[test]
+    fn test_spend_native_allowance_rejects_overspending() {
+        let (_, mut data) = serialize_v5_session(NativeAllowance::Limited(1_000));
+        let original = data.clone();
+
+        assert!(matches!(
+            Session::spend_native_allowance_system_program(&mut data, &clock(50), 1_001),
+            Err(SessionError::LimitsExceeded)
+        ));
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn test_get_user_checked_system_program_native_allowance() {
+        let (_, data) = serialize_v5_session(NativeAllowance::Limited(1_000));
+        assert!(matches!(
+            Session::try_deserialize(&mut data.as_slice())
+                .unwrap()
+                .get_user_checked_system_program(&clock(50)),
+            Err(SessionError::LimitsExceeded)
+        ));
+
+        let (user, data) = serialize_v5_session(NativeAllowance::Unlimited);
+        assert_eq!(
+            Session::try_deserialize(&mut data.as_slice())
+                .unwrap()
+                .get_user_checked_system_program(&clock(50))
+                .unwrap(),
+            user
+        );
+    }
+
+    #[test]
+    fn test_spend_native_allowance_unlimited() {
+        let (user, mut data) = serialize_v5_session(NativeAllowance::Unlimited);
+        let original = data.clone();
+
+        assert_eq!(
+            Session::spend_native_allowance_system_program(&mut data, &clock(50), u64::MAX)
+                .unwrap(),
+            user
+        );
+        assert_eq!(data, original);
+    }
+
+    #[test]
+    fn test_spend_native_allowance_expired() {
+        let (_, mut data) = serialize_v5_session(NativeAllowance::Limited(1_000));
+
+        assert!(matches!(
+            Session::spend_native_allowance_system_program(&mut data, &clock(101), 1),
+            Err(SessionError::Expired)
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::domain_registry::get_domain_programs;
+use crate::session::AuthorizedProgram;
 use crate::session::AuthorizedPrograms;
 use crate::session::AuthorizedTokens;
 use crate::session::Session;
@@ -6,11 +8,12 @@ use crate::session::SessionInfo;
 use crate::session::V2;
 use crate::session::V3;
 use crate::session::V4;
+use crate::session::V5;
+use crate::session::V6;
 use solana_program::account_info::AccountInfo;
 use solana_program::pubkey::Pubkey;
 
-pub const SESSION_SETTER: Pubkey =
-    solana_program::pubkey!("akbpBKqNWBiZn3ejes3ejieJ5t3vqEhoq1ZzLBG7jQo");
+pub use crate::session::SESSION_SETTER;
 
 impl Session {
     fn authorized_tokens(&self) -> Result<&AuthorizedTokens, SessionError> {
@@ -28,6 +31,14 @@ impl Session {
                 V4::Revoked(session) => Ok(session.authorized_tokens_with_mints.as_ref()),
                 V4::Active(session) => Ok(session.as_ref().authorized_tokens.as_ref()),
             },
+            SessionInfo::V5(session) => match session {
+                V5::Revoked(session) => Ok(session.authorized_tokens_with_mints.as_ref()),
+                V5::Active(session) => Ok(session.as_ref().authorized_tokens.as_ref()),
+            },
+            SessionInfo::V6(session) => match session {
+                V6::Revoked(session) => Ok(session.authorized_tokens_with_mints.as_ref()),
+                V6::Active(session) => Ok(session.as_ref().authorized_tokens.as_ref()),
+            },
             SessionInfo::Invalid => Err(SessionError::InvalidAccountVersion),
         }
     }
@@ -42,17 +53,30 @@ impl Session {
     fn check_authorized_program_signer(&self, signers: &[AccountInfo]) -> Result<(), SessionError> {
         match self.authorized_programs()? {
             AuthorizedPrograms::Specific(ref programs) => {
-                let signer_account_info = signers
-                    .iter()
-                    .find(|signer| programs.iter().any(|item| *signer.key == item.signer_pda))
-                    .ok_or(SessionError::UnauthorizedProgram)?;
-                if !signer_account_info.is_signer {
-                    return Err(SessionError::MissingRequiredSignature);
+                let program = check_program_signer(programs, signers)?;
+                // Programs that pass the domain record of the session's domain along with the signers also get programs removed from the domain rejected
+                match self
+                    .domain_record_address()
+                    .ok()
+                    .and_then(|address| signers.iter().find(|account| *account.key == address))
+                {
+                    Some(domain_record) => {
+                        self.check_program_not_removed(&program.program_id, domain_record)
+                    }
+                    None => Ok(()),
                 }
             }
-            AuthorizedPrograms::All => {}
+            AuthorizedPrograms::All => Ok(()),
+            AuthorizedPrograms::Domain => {
+                // Domain-scoped sessions need the domain record to be passed along with the signers
+                let domain_record_address = self.domain_record_address()?;
+                let domain_record = signers
+                    .iter()
+                    .find(|account| *account.key == domain_record_address)
+                    .ok_or(SessionError::DomainRecordRequired)?;
+                check_program_signer(&get_domain_programs(domain_record), signers).map(|_| ())
+            }
         }
-        Ok(())
     }
 
     pub fn get_token_permissions_checked(
@@ -71,3 +95,145 @@ impl Session {
         Ok(*self.user()?)
     }
 }
+
+/// Returns the program in `programs` whose signer PDA signed
+fn check_program_signer<'a>(
+    programs: &'a [AuthorizedProgram],
+    signers: &[AccountInfo],
+) -> Result<&'a AuthorizedProgram, SessionError> {
+    let (signer_account_info, program) = signers
+        .iter()
+        .find_map(|signer| {
+            programs
+                .iter()
+                .find(|item| *signer.key == item.signer_pda)
+                .map(|program| (signer, program))
+        })
+        .ok_or(SessionError::UnauthorizedProgram)?;
+    if !signer_account_info.is_signer {
+        return Err(SessionError::MissingRequiredSignature);
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain_registry::{
+        get_domain_record_address, DomainRecordHeader, DOMAIN_REGISTRY_PROGRAM_ID,
+    };
+    use crate::session::tests::{
+        account, authorized_program, domain_record_data, set_default_clock, v4_session, v5_session,
+        DOMAIN,
+    };
+
+    #[test]
+    fn test_get_token_permissions_checked_rejects_removed_programs() {
+        set_default_clock();
+        let user = Pubkey::new_unique();
+        let program = authorized_program();
+        let session = v4_session(user, AuthorizedPrograms::Specific(vec![program.clone()]));
+        let address = get_domain_record_address(DOMAIN);
+        let (mut signer_lamports, mut lamports, mut removed_lamports) = (0, 0, 0);
+        let mut signer_data = vec![];
+        let mut data = domain_record_data(&DomainRecordHeader::default(), &[]);
+        let mut removed_data = domain_record_data(
+            &DomainRecordHeader {
+                removed_programs: vec![program.program_id],
+                ..DomainRecordHeader::default()
+            },
+            &[],
+        );
+        let program_signer = account(
+            &program.signer_pda,
+            true,
+            &program.program_id,
+            &mut signer_lamports,
+            &mut signer_data,
+        );
+        let domain_record = account(
+            &address,
+            false,
+            &DOMAIN_REGISTRY_PROGRAM_ID,
+            &mut lamports,
+            &mut data,
+        );
+        let removed_domain_record = account(
+            &address,
+            false,
+            &DOMAIN_REGISTRY_PROGRAM_ID,
+            &mut removed_lamports,
+            &mut removed_data,
+        );
+
+        assert!(matches!(
+            session.get_token_permissions_checked(&user, &[program_signer.clone(), domain_record]),
+            Ok(AuthorizedTokens::Specific)
+        ));
+        assert!(matches!(
+            session.get_token_permissions_checked(
+                &user,
+                &[program_signer.clone(), removed_domain_record]
+            ),
+            Err(SessionError::UnauthorizedProgram)
+        ));
+        // Without the domain record, only the programs the session authorized are checked
+        assert!(matches!(
+            session.get_token_permissions_checked(&user, &[program_signer]),
+            Ok(AuthorizedTokens::Specific)
+        ));
+    }
+
+    #[test]
+    fn test_get_token_permissions_checked_domain_programs() {
+        set_default_clock();
+        let user = Pubkey::new_unique();
+        let program = authorized_program();
+        let other_program = authorized_program();
+        let session = v5_session(user, AuthorizedPrograms::Domain);
+        let address = get_domain_record_address(DOMAIN);
+        let (mut signer_lamports, mut other_signer_lamports, mut lamports) = (0, 0, 0);
+        let (mut signer_data, mut other_signer_data) = (vec![], vec![]);
+        let mut data = domain_record_data(
+            &DomainRecordHeader::default(),
+            std::slice::from_ref(&program),
+        );
+        let program_signer = account(
+            &program.signer_pda,
+            true,
+            &program.program_id,
+            &mut signer_lamports,
+            &mut signer_data,
+        );
+        let other_program_signer = account(
+            &other_program.signer_pda,
+            true,
+            &other_program.program_id,
+            &mut other_signer_lamports,
+            &mut other_signer_data,
+        );
+        let domain_record = account(
+            &address,
+            false,
+            &DOMAIN_REGISTRY_PROGRAM_ID,
+            &mut lamports,
+            &mut data,
+        );
+
+        assert!(matches!(
+            session.get_token_permissions_checked(
+                &user,
+                &[program_signer.clone(), domain_record.clone()]
+            ),
+            Ok(AuthorizedTokens::Specific)
+        ));
+        assert!(matches!(
+            session.get_token_permissions_checked(&user, &[other_program_signer, domain_record]),
+            Err(SessionError::UnauthorizedProgram)
+        ));
+        assert!(matches!(
+            session.get_token_permissions_checked(&user, &[program_signer]),
+            Err(SessionError::DomainRecordRequired)
+        ));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::error::SessionError;
+use crate::session::SESSION_MANAGER_ID;
+use solana_program::pubkey::Pubkey;
+
+#[cfg(not(feature = "anchor"))]
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+
+#[cfg(feature = "anchor")]
+use anchor_lang::{account, AccountDeserialize, AnchorDeserialize, AnchorSerialize, Discriminator};
+
+/// The seed of the PDA of the session manager program indexing the sessions of a user
+pub const USER_SESSIONS_SEED: &[u8] = b"user_sessions";
+
+/// The maximum number of sessions in the index of a user. Sessions started once the index is full aren't added to it, starting them doesn't fail.
+/// This bounds the size of the index and the cost of updating it when sessions are started and closed.
+pub const MAX_USER_SESSIONS: usize = 64;
+
+/// Returns the address of the account indexing the sessions of `user`
+pub fn get_user_sessions_address(user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[USER_SESSIONS_SEED, user.as_ref()], &SESSION_MANAGER_ID).0
+}
+
+/// The index of the sessions of a user. The session manager program adds sessions to this index when they are started and removes them when they are closed, so it also contains revoked and expired sessions that haven't been closed yet.
+/// The index is informational and may be incomplete: passing it when starting or closing a session is optional, and sessions started before it was introduced or once it was full are not in it.
+#[cfg_attr(feature = "anchor", account)]
+#[cfg_attr(
+    not(feature = "anchor"),
+    derive(Debug, Clone, BorshDeserialize, BorshSerialize, BorshSchema)
+)]
+pub struct UserSessions {
+    #[cfg(not(feature = "anchor"))]
+    pub discriminator: [u8; 8],
+    /// The user whose sessions are indexed
+    pub user: Pubkey,
+    /// The sponsor that paid the rent of the empty index when it was created, only this sponsor gets it back when the index is closed.
+    /// The rent of each entry is paid by the sponsor of its session and refunded to it when the session is closed.
+    pub rent_payer: Pubkey,
+    /// The addresses of the session accounts of the user
+    pub sessions: Vec<Pubkey>,
+}
+
+impl UserSessions {
+    #[cfg(feature = "anchor")]
+    #[allow(clippy::same_name_method)]
+    /// Tries to deserialize a user sessions account. This should only be used after checking that the account is owned by the session manager program.
+    pub fn try_deserialize(data: &mut &[u8]) -> Result<Self, SessionError> {
+        AccountDeserialize::try_deserialize(data).map_err(|_| SessionError::InvalidAccountData)
+    }
+
+    #[cfg(not(feature = "anchor"))]
+    const DISCRIMINATOR: [u8; 8] = [221, 40, 143, 33, 112, 207, 141, 238];
+    #[cfg(not(feature = "anchor"))]
+    /// Tries to deserialize a user sessions account. This should only be used after checking that the account is owned by the session manager program.
+    pub fn try_deserialize(data: &mut &[u8]) -> Result<Self, SessionError> {
+        let result =
+            UserSessions::deserialize(data).map_err(|_| SessionError::InvalidAccountData)?;
+        if result.discriminator != Self::DISCRIMINATOR {
+            return Err(SessionError::InvalidAccountDiscriminator);
+        }
+        Ok(result)
+    }
+}
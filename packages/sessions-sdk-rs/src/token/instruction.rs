@@ -6,14 +6,27 @@ use solana_program::{
 
 const SPL_TOKEN_PROGRAM_ID: Pubkey =
     solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const SPL_TOKEN_2022_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
 
+/// The instructions built in this module have the same layout in the legacy token program and Token-2022, so both are accepted
 fn check_program_account(spl_token_program_id: &Pubkey) -> Result<(), ProgramError> {
-    if spl_token_program_id != &SPL_TOKEN_PROGRAM_ID {
+    if spl_token_program_id != &SPL_TOKEN_PROGRAM_ID
+        && spl_token_program_id != &SPL_TOKEN_2022_PROGRAM_ID
+    {
         return Err(ProgramError::IncorrectProgramId);
     }
     Ok(())
 }
 
+/// Appends the domain record of the session's domain to an instruction built in this module.
+/// Domain-scoped sessions need the domain record for the token program to check that the program is registered in the domain of the session. For other sessions with a domain (V4 onwards), passing it also gets programs that were removed from the domain rejected.
+pub fn append_domain_record(instruction: &mut Instruction, domain_record: &Pubkey) {
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(*domain_record, false));
+}
+
 /// This function is meant to replace `spl_token::instruction::transfer` in the context of sessions.
 /// In-session token transfers are different from regular transfers in that they not only require the session key to sign as the authority, but also require an additional signer.
 /// This additional signer is the `program_signer` and allows the token program to verify that the transfer is happening within an authorized program. It is the PDA of the authorized program that will call the instruction via CPI with seed `PROGRAM_SIGNER_SEED`.
@@ -1,4 +1,15 @@
+use crate::session::DomainHash;
 use solana_program::pubkey::Pubkey;
 
 pub const TOLLBOOTH_PROGRAM_ID: Pubkey =
     solana_program::pubkey!("toLLShH3xqYgVZuNUotUgQNWZ3Ldwrq9qCp27sJBaDp");
+const TOLL_RECIPIENT_SEED: &[u8] = b"toll_recipient";
+
+/// Returns the address of the toll recipient `recipient_id` of the domain whose hash is `domain_hash`. Tolls are paid to the associated token accounts of toll recipients.
+pub fn get_toll_recipient_address(domain_hash: &DomainHash, recipient_id: u8) -> Pubkey {
+    Pubkey::find_program_address(
+        &[TOLL_RECIPIENT_SEED, &[recipient_id], domain_hash],
+        &TOLLBOOTH_PROGRAM_ID,
+    )
+    .0
+}
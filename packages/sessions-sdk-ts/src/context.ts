@@ -1,8 +1,9 @@
 import type { Wallet } from "@coral-xyz/anchor";
 import { AnchorProvider } from "@coral-xyz/anchor";
 import { ChainIdProgram } from "@fogo/sessions-idls";
-import type { PublicKey, Connection as Web3Connection } from "@solana/web3.js";
-import { Keypair } from "@solana/web3.js";
+import type { Connection as Web3Connection } from "@solana/web3.js";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import BN from "bn.js";
 
 import type {
   Connection,
@@ -70,13 +71,18 @@ const fetchChainId = async (connection: Web3Connection) => {
     ),
   ); // We mock the wallet because we don't need to sign anything
   const { chainIdAccount: chainIdAddress } = await chainIdProgram.methods
-    .set("")
-    .pubkeys(); // We use Anchor to derive the chain ID address, not caring about the actual argument of `set`
+    .set("", new BN(0), PublicKey.default)
+    .pubkeys(); // We use Anchor to derive the chain ID address, not caring about the actual arguments of `set`
   if (chainIdAddress === undefined) {
     throw new NoChainIdAddressError();
   }
-  const chainId = await chainIdProgram.account.chainId.fetch(chainIdAddress);
-  return chainId.chainId;
+  const chainIdAccount = await connection.getAccountInfo(chainIdAddress);
+  if (chainIdAccount === null) {
+    throw new NoChainIdAccountError();
+  }
+  // We only decode the name of the chain, which comes first, because legacy chain ID accounts don't have the fields after it
+  const length = chainIdAccount.data.readUInt32LE(8);
+  return chainIdAccount.data.subarray(12, 12 + length).toString("utf8");
 };
 
 const getDomain = (requestedDomain?: string) => {
@@ -100,6 +106,13 @@ class NoChainIdAddressError extends Error {
   }
 }
 
+class NoChainIdAccountError extends Error {
+  constructor() {
+    super("The chain ID account doesn't exist");
+    this.name = "NoChainIdAccountError";
+  }
+}
+
 class DomainRequiredError extends Error {
   constructor() {
     super(
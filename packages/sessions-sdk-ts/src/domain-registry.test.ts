@@ -0,0 +1,68 @@
+import { sha256 } from "@noble/hashes/sha2.js";
+
+import {
+  getDomainHash,
+  getDomainRecordAddress,
+  normalizeDomain,
+} from "./domain-registry.js";
+
+describe("normalizeDomain", () => {
+  it("returns the canonical form of web origins", () => {
+    for (const [domain, normalized] of [
+      ["https://app.xyz", "https://app.xyz"],
+      ["https://app.xyz/", "https://app.xyz"],
+      ["HTTPS://App.XYZ", "https://app.xyz"],
+      ["https://app.xyz:443", "https://app.xyz"],
+      ["https://app.xyz:8443", "https://app.xyz:8443"],
+      ["http://localhost:3000", "http://localhost:3000"],
+      ["http://localhost:80/", "http://localhost"],
+      ["http://127.0.0.1:8080", "http://127.0.0.1:8080"],
+      ["https://xn--mnchen-3ya.de", "https://xn--mnchen-3ya.de"],
+    ] as const) {
+      expect(normalizeDomain(domain)).toBe(normalized);
+    }
+  });
+
+  it("returns undefined for domains that aren't web origins", () => {
+    for (const domain of [
+      "app.xyz",
+      "ftp://app.xyz",
+      " https://app.xyz",
+      "https://app.xyz/path",
+      "https://app.xyz//",
+      "https://app.xyz?query",
+      "https://app.xyz#fragment",
+      "https://user@app.xyz",
+      "https://",
+      "https://app .xyz",
+      "https://app.xyz ",
+      "https://app.xyz.",
+      "https://-app.xyz",
+      "https://app_1.xyz",
+      "https://münchen.de",
+      "https://Kapp.xyz",
+      "https://[::1]:3000",
+      "https://app.xyz:",
+      "https://app.xyz:0",
+      "https://app.xyz:65536",
+      "https://app.xyz:+80",
+      "https://app.xyz:80:80",
+    ]) {
+      expect(normalizeDomain(domain)).toBeUndefined();
+    }
+  });
+});
+
+describe("getDomainRecordAddress", () => {
+  it("derives the same address for equivalent domains", () => {
+    expect(getDomainRecordAddress("HTTPS://App.xyz:443/")).toEqual(
+      getDomainRecordAddress("https://app.xyz"),
+    );
+  });
+
+  it("hashes domains that can't be normalized as they are", () => {
+    expect(getDomainHash("app.xyz")).toEqual(
+      sha256(new TextEncoder().encode("app.xyz")),
+    );
+  });
+});
@@ -0,0 +1,86 @@
+import { DomainRegistryIdl } from "@fogo/sessions-idls";
+import { sha256 } from "@noble/hashes/sha2.js";
+import { PublicKey } from "@solana/web3.js";
+
+// The longest host allowed in a domain, as in DNS
+const MAX_HOST_LENGTH = 253;
+// The longest label allowed in the host of a domain, as in DNS
+const MAX_LABEL_LENGTH = 63;
+const DEFAULT_PORTS: Partial<Record<string, number>> = {
+  http: 80,
+  https: 443,
+};
+
+// Unlike `toLowerCase`, this never turns a non-ASCII character into an ASCII one, like the domain registry
+const toAsciiLowerCase = (value: string) =>
+  value.replace(/[A-Z]/g, (char) => char.toLowerCase());
+
+const parsePort = (port: string) => {
+  if (!/^\d+$/.test(port)) {
+    return undefined;
+  }
+  const value = Number.parseInt(port, 10);
+  return value > 0 && value <= 65_535 ? value : undefined;
+};
+
+const isValidHost = (host: string) =>
+  host.length <= MAX_HOST_LENGTH &&
+  host
+    .split(".")
+    .every(
+      (label) =>
+        label.length > 0 &&
+        label.length <= MAX_LABEL_LENGTH &&
+        !label.startsWith("-") &&
+        !label.endsWith("-") &&
+        /^[a-z\d-]+$/.test(label),
+    );
+
+/**
+ * Returns the canonical form of a web origin (`scheme://host[:port]`), which identifies its domain record, like the domain registry does.
+ * The canonical form has a lowercase scheme and host, no trailing slash and no port if it's the default port of the scheme.
+ * Returns `undefined` if `domain` isn't a web origin with an ASCII host, internationalized domain names need to be punycode-encoded.
+ */
+export const normalizeDomain = (domain: string) => {
+  const schemeEnd = domain.indexOf("://");
+  if (schemeEnd === -1) {
+    return;
+  }
+  const scheme = toAsciiLowerCase(domain.slice(0, schemeEnd));
+  const defaultPort = DEFAULT_PORTS[scheme];
+  if (defaultPort === undefined) {
+    return;
+  }
+
+  const rest = domain.slice(schemeEnd + 3);
+  const authority = rest.endsWith("/") ? rest.slice(0, -1) : rest;
+  if (/[/?#@]/.test(authority)) {
+    return;
+  }
+  const portStart = authority.lastIndexOf(":");
+  const host = toAsciiLowerCase(
+    portStart === -1 ? authority : authority.slice(0, portStart),
+  );
+  const port =
+    portStart === -1 ? defaultPort : parsePort(authority.slice(portStart + 1));
+  if (port === undefined || !isValidHost(host)) {
+    return;
+  }
+
+  return port === defaultPort
+    ? `${scheme}://${host}`
+    : `${scheme}://${host}:${port.toString()}`;
+};
+
+/**
+ * Returns the hash that identifies `domain` on chain, after normalizing it like the domain registry does.
+ * Domains that can't be normalized are hashed as they are, the domain registry never creates records for them.
+ */
+export const getDomainHash = (domain: string) =>
+  sha256(new TextEncoder().encode(normalizeDomain(domain) ?? domain));
+
+export const getDomainRecordAddress = (domain: string) =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from("domain-record"), getDomainHash(domain)],
+    new PublicKey(DomainRegistryIdl.address),
+  )[0];
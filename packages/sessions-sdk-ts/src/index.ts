@@ -1,7 +1,6 @@
 import type { Wallet } from "@coral-xyz/anchor";
 import { AnchorProvider, BorshAccountsCoder } from "@coral-xyz/anchor";
 import {
-  DomainRegistryIdl,
   IntentTransferIdl,
   IntentTransferProgram,
   SessionManagerIdl,
@@ -13,7 +12,6 @@ import {
 } from "@metaplex-foundation/mpl-token-metadata";
 import { publicKey as metaplexPublicKey } from "@metaplex-foundation/umi";
 import { createUmi } from "@metaplex-foundation/umi-bundle-defaults";
-import { sha256 } from "@noble/hashes/sha2.js";
 import { fromLegacyPublicKey } from "@solana/compat";
 import {
   generateKeyPair,
@@ -64,6 +62,7 @@ import {
   signMessageWithKey,
   verifyMessageWithKey,
 } from "./crypto.js";
+import { getDomainRecordAddress } from "./domain-registry.js";
 import {
   createSessionUnwrapInstruction,
   createSessionWrapInstructions,
@@ -86,6 +85,11 @@ export {
   type SendTransactionOptions,
   type SessionContext,
 } from "./context.js";
+export {
+  getDomainHash,
+  getDomainRecordAddress,
+  normalizeDomain,
+} from "./domain-registry.js";
 export {
   createPaymasterFeeInstruction,
   createSessionUnwrapInstruction,
@@ -286,7 +290,11 @@ const getDomainRegistryAuthorizedPrograms = async (
     return [];
   } else {
     const programs = [];
-    for (let i = 0; i < result.data.length; i += 64) {
+    for (
+      let i = getDomainRecordProgramsOffset(result.data);
+      i < result.data.length;
+      i += 64
+    ) {
       programs.push({
         programId: new PublicKey(result.data.subarray(i, i + 32)),
         signerPda: new PublicKey(result.data.subarray(i + 32, i + 64)),
@@ -296,6 +304,78 @@ const getDomainRegistryAuthorizedPrograms = async (
   }
 };
 
+// Domain records may start with a header: a discriminator, the length of the header as a little-endian u32 and the header itself
+const DOMAIN_RECORD_HEADER_DISCRIMINATOR = Buffer.from([
+  92, 194, 234, 129, 71, 32, 52, 179,
+]);
+
+const getDomainRecordProgramsOffset = (data: Buffer) =>
+  data.subarray(0, 8).equals(DOMAIN_RECORD_HEADER_DISCRIMINATOR)
+    ? 12 + data.readUInt32LE(8)
+    : 0;
+
+// New fields are appended to the header, headers written before a field existed are shorter and their missing bytes decode as zeros
+const decodeDomainRecordHeader = (data: Buffer) => {
+  if (!data.subarray(0, 8).equals(DOMAIN_RECORD_HEADER_DISCRIMINATOR)) {
+    return;
+  }
+  const header = data.subarray(12, 12 + data.readUInt32LE(8));
+  let offset = 0;
+  const read = (length: number) => {
+    const bytes = Buffer.alloc(length);
+    header.copy(bytes, 0, offset, offset + length);
+    offset += length;
+    return bytes;
+  };
+  const readOption = <T>(readValue: () => T) =>
+    read(1).readUInt8(0) === 0 ? undefined : readValue();
+  const readString = () => read(read(4).readUInt32LE(0)).toString("utf8");
+  const readPublicKey = () => new PublicKey(read(32));
+
+  return {
+    minSessionDuration: readOption(() => read(8).readBigInt64LE(0)),
+    maxSessionDuration: readOption(() => read(8).readBigInt64LE(0)),
+    admin: readOption(readPublicKey),
+    pendingAdmin: readOption(readPublicKey),
+    metadata: readOption(() => ({
+      displayName: readString(),
+      iconUri: readOption(readString),
+      contact: readOption(readString),
+    })),
+    verified: read(1).readUInt8(0) !== 0,
+    removedPrograms: Array.from(
+      { length: read(4).readUInt32LE(0) },
+      readPublicKey,
+    ),
+  };
+};
+
+export type DomainMetadata = {
+  displayName: string;
+  iconUri: string | undefined;
+  contact: string | undefined;
+  /** Whether the domain registry authority has checked this metadata */
+  verified: boolean;
+};
+
+/**
+ * Returns the metadata that the app of `domain` registered in the domain registry, if any.
+ * Wallets should only trust metadata that is `verified`.
+ */
+export const getDomainMetadata = async (
+  connection: Connection,
+  domain: string,
+): Promise<DomainMetadata | undefined> => {
+  const result = await connection.getAccountInfo(
+    getDomainRecordAddress(domain),
+    "confirmed",
+  );
+  const header = result ? decodeDomainRecordHeader(result.data) : undefined;
+  return header?.metadata
+    ? { ...header.metadata, verified: header.verified }
+    : undefined;
+};
+
 const authorizedProgramsMatchDomainRegistry = (
   sessionAuthorizedPrograms: NonNullable<
     z.infer<typeof sessionInfoSchema>
@@ -818,13 +898,11 @@ const amountToString = (amount: bigint, decimals: number): string => {
   ].join("");
 };
 
-export const getDomainRecordAddress = (domain: string) => {
-  const hash = sha256(new TextEncoder().encode(domain));
-  return PublicKey.findProgramAddressSync(
-    [Buffer.from("domain-record"), hash],
-    new PublicKey(DomainRegistryIdl.address),
+const getUserSessionsAddress = (user: PublicKey) =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from("user_sessions"), user.toBuffer()],
+    new PublicKey(SessionManagerIdl.address),
   )[0];
-};
 
 const BRIDGING_ADDRESS_LOOKUP_TABLE: Record<
   Network,
@@ -846,8 +924,8 @@ const buildStartSessionInstruction = async (
   options: EstablishSessionOptions,
   sessionKey: CryptoKeyPair,
   tokens?: TokenInfo[],
-) => {
-  const instruction = new SessionManagerProgram(
+) =>
+  new SessionManagerProgram(
     new AnchorProvider(options.context.connection, {} as Wallet, {}),
   ).methods
     .startSession()
@@ -855,39 +933,37 @@ const buildStartSessionInstruction = async (
       domainRegistry: getDomainRecordAddress(options.context.domain),
       session: await getAddressFromPublicKey(sessionKey.publicKey),
       sponsor: options.context.payer,
-    });
-
-  return tokens === undefined
-    ? instruction.instruction()
-    : instruction
-        .remainingAccounts(
-          tokens.flatMap(({ symbolOrMint, mint, metadataAddress }) => [
-            {
-              isSigner: false,
-              isWritable: true,
-              pubkey: getAssociatedTokenAddressSync(
-                mint,
-                options.walletPublicKey,
-              ),
-            },
-            {
-              isSigner: false,
-              isWritable: false,
-              pubkey: mint,
-            },
-            ...(symbolOrMint.type === SymbolOrMintType.Symbol
-              ? [
-                  {
-                    isSigner: false,
-                    isWritable: false,
-                    pubkey: metadataAddress,
-                  },
-                ]
-              : []),
-          ]),
-        )
-        .instruction();
-};
+    })
+    .remainingAccounts([
+      // The user sessions account is optional, it needs to come before the token accounts
+      {
+        isSigner: false,
+        isWritable: true,
+        pubkey: getUserSessionsAddress(options.walletPublicKey),
+      },
+      ...(tokens ?? []).flatMap(({ symbolOrMint, mint, metadataAddress }) => [
+        {
+          isSigner: false,
+          isWritable: true,
+          pubkey: getAssociatedTokenAddressSync(mint, options.walletPublicKey),
+        },
+        {
+          isSigner: false,
+          isWritable: false,
+          pubkey: mint,
+        },
+        ...(symbolOrMint.type === SymbolOrMintType.Symbol
+          ? [
+              {
+                isSigner: false,
+                isWritable: false,
+                pubkey: metadataAddress,
+              },
+            ]
+          : []),
+      ]),
+    ])
+    .instruction();
 
 export enum SessionResultType {
   Success,
@@ -1,7 +1,6 @@
 import type { Wallet } from "@coral-xyz/anchor";
 import { AnchorProvider } from "@coral-xyz/anchor";
 import { TollboothIdl, TollboothProgram } from "@fogo/sessions-idls";
-import { sha256 } from "@noble/hashes/sha2.js";
 import {
   createAssociatedTokenAccountIdempotentInstruction,
   createCloseAccountInstruction,
@@ -17,6 +16,8 @@ import {
 } from "@solana/web3.js";
 import type BN from "bn.js";
 
+import { getDomainHash, getDomainRecordAddress } from "./domain-registry.js";
+
 const SESSION_WRAP_DISCRIMINATOR = 4_000_000;
 
 function getNativeMintAssociatedTokenAddressSync(walletPublicKey: PublicKey) {
@@ -99,18 +100,21 @@ export function createSessionUnwrapInstruction(
   );
 }
 
-const getDomainTollRecipientAddress = (domain: string) => {
-  const hash = sha256(new TextEncoder().encode(domain));
-  return PublicKey.findProgramAddressSync(
-    [Buffer.from("toll_recipient"), Buffer.from([0]), hash],
+const getDomainTollRecipientAddress = (domain: string, recipientId: number) =>
+  PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("toll_recipient"),
+      Buffer.from([recipientId]),
+      getDomainHash(domain),
+    ],
     new PublicKey(TollboothIdl.address),
   )[0];
-};
 
 /**
  * Creates the instruction required to pay the paymaster fee for a transaction.
  * This instruction is only required if the transaction variation has a fee and may be placed anywhere in the instruction list.
  * The fee amount for a variation in a given token can be retrieved using the `getPaymasterFee` function.
+ * Domains may have several toll recipients, identified by `recipientId` from 0 to 255, so that concurrent transactions don't all write to the same token account.
  */
 export const createPaymasterFeeInstruction = ({
   sessionKey,
@@ -118,20 +122,23 @@ export const createPaymasterFeeInstruction = ({
   domain,
   feeMint,
   feeAmount,
+  recipientId = 0,
 }: {
   sessionKey: PublicKey;
   walletPublicKey: PublicKey;
   domain: string;
   feeMint: PublicKey;
   feeAmount: BN;
+  recipientId?: number | undefined;
 }): Promise<TransactionInstruction> => {
-  const recipient = getDomainTollRecipientAddress(domain);
+  const recipient = getDomainTollRecipientAddress(domain, recipientId);
   return new TollboothProgram(
     new AnchorProvider({} as Connection, {} as Wallet),
   ).methods
-    .payToll(feeAmount, 0)
+    .payToll(feeAmount, recipientId)
     .accounts({
       destination: getAssociatedTokenAddressSync(feeMint, recipient, true),
+      domainRecord: getDomainRecordAddress(domain),
       mint: feeMint,
       session: sessionKey,
       source: getAssociatedTokenAddressSync(feeMint, walletPublicKey),
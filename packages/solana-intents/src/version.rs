@@ -8,7 +8,7 @@ use nom::{
 };
 use std::str::FromStr;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub struct Version {
     pub major: u8,
     pub minor: u8,
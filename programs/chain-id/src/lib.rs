@@ -1,6 +1,8 @@
 #![allow(unexpected_cfgs)] // warning: unexpected `cfg` condition value: `anchor-debug`
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use std::io::Read;
 
 declare_id!("Cha1RcWkdcF1dmGuTui53JmSnVCacCc2Kx2SY7zSFhaN");
 
@@ -9,25 +11,190 @@ pub const SEED: &[u8] = b"chain_id";
 #[program]
 pub mod chain_id {
     use super::*;
-    pub fn set<'info>(ctx: Context<'_, '_, '_, 'info, Set<'info>>, chain_id: String) -> Result<()> {
-        ctx.accounts
-            .chain_id_account
-            .set_inner(ChainId { chain_id });
+
+    /// Creates the chain id account. Signed by the upgrade authority of this program.
+    pub fn set<'info>(
+        ctx: Context<'_, '_, '_, 'info, Set<'info>>,
+        chain_id: String,
+        numeric_id: u64,
+        authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.chain_id_account.set_inner(ChainId {
+            chain_id,
+            details: ChainIdDetails {
+                numeric_id,
+                authority,
+            },
+        });
         Ok(())
     }
+
+    /// Replaces the chain id and its authority. Signed by the authority of the chain id or by the upgrade authority of this program, which can also update legacy chain id accounts that don't have an authority.
+    pub fn update<'info>(
+        ctx: Context<'_, '_, '_, 'info, Update<'info>>,
+        chain_id: String,
+        numeric_id: u64,
+        authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.check_authority()?;
+        ctx.accounts.write(&ChainId {
+            chain_id,
+            details: ChainIdDetails {
+                numeric_id,
+                authority,
+            },
+        })
+    }
 }
 
 #[derive(Accounts)]
 #[instruction(chain_id: String)]
 pub struct Set<'info> {
-    #[account(mut)]
+    #[account(mut, address = program_data.upgrade_authority_address.ok_or(ChainIdError::Unauthorized)? @ ChainIdError::Unauthorized)]
     pub sponsor: Signer<'info>,
-    #[account(init, payer = sponsor, seeds = [SEED], bump, space = 8 + 4 + chain_id.len())]
+    #[account(seeds = [crate::ID.as_ref()], bump, seeds::program = bpf_loader_upgradeable::ID)]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(init, payer = sponsor, seeds = [SEED], bump, space = ChainId::space(&chain_id))]
+    pub chain_id_account: Account<'info, ChainId>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Update<'info> {
+    /// The authority of the chain id or the upgrade authority of this program
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    /// Only required when the signer is the upgrade authority
+    #[account(seeds = [crate::ID.as_ref()], bump, seeds::program = bpf_loader_upgradeable::ID)]
+    pub program_data: Option<Account<'info, ProgramData>>,
+    #[account(mut, seeds = [SEED], bump)]
     pub chain_id_account: Account<'info, ChainId>,
     pub system_program: Program<'info, System>,
 }
 
+impl<'info> Update<'info> {
+    fn check_authority(&self) -> Result<()> {
+        let is_upgrade_authority = self.program_data.as_ref().is_some_and(|program_data| {
+            program_data.upgrade_authority_address == Some(self.signer.key())
+        });
+        require!(
+            is_upgrade_authority || self.chain_id_account.details.authority == self.signer.key(),
+            ChainIdError::Unauthorized
+        );
+        Ok(())
+    }
+
+    /// Resizes the chain id account for `chain_id`, which also migrates legacy accounts, and writes it
+    fn write(&mut self, chain_id: &ChainId) -> Result<()> {
+        let space = ChainId::space(&chain_id.chain_id);
+        let rent = Rent::get()?.minimum_balance(space);
+        let account_info = self.chain_id_account.to_account_info();
+        let lamports = account_info.lamports();
+        if rent > lamports {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: self.signer.to_account_info(),
+                        to: account_info.clone(),
+                    },
+                ),
+                rent - lamports,
+            )?;
+        } else {
+            **account_info.try_borrow_mut_lamports()? -= lamports - rent;
+            **self.signer.try_borrow_mut_lamports()? += lamports - rent;
+        }
+        account_info.realloc(space, false)?;
+        self.chain_id_account.set_inner(chain_id.clone());
+        Ok(())
+    }
+}
+
+/// The chain that intents are signed for, intents must contain `chain_id` to be accepted on this chain
 #[account]
 pub struct ChainId {
+    /// The name of the chain, as it appears in intents
     pub chain_id: String,
+    pub details: ChainIdDetails,
+}
+
+impl ChainId {
+    pub fn space(chain_id: &str) -> usize {
+        8 + 4 + chain_id.len() + ChainIdDetails::SPACE
+    }
+
+    /// Returns whether `chain_id`, the chain id of an intent, designates this chain, either by its name or by its numeric id
+    pub fn matches(&self, chain_id: &str) -> bool {
+        self.chain_id == chain_id
+            || (self.details.numeric_id != 0
+                && chain_id.parse::<u64>() == Ok(self.details.numeric_id))
+    }
+}
+
+/// The fields of `ChainId` that legacy chain id accounts, which only contain the name of the chain, don't have.
+/// Legacy accounts decode with the default details until the upgrade authority updates them.
+#[derive(AnchorSerialize, Clone, Debug, Default, PartialEq)]
+pub struct ChainIdDetails {
+    /// The numeric identifier of the chain, 0 if it doesn't have one
+    pub numeric_id: u64,
+    /// The key that can update the chain id account, in addition to the upgrade authority of this program
+    pub authority: Pubkey,
+}
+
+impl ChainIdDetails {
+    pub const SPACE: usize = 8 + 32;
+}
+
+impl AnchorDeserialize for ChainIdDetails {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut first_byte = [0u8; 1];
+        if reader.read(&mut first_byte)? == 0 {
+            return Ok(Self::default());
+        }
+        let mut reader = first_byte.chain(reader);
+        Ok(Self {
+            numeric_id: AnchorDeserialize::deserialize_reader(&mut reader)?,
+            authority: AnchorDeserialize::deserialize_reader(&mut reader)?,
+        })
+    }
+}
+
+#[error_code]
+pub enum ChainIdError {
+    #[msg("The signer is neither the authority of the chain id nor the upgrade authority of the program")]
+    Unauthorized,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_legacy_chain_id() {
+        let mut legacy = ChainId::DISCRIMINATOR.to_vec();
+        "fogo-testnet".to_string().serialize(&mut legacy).unwrap();
+        let chain_id = ChainId::try_deserialize(&mut legacy.as_slice()).unwrap();
+        assert_eq!(chain_id.chain_id, "fogo-testnet");
+        assert_eq!(chain_id.details, ChainIdDetails::default());
+        assert!(chain_id.matches("fogo-testnet"));
+        assert!(!chain_id.matches("0"));
+
+        let chain_id = ChainId {
+            chain_id: "fogo-testnet".to_string(),
+            details: ChainIdDetails {
+                numeric_id: 42,
+                authority: Pubkey::new_unique(),
+            },
+        };
+        let mut data = vec![];
+        chain_id.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), ChainId::space(&chain_id.chain_id));
+        let decoded = ChainId::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(decoded.details, chain_id.details);
+        assert!(decoded.matches("42"));
+        assert!(!decoded.matches("43"));
+
+        assert!(ChainId::try_deserialize(&mut data.get(..data.len() - 1).unwrap()).is_err());
+    }
 }
@@ -0,0 +1,327 @@
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{bpf_loader_upgradeable, instruction::Instruction},
+    system_program, AccountDeserialize, AnchorSerialize, Discriminator, InstructionData,
+    ToAccountMetas,
+};
+use chain_id::{ChainId, ChainIdDetails, SEED};
+use litesvm::{types::TransactionResult, LiteSVM};
+use solana_account::Account;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+fn program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[chain_id::ID.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+fn chain_id_address() -> Pubkey {
+    Pubkey::find_program_address(&[SEED], &chain_id::ID).0
+}
+
+fn generate_and_fund_key(svm: &mut LiteSVM) -> Keypair {
+    let keypair = Keypair::new();
+    svm.airdrop(&keypair.pubkey(), 1_000_000_000).unwrap();
+    keypair
+}
+
+/// Loads the chain id program with a program data account whose upgrade authority is the returned key
+fn setup() -> (LiteSVM, Keypair) {
+    let mut svm = LiteSVM::new();
+    svm.add_program_from_file(chain_id::ID, "../../target/deploy/chain_id.so")
+        .expect("Failed to load chain_id program, run `cargo build-sbf` before this test");
+    let upgrade_authority = generate_and_fund_key(&mut svm);
+
+    // The bincode encoding of `UpgradeableLoaderState::ProgramData { slot: 0, upgrade_authority_address: Some(upgrade_authority) }`
+    let data = [
+        3u32.to_le_bytes().as_slice(),
+        &0u64.to_le_bytes(),
+        &[1],
+        upgrade_authority.pubkey().as_ref(),
+    ]
+    .concat();
+    svm.set_account(
+        program_data_address(),
+        Account {
+            lamports: svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: bpf_loader_upgradeable::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+    (svm, upgrade_authority)
+}
+
+#[allow(clippy::result_large_err)]
+fn submit_instruction(
+    svm: &mut LiteSVM,
+    instruction: Instruction,
+    signer: &Keypair,
+) -> TransactionResult {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&signer.pubkey()),
+        &[signer],
+        svm.latest_blockhash(),
+    );
+    svm.send_transaction(transaction)
+}
+
+fn set_instruction(
+    sponsor: &Pubkey,
+    chain_id: &str,
+    numeric_id: u64,
+    authority: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: chain_id::ID,
+        accounts: chain_id::accounts::Set {
+            sponsor: *sponsor,
+            program_data: program_data_address(),
+            chain_id_account: chain_id_address(),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: chain_id::instruction::Set {
+            chain_id: chain_id.to_string(),
+            numeric_id,
+            authority: *authority,
+        }
+        .data(),
+    }
+}
+
+fn update_instruction(
+    signer: &Pubkey,
+    as_upgrade_authority: bool,
+    chain_id: &str,
+    numeric_id: u64,
+    authority: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: chain_id::ID,
+        accounts: chain_id::accounts::Update {
+            signer: *signer,
+            program_data: as_upgrade_authority.then(program_data_address),
+            chain_id_account: chain_id_address(),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: chain_id::instruction::Update {
+            chain_id: chain_id.to_string(),
+            numeric_id,
+            authority: *authority,
+        }
+        .data(),
+    }
+}
+
+/// Reads the chain id account and checks that its size matches its content
+fn get_chain_id(svm: &LiteSVM) -> ChainId {
+    let account = svm.get_account(&chain_id_address()).unwrap();
+    let chain_id = ChainId::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(account.data.len(), ChainId::space(&chain_id.chain_id));
+    chain_id
+}
+
+#[test]
+fn test_set() {
+    let (mut svm, upgrade_authority) = setup();
+    let authority = Pubkey::new_unique();
+
+    let other = generate_and_fund_key(&mut svm);
+    assert!(submit_instruction(
+        &mut svm,
+        set_instruction(&other.pubkey(), "fogo-testnet", 1, &other.pubkey()),
+        &other
+    )
+    .is_err());
+
+    submit_instruction(
+        &mut svm,
+        set_instruction(&upgrade_authority.pubkey(), "fogo-testnet", 1, &authority),
+        &upgrade_authority,
+    )
+    .unwrap();
+    let chain_id = get_chain_id(&svm);
+    assert_eq!(chain_id.chain_id, "fogo-testnet");
+    assert_eq!(
+        chain_id.details,
+        ChainIdDetails {
+            numeric_id: 1,
+            authority
+        }
+    );
+}
+
+#[test]
+fn test_update_by_authority() {
+    let (mut svm, upgrade_authority) = setup();
+    let authority = generate_and_fund_key(&mut svm);
+    submit_instruction(
+        &mut svm,
+        set_instruction(
+            &upgrade_authority.pubkey(),
+            "fogo-testnet",
+            1,
+            &authority.pubkey(),
+        ),
+        &upgrade_authority,
+    )
+    .unwrap();
+
+    let new_authority = Pubkey::new_unique();
+    submit_instruction(
+        &mut svm,
+        update_instruction(
+            &authority.pubkey(),
+            false,
+            "fogo-testnet-2",
+            2,
+            &new_authority,
+        ),
+        &authority,
+    )
+    .unwrap();
+    let chain_id = get_chain_id(&svm);
+    assert_eq!(chain_id.chain_id, "fogo-testnet-2");
+    assert_eq!(
+        chain_id.details,
+        ChainIdDetails {
+            numeric_id: 2,
+            authority: new_authority
+        }
+    );
+
+    // The previous authority can't update the chain id anymore
+    svm.expire_blockhash();
+    assert!(submit_instruction(
+        &mut svm,
+        update_instruction(&authority.pubkey(), false, "fogo", 3, &authority.pubkey()),
+        &authority
+    )
+    .is_err());
+}
+
+#[test]
+fn test_update_by_upgrade_authority() {
+    let (mut svm, upgrade_authority) = setup();
+    let authority = Pubkey::new_unique();
+    submit_instruction(
+        &mut svm,
+        set_instruction(&upgrade_authority.pubkey(), "fogo-testnet", 1, &authority),
+        &upgrade_authority,
+    )
+    .unwrap();
+
+    submit_instruction(
+        &mut svm,
+        update_instruction(&upgrade_authority.pubkey(), true, "fogo", 2, &authority),
+        &upgrade_authority,
+    )
+    .unwrap();
+    let chain_id = get_chain_id(&svm);
+    assert_eq!(chain_id.chain_id, "fogo");
+    assert_eq!(
+        chain_id.details,
+        ChainIdDetails {
+            numeric_id: 2,
+            authority
+        }
+    );
+}
+
+#[test]
+fn test_update_rejects_unauthorized_signer() {
+    let (mut svm, upgrade_authority) = setup();
+    submit_instruction(
+        &mut svm,
+        set_instruction(
+            &upgrade_authority.pubkey(),
+            "fogo-testnet",
+            1,
+            &Pubkey::new_unique(),
+        ),
+        &upgrade_authority,
+    )
+    .unwrap();
+
+    let other = generate_and_fund_key(&mut svm);
+    for as_upgrade_authority in [false, true] {
+        assert!(submit_instruction(
+            &mut svm,
+            update_instruction(
+                &other.pubkey(),
+                as_upgrade_authority,
+                "fogo",
+                2,
+                &other.pubkey()
+            ),
+            &other
+        )
+        .is_err());
+    }
+    assert_eq!(get_chain_id(&svm).chain_id, "fogo-testnet");
+}
+
+#[test]
+fn test_update_migrates_legacy_chain_id() {
+    let (mut svm, upgrade_authority) = setup();
+    // Chain id accounts created before the authority and the numeric id existed only contain the name of the chain
+    let mut data = ChainId::DISCRIMINATOR.to_vec();
+    "fogo-testnet".to_string().serialize(&mut data).unwrap();
+    svm.set_account(
+        chain_id_address(),
+        Account {
+            lamports: svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: chain_id::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+    let legacy = ChainId::try_deserialize(
+        &mut svm
+            .get_account(&chain_id_address())
+            .unwrap()
+            .data
+            .as_slice(),
+    )
+    .unwrap();
+    assert_eq!(legacy.chain_id, "fogo-testnet");
+    assert_eq!(legacy.details, ChainIdDetails::default());
+
+    let other = generate_and_fund_key(&mut svm);
+    assert!(submit_instruction(
+        &mut svm,
+        update_instruction(&other.pubkey(), false, "fogo-testnet", 1, &other.pubkey()),
+        &other
+    )
+    .is_err());
+
+    let authority = Pubkey::new_unique();
+    submit_instruction(
+        &mut svm,
+        update_instruction(
+            &upgrade_authority.pubkey(),
+            true,
+            "fogo-testnet",
+            1,
+            &authority,
+        ),
+        &upgrade_authority,
+    )
+    .unwrap();
+    let chain_id = get_chain_id(&svm);
+    assert_eq!(chain_id.chain_id, "fogo-testnet");
+    assert_eq!(
+        chain_id.details,
+        ChainIdDetails {
+            numeric_id: 1,
+            authority
+        }
+    );
+}
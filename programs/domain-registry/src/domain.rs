@@ -1,15 +1,25 @@
+use crate::error::DomainRegistryError;
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::hash::{hashv, HASH_BYTES};
 use anchor_lang::solana_program::pubkey::Pubkey;
+use fogo_sessions_sdk::domain_registry::domain::normalize_domain;
 
 const DOMAIN_RECORD_SEED: &[u8] = b"domain-record";
 #[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Debug)]
 pub struct Domain(String);
 
 impl Domain {
+    /// Checks that `domain` is a web origin and normalizes it, so that equivalent spellings of a domain share the same domain record.
+    /// See `normalize_domain` for the accepted format.
     pub fn new_checked(domain: &str) -> Result<Self> {
-        // TODO: Restrict the characters that can be used in the domain
-        Ok(Self(domain.to_string()))
+        normalize_domain(domain)
+            .map(Self)
+            .map_err(|_| error!(DomainRegistryError::InvalidDomain))
+    }
+
+    /// Keeps `domain` as it is, to address domain records created before domains were normalized
+    pub(crate) fn new_unchecked(domain: &str) -> Self {
+        Self(domain.to_string())
     }
 
     pub fn get_domain_hash(&self) -> [u8; HASH_BYTES] {
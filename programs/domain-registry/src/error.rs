@@ -8,4 +8,32 @@ pub enum DomainRegistryError {
     ProgramAlreadyAdded,
     #[msg("This program is not in the domain record")]
     ProgramNotFound,
+    #[msg("The header of the domain record could not be decoded")]
+    InvalidDomainRecordHeader,
+    #[msg("Session durations must be positive and the minimum can't exceed the maximum")]
+    InvalidSessionDurationLimits,
+    #[msg("The domain must be a web origin like https://app.example, without a path")]
+    InvalidDomain,
+    #[msg("The signer is neither the registry authority nor the admin of this domain")]
+    Unauthorized,
+    #[msg("The admin of this domain is already set")]
+    DomainAdminAlreadySet,
+    #[msg("The signer is not the admin of this domain")]
+    NotDomainAdmin,
+    #[msg("The signer is not the pending authority of the registry")]
+    NotPendingAuthority,
+    #[msg("The signer is not the pending admin of this domain")]
+    NotPendingDomainAdmin,
+    #[msg("The display name of the domain metadata must be set and the fields of the domain metadata can't exceed their maximum length")]
+    InvalidDomainMetadata,
+    #[msg("This domain doesn't have metadata to verify")]
+    MissingDomainMetadata,
+    #[msg("The legacy domain is already in its canonical form")]
+    DomainAlreadyCanonical,
+    #[msg("The domain record of the canonical domain already exists")]
+    DomainRecordAlreadyExists,
+    #[msg("The remaining accounts must be the program domains PDAs of the programs of the domain record, in order")]
+    InvalidProgramDomainsPda,
+    #[msg("The legacy domain record is too large to be moved")]
+    LegacyDomainRecordTooLarge,
 }
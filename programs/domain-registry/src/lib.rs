@@ -4,8 +4,11 @@ use crate::error::DomainRegistryError;
 use crate::state::Config;
 use crate::state::DomainProgram;
 use crate::state::DomainRecordInner;
-use crate::state::CONFIG_SEED;
+use crate::state::ProgramDomainsInner;
+use crate::state::{CONFIG_SEED, PROGRAM_DOMAINS_SEED};
+use crate::state::{MAX_CONTACT_LEN, MAX_DISPLAY_NAME_LEN, MAX_ICON_URI_LEN};
 use anchor_lang::prelude::*;
+use fogo_sessions_sdk::domain_registry::DomainMetadata;
 use fogo_sessions_sdk::token::PROGRAM_SIGNER_SEED;
 
 pub mod domain;
@@ -24,11 +27,28 @@ pub mod domain_registry {
         Ok(())
     }
 
-    pub fn update_authority<'info>(
-        ctx: Context<'_, '_, '_, 'info, UpdateAuthority<'info>>,
+    /// Grows a config account created before `pending_authority` existed to its current size, anyone can pay for it
+    pub fn migrate_config<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateConfig<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.migrate()
+    }
+
+    /// Proposes `new_authority` as the next authority of the registry, it only becomes the authority once it calls `accept_authority`.
+    /// Proposing again replaces the pending authority.
+    pub fn propose_authority<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProposeAuthority<'info>>,
         new_authority: Pubkey,
     ) -> Result<()> {
-        ctx.accounts.config.authority = new_authority;
+        ctx.accounts.config.pending_authority = Some(new_authority);
+        Ok(())
+    }
+
+    pub fn accept_authority<'info>(
+        ctx: Context<'_, '_, '_, 'info, AcceptAuthority<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.config.authority = ctx.accounts.pending_authority.key();
+        ctx.accounts.config.pending_authority = None;
         Ok(())
     }
 
@@ -44,10 +64,11 @@ pub mod domain_registry {
         );
         ctx.accounts.create_domain_record_if_needed(&domain)?; // We are creating the PDA outside of Anchor because Anchor doesn't support the seed to be a non-trivial function of the instruction arguments
 
-        let mut domain_record = DomainRecordInner::load(
+        let mut domain_record = DomainRecordInner::load_domain_record(
             ctx.accounts.domain_record.to_account_info(),
             ctx.accounts.authority.to_account_info(),
-        );
+        )?;
+        domain_record.check_authority(&ctx.accounts.config, ctx.accounts.authority.key())?;
         let domain_program = DomainProgram {
             program_id: ctx.accounts.program_id.key(),
             signer_pda: ctx.accounts.signer_pda.key(),
@@ -56,6 +77,23 @@ pub mod domain_registry {
             return Err(DomainRegistryError::ProgramAlreadyAdded.into());
         }
         domain_record.push(domain_program)?;
+
+        // Adding a program back lets the sessions that authorized it before its removal use it again
+        let mut header = domain_record.header()?;
+        if header.is_program_removed(&domain_program.program_id) {
+            header
+                .removed_programs
+                .retain(|program_id| *program_id != domain_program.program_id);
+            domain_record.set_header(&header)?;
+        }
+
+        ctx.accounts
+            .create_program_domains_if_needed(ctx.bumps.program_domains)?;
+        ProgramDomainsInner::load_program_domains(
+            ctx.accounts.program_domains.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+        )
+        .insert(domain.get_domain_hash())?;
         Ok(())
     }
 
@@ -69,16 +107,308 @@ pub mod domain_registry {
             domain.get_domain_record_address(),
             DomainRegistryError::InvalidDomainRecordPda
         );
-        let mut domain_record = DomainRecordInner::load(
+        let mut domain_record = DomainRecordInner::load_domain_record(
             ctx.accounts.domain_record.to_account_info(),
             ctx.accounts.authority.to_account_info(),
-        );
+        )?;
+        domain_record.check_authority(&ctx.accounts.config, ctx.accounts.authority.key())?;
         let index_to_remove = domain_record
             .position(|program: &DomainProgram| {
                 program.program_id == ctx.accounts.program_id.key()
             })?
             .ok_or(DomainRegistryError::ProgramNotFound)?;
         domain_record.swap_remove(index_to_remove)?;
+
+        // Sessions keep a snapshot of their programs, so the removal is recorded for the session checks to reject the program
+        let still_registered = domain_record
+            .position(|program: &DomainProgram| {
+                program.program_id == ctx.accounts.program_id.key()
+            })?
+            .is_some();
+        let mut header = domain_record.header()?;
+        if !still_registered && !header.is_program_removed(&ctx.accounts.program_id.key()) {
+            header.removed_programs.push(ctx.accounts.program_id.key());
+            domain_record.set_header(&header)?;
+        }
+
+        ProgramDomainsInner::load_program_domains(
+            ctx.accounts.program_domains.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+        )
+        .remove(domain.get_domain_hash())?;
+        Ok(())
+    }
+
+    /// Adds a domain to the index of the domains of a program registered before the index existed, anyone can pay for it
+    pub fn index_program<'info>(
+        ctx: Context<'_, '_, '_, 'info, IndexProgram<'info>>,
+        domain: String,
+    ) -> Result<()> {
+        let domain = Domain::new_checked(&domain)?;
+        require_eq!(
+            ctx.accounts.domain_record.key(),
+            domain.get_domain_record_address(),
+            DomainRegistryError::InvalidDomainRecordPda
+        );
+        let domain_record = DomainRecordInner::load_domain_record(
+            ctx.accounts.domain_record.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+        )?;
+        domain_record
+            .position(|program: &DomainProgram| {
+                program.program_id == ctx.accounts.program_id.key()
+            })?
+            .ok_or(DomainRegistryError::ProgramNotFound)?;
+
+        ctx.accounts
+            .create_program_domains_if_needed(ctx.bumps.program_domains)?;
+        ProgramDomainsInner::load_program_domains(
+            ctx.accounts.program_domains.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+        )
+        .insert(domain.get_domain_hash())
+    }
+
+    /// Sets the minimum and maximum duration of the sessions of a domain, in seconds. `None` leaves the duration unbounded, apart from the session manager's global maximum.
+    pub fn set_session_duration_limits<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetSessionDurationLimits<'info>>,
+        domain: String,
+        min_session_duration: Option<i64>,
+        max_session_duration: Option<i64>,
+    ) -> Result<()> {
+        let valid_limits = match (min_session_duration, max_session_duration) {
+            (Some(min), Some(max)) => 0 < min && min <= max,
+            (Some(duration), None) | (None, Some(duration)) => duration > 0,
+            (None, None) => true,
+        };
+        require!(
+            valid_limits,
+            DomainRegistryError::InvalidSessionDurationLimits
+        );
+        let domain = Domain::new_checked(&domain)?;
+        require_eq!(
+            ctx.accounts.domain_record.key(),
+            domain.get_domain_record_address(),
+            DomainRegistryError::InvalidDomainRecordPda
+        );
+        ctx.accounts.create_domain_record_if_needed(&domain)?;
+
+        let mut domain_record = DomainRecordInner::load_domain_record(
+            ctx.accounts.domain_record.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+        )?;
+        domain_record.check_authority(&ctx.accounts.config, ctx.accounts.authority.key())?;
+        let mut header = domain_record.header()?;
+        header.min_session_duration = min_session_duration;
+        header.max_session_duration = max_session_duration;
+        domain_record.set_header(&header)?;
+        Ok(())
+    }
+
+    /// Sets the metadata that wallets can show about the app of a domain, a `display_name` of `None` removes the metadata.
+    /// Changing the metadata clears its verification unless the registry authority does it.
+    pub fn set_domain_metadata<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetDomainMetadata<'info>>,
+        domain: String,
+        display_name: Option<String>,
+        icon_uri: Option<String>,
+        contact: Option<String>,
+    ) -> Result<()> {
+        let metadata = display_name.map(|display_name| DomainMetadata {
+            display_name,
+            icon_uri,
+            contact,
+        });
+        if let Some(metadata) = &metadata {
+            check_metadata(metadata)?;
+        }
+        let domain = Domain::new_checked(&domain)?;
+        require_eq!(
+            ctx.accounts.domain_record.key(),
+            domain.get_domain_record_address(),
+            DomainRegistryError::InvalidDomainRecordPda
+        );
+        ctx.accounts.create_domain_record_if_needed(&domain)?;
+
+        let mut domain_record = DomainRecordInner::load_domain_record(
+            ctx.accounts.domain_record.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+        )?;
+        domain_record.check_authority(&ctx.accounts.config, ctx.accounts.authority.key())?;
+        let mut header = domain_record.header()?;
+        if header.metadata != metadata {
+            header.verified &= ctx.accounts.authority.key() == ctx.accounts.config.authority;
+        }
+        header.metadata = metadata;
+        domain_record.set_header(&header)?;
+        Ok(())
+    }
+
+    /// Marks the metadata of a domain as checked, or not, by the registry authority
+    pub fn set_domain_verified<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetDomainVerified<'info>>,
+        domain: String,
+        verified: bool,
+    ) -> Result<()> {
+        let domain = Domain::new_checked(&domain)?;
+        require_eq!(
+            ctx.accounts.domain_record.key(),
+            domain.get_domain_record_address(),
+            DomainRegistryError::InvalidDomainRecordPda
+        );
+        let mut domain_record = DomainRecordInner::load_domain_record(
+            ctx.accounts.domain_record.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+        )?;
+        let mut header = domain_record.header()?;
+        require!(
+            !verified || header.metadata.is_some(),
+            DomainRegistryError::MissingDomainMetadata
+        );
+        header.verified = verified;
+        domain_record.set_header(&header)?;
+        Ok(())
+    }
+
+    /// Sets the admin of a domain, who can then manage the programs and settings of the domain without the registry authority.
+    /// The registry authority is expected to check that `admin` controls the domain, for example with a DNS record, before calling this instruction. The admin can only be set once, afterwards only the admin can transfer it.
+    pub fn set_domain_admin<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetDomainAdmin<'info>>,
+        domain: String,
+        admin: Pubkey,
+    ) -> Result<()> {
+        let domain = Domain::new_checked(&domain)?;
+        require_eq!(
+            ctx.accounts.domain_record.key(),
+            domain.get_domain_record_address(),
+            DomainRegistryError::InvalidDomainRecordPda
+        );
+        ctx.accounts.create_domain_record_if_needed(&domain)?;
+
+        let mut domain_record = DomainRecordInner::load_domain_record(
+            ctx.accounts.domain_record.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+        )?;
+        let mut header = domain_record.header()?;
+        require!(
+            header.admin.is_none(),
+            DomainRegistryError::DomainAdminAlreadySet
+        );
+        header.admin = Some(admin);
+        domain_record.set_header(&header)?;
+        Ok(())
+    }
+
+    /// Proposes `new_admin` as the next admin of a domain, it only becomes the admin once it calls `accept_domain_admin`.
+    /// Proposing again replaces the pending admin.
+    pub fn propose_domain_admin<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProposeDomainAdmin<'info>>,
+        domain: String,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        let domain = Domain::new_checked(&domain)?;
+        require_eq!(
+            ctx.accounts.domain_record.key(),
+            domain.get_domain_record_address(),
+            DomainRegistryError::InvalidDomainRecordPda
+        );
+        let mut domain_record = DomainRecordInner::load_domain_record(
+            ctx.accounts.domain_record.to_account_info(),
+            ctx.accounts.admin.to_account_info(),
+        )?;
+        let mut header = domain_record.header()?;
+        require!(
+            header.admin == Some(ctx.accounts.admin.key()),
+            DomainRegistryError::NotDomainAdmin
+        );
+        header.pending_admin = Some(new_admin);
+        domain_record.set_header(&header)?;
+        Ok(())
+    }
+
+    pub fn accept_domain_admin<'info>(
+        ctx: Context<'_, '_, '_, 'info, AcceptDomainAdmin<'info>>,
+        domain: String,
+    ) -> Result<()> {
+        let domain = Domain::new_checked(&domain)?;
+        require_eq!(
+            ctx.accounts.domain_record.key(),
+            domain.get_domain_record_address(),
+            DomainRegistryError::InvalidDomainRecordPda
+        );
+        let mut domain_record = DomainRecordInner::load_domain_record(
+            ctx.accounts.domain_record.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+        )?;
+        let mut header = domain_record.header()?;
+        require!(
+            header.pending_admin == Some(ctx.accounts.pending_admin.key()),
+            DomainRegistryError::NotPendingDomainAdmin
+        );
+        header.admin = header.pending_admin.take();
+        domain_record.set_header(&header)?;
+        Ok(())
+    }
+
+    /// Moves a domain record created before domains were normalized, under a non-canonical spelling `legacy_domain` of its domain like `https://App.xyz/`, to the domain record of the canonical domain.
+    /// The other instructions and new sessions only use the canonical domain record, so the legacy domain record can't be managed until it is moved. Sessions started with the legacy spelling that authorized the programs of their domain lose them once it is closed.
+    /// The remaining accounts are the program domains PDAs of the programs of the domain record, in order, which are moved to the canonical domain. Programs that aren't indexed yet are skipped.
+    pub fn migrate_domain_record<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MigrateDomainRecord<'info>>,
+        legacy_domain: String,
+    ) -> Result<()> {
+        let domain = Domain::new_checked(&legacy_domain)?;
+        let legacy_domain = Domain::new_unchecked(&legacy_domain);
+        require!(
+            legacy_domain != domain,
+            DomainRegistryError::DomainAlreadyCanonical
+        );
+        require_eq!(
+            ctx.accounts.legacy_domain_record.key(),
+            legacy_domain.get_domain_record_address(),
+            DomainRegistryError::InvalidDomainRecordPda
+        );
+        require_eq!(
+            ctx.accounts.domain_record.key(),
+            domain.get_domain_record_address(),
+            DomainRegistryError::InvalidDomainRecordPda
+        );
+        require_keys_eq!(
+            *ctx.accounts.domain_record.owner,
+            System::id(),
+            DomainRegistryError::DomainRecordAlreadyExists
+        );
+        ctx.accounts.move_domain_record(&domain)?;
+
+        let programs: Vec<DomainProgram> = DomainRecordInner::load_domain_record(
+            ctx.accounts.domain_record.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+        )?
+        .to_vec()?;
+        require_eq!(
+            ctx.remaining_accounts.len(),
+            programs.len(),
+            DomainRegistryError::InvalidProgramDomainsPda
+        );
+        for (program, program_domains) in programs.iter().zip(ctx.remaining_accounts) {
+            require_keys_eq!(
+                program_domains.key(),
+                Pubkey::find_program_address(
+                    &[PROGRAM_DOMAINS_SEED, program.program_id.as_ref()],
+                    &ID
+                )
+                .0,
+                DomainRegistryError::InvalidProgramDomainsPda
+            );
+            if program_domains.owner == &ID {
+                let mut program_domains = ProgramDomainsInner::load_program_domains(
+                    program_domains.clone(),
+                    ctx.accounts.authority.to_account_info(),
+                );
+                program_domains.remove(legacy_domain.get_domain_hash())?;
+                program_domains.insert(domain.get_domain_hash())?;
+            }
+        }
         Ok(())
     }
 }
@@ -87,24 +417,47 @@ pub mod domain_registry {
 pub struct Initialize<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
-    #[account(init, payer = authority, space = 8 + 32, seeds = [CONFIG_SEED], bump)]
+    #[account(init, payer = authority, space = Config::LEN, seeds = [CONFIG_SEED], bump)]
     pub config: Account<'info, Config>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateAuthority<'info> {
+pub struct MigrateConfig<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Config accounts created before `pending_authority` existed can't be deserialized, we check the address and the owner
+    #[account(mut, seeds = [CONFIG_SEED], bump, owner = ID)]
+    pub config: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
     pub authority: Signer<'info>,
     #[account(mut, seeds = [CONFIG_SEED], bump, has_one = authority)]
     pub config: Account<'info, Config>,
 }
 
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub pending_authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = config.pending_authority == Some(pending_authority.key()) @ DomainRegistryError::NotPendingAuthority
+    )]
+    pub config: Account<'info, Config>,
+}
+
 #[derive(Accounts)]
 #[instruction(domain: String)]
 pub struct AddProgram<'info> {
+    /// The registry authority or the admin of the domain
     #[account(mut)]
     pub authority: Signer<'info>,
-    #[account(seeds = [CONFIG_SEED], bump, has_one = authority)]
+    #[account(seeds = [CONFIG_SEED], bump)]
     pub config: Account<'info, Config>,
     /// CHECK: We will do the checks in the function since Anchor isn't expressive enough
     #[account(mut)]
@@ -114,37 +467,310 @@ pub struct AddProgram<'info> {
     /// CHECK: We check the PDA derivation
     #[account(seeds = [PROGRAM_SIGNER_SEED], bump, seeds::program = program_id.key())]
     pub signer_pda: AccountInfo<'info>,
+    /// CHECK: The index of the domains of `program_id`, we create it outside of Anchor since it is resized as domains are added and removed
+    #[account(mut, seeds = [PROGRAM_DOMAINS_SEED, program_id.key().as_ref()], bump)]
+    pub program_domains: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(domain: String)]
 pub struct RemoveProgram<'info> {
+    /// The registry authority or the admin of the domain
     #[account(mut)]
     pub authority: Signer<'info>,
-    #[account(seeds = [CONFIG_SEED], bump, has_one = authority)]
+    #[account(seeds = [CONFIG_SEED], bump)]
     pub config: Account<'info, Config>,
     /// CHECK: We will do the checks in the function since Anchor isn't expressive enough
     #[account(mut)]
     pub domain_record: AccountInfo<'info>,
     /// CHECK: The only requirement for program_id is that it is a valid Pubkey
     pub program_id: AccountInfo<'info>,
+    /// CHECK: The index of the domains of `program_id`
+    #[account(mut, seeds = [PROGRAM_DOMAINS_SEED, program_id.key().as_ref()], bump)]
+    pub program_domains: AccountInfo<'info>,
     pub system_program: Program<'info, System>,
 }
 
-impl<'info> AddProgram<'info> {
-    fn create_domain_record_if_needed(&self, domain: &Domain) -> Result<()> {
-        if self.domain_record.owner == &System::id() {
-            system_program::create_pda(
-                &self.authority,
-                &self.domain_record,
-                &self.system_program,
-                &ID,
-                &Rent::get()?,
-                0,
-                domain.get_seeds(),
+#[derive(Accounts)]
+#[instruction(domain: String)]
+pub struct IndexProgram<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: We will do the checks in the function since Anchor isn't expressive enough
+    pub domain_record: AccountInfo<'info>,
+    /// CHECK: The only requirement for program_id is that it is a valid Pubkey
+    pub program_id: AccountInfo<'info>,
+    /// CHECK: The index of the domains of `program_id`
+    #[account(mut, seeds = [PROGRAM_DOMAINS_SEED, program_id.key().as_ref()], bump)]
+    pub program_domains: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(domain: String)]
+pub struct SetSessionDurationLimits<'info> {
+    /// The registry authority or the admin of the domain
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: We will do the checks in the function since Anchor isn't expressive enough
+    #[account(mut)]
+    pub domain_record: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(domain: String)]
+pub struct SetDomainAdmin<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [CONFIG_SEED], bump, has_one = authority)]
+    pub config: Account<'info, Config>,
+    /// CHECK: We will do the checks in the function since Anchor isn't expressive enough
+    #[account(mut)]
+    pub domain_record: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(domain: String)]
+pub struct ProposeDomainAdmin<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// CHECK: We will do the checks in the function since Anchor isn't expressive enough
+    #[account(mut)]
+    pub domain_record: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(domain: String)]
+pub struct AcceptDomainAdmin<'info> {
+    /// Receives the rent freed by clearing the pending admin, so that the pending admin doesn't need to hold lamports
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub pending_admin: Signer<'info>,
+    /// CHECK: We will do the checks in the function since Anchor isn't expressive enough
+    #[account(mut)]
+    pub domain_record: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(legacy_domain: String)]
+pub struct MigrateDomainRecord<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [CONFIG_SEED], bump, has_one = authority)]
+    pub config: Account<'info, Config>,
+    /// CHECK: We check the PDA derivation in the handler, since its seeds depend on the hash of `legacy_domain`
+    #[account(mut, owner = ID)]
+    pub legacy_domain_record: AccountInfo<'info>,
+    /// CHECK: We will do the checks in the function since Anchor isn't expressive enough
+    #[account(mut)]
+    pub domain_record: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateConfig<'info> {
+    fn migrate(&self) -> Result<()> {
+        if self.config.data_len() >= Config::LEN {
+            return Ok(());
+        }
+        self.config.realloc(Config::LEN, true)?;
+        let required_lamports = Rent::get()?
+            .minimum_balance(Config::LEN)
+            .saturating_sub(self.config.lamports());
+        if required_lamports > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: self.payer.to_account_info(),
+                        to: self.config.to_account_info(),
+                    },
+                ),
+                required_lamports,
             )?;
         }
         Ok(())
     }
 }
+
+impl<'info> MigrateDomainRecord<'info> {
+    /// Creates the domain record of `domain` with the content of the legacy domain record and closes the legacy domain record
+    fn move_domain_record(&self, domain: &Domain) -> Result<()> {
+        let data = self.legacy_domain_record.try_borrow_data()?.to_vec();
+        system_program::create_pda(
+            &self.authority,
+            &self.domain_record,
+            &self.system_program,
+            &ID,
+            &Rent::get()?,
+            u64::try_from(data.len())
+                .map_err(|_| DomainRegistryError::LegacyDomainRecordTooLarge)?,
+            domain.get_seeds(),
+        )?;
+        self.domain_record
+            .try_borrow_mut_data()?
+            .copy_from_slice(&data);
+
+        let lamports = self.legacy_domain_record.lamports();
+        **self.legacy_domain_record.try_borrow_mut_lamports()? -= lamports;
+        **self.authority.try_borrow_mut_lamports()? += lamports;
+        self.legacy_domain_record.realloc(0, false)?;
+        self.legacy_domain_record.assign(&System::id());
+        Ok(())
+    }
+}
+
+impl<'info> AddProgram<'info> {
+    fn create_domain_record_if_needed(&self, domain: &Domain) -> Result<()> {
+        create_domain_record_if_needed(
+            &self.authority,
+            &self.domain_record,
+            &self.system_program,
+            domain,
+        )
+    }
+
+    fn create_program_domains_if_needed(&self, bump: u8) -> Result<()> {
+        create_program_domains_if_needed(
+            &self.authority,
+            &self.program_domains,
+            &self.system_program,
+            &self.program_id.key(),
+            bump,
+        )
+    }
+}
+
+impl<'info> IndexProgram<'info> {
+    fn create_program_domains_if_needed(&self, bump: u8) -> Result<()> {
+        create_program_domains_if_needed(
+            &self.payer,
+            &self.program_domains,
+            &self.system_program,
+            &self.program_id.key(),
+            bump,
+        )
+    }
+}
+
+impl<'info> SetSessionDurationLimits<'info> {
+    fn create_domain_record_if_needed(&self, domain: &Domain) -> Result<()> {
+        create_domain_record_if_needed(
+            &self.authority,
+            &self.domain_record,
+            &self.system_program,
+            domain,
+        )
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(domain: String)]
+pub struct SetDomainMetadata<'info> {
+    /// The registry authority or the admin of the domain
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: We will do the checks in the function since Anchor isn't expressive enough
+    #[account(mut)]
+    pub domain_record: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(domain: String)]
+pub struct SetDomainVerified<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [CONFIG_SEED], bump, has_one = authority)]
+    pub config: Account<'info, Config>,
+    /// CHECK: We will do the checks in the function since Anchor isn't expressive enough
+    #[account(mut)]
+    pub domain_record: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetDomainMetadata<'info> {
+    fn create_domain_record_if_needed(&self, domain: &Domain) -> Result<()> {
+        create_domain_record_if_needed(
+            &self.authority,
+            &self.domain_record,
+            &self.system_program,
+            domain,
+        )
+    }
+}
+
+impl<'info> SetDomainAdmin<'info> {
+    fn create_domain_record_if_needed(&self, domain: &Domain) -> Result<()> {
+        create_domain_record_if_needed(
+            &self.authority,
+            &self.domain_record,
+            &self.system_program,
+            domain,
+        )
+    }
+}
+
+fn check_metadata(metadata: &DomainMetadata) -> Result<()> {
+    require!(
+        !metadata.display_name.is_empty()
+            && metadata.display_name.len() <= MAX_DISPLAY_NAME_LEN
+            && metadata.icon_uri.as_deref().map_or(0, str::len) <= MAX_ICON_URI_LEN
+            && metadata.contact.as_deref().map_or(0, str::len) <= MAX_CONTACT_LEN,
+        DomainRegistryError::InvalidDomainMetadata
+    );
+    Ok(())
+}
+
+fn create_domain_record_if_needed<'info>(
+    payer: &AccountInfo<'info>,
+    domain_record: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    domain: &Domain,
+) -> Result<()> {
+    if domain_record.owner == &System::id() {
+        system_program::create_pda(
+            payer,
+            domain_record,
+            system_program,
+            &ID,
+            &Rent::get()?,
+            0,
+            domain.get_seeds(),
+        )?;
+    }
+    Ok(())
+}
+
+fn create_program_domains_if_needed<'info>(
+    payer: &AccountInfo<'info>,
+    program_domains: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    program_id: &Pubkey,
+    bump: u8,
+) -> Result<()> {
+    if program_domains.owner == &System::id() {
+        system_program::create_pda(
+            payer,
+            program_domains,
+            system_program,
+            &ID,
+            &Rent::get()?,
+            0,
+            vec![
+                PROGRAM_DOMAINS_SEED.to_vec(),
+                program_id.to_bytes().to_vec(),
+                vec![bump],
+            ],
+        )?;
+    }
+    Ok(())
+}
@@ -1,12 +1,28 @@
+use crate::error::DomainRegistryError;
 use anchor_lang::{prelude::*, solana_program::system_instruction};
 use bytemuck::{Pod, Zeroable};
+use fogo_sessions_sdk::domain_registry::{split_domain_record, DomainRecordHeader};
 use fogo_sessions_sdk::session::AuthorizedProgram;
 use std::marker::PhantomData;
 
 pub const CONFIG_SEED: &[u8] = b"config";
+pub const PROGRAM_DOMAINS_SEED: &[u8] = b"program-domains";
+
+/// The longest display name allowed in domain metadata, in bytes
+pub const MAX_DISPLAY_NAME_LEN: usize = 64;
+/// The longest icon URI allowed in domain metadata, in bytes
+pub const MAX_ICON_URI_LEN: usize = 256;
+/// The longest contact allowed in domain metadata, in bytes
+pub const MAX_CONTACT_LEN: usize = 128;
 #[account]
 pub struct Config {
     pub authority: Pubkey,
+    /// The key proposed as the next authority, it becomes the authority once it accepts
+    pub pending_authority: Option<Pubkey>,
+}
+
+impl Config {
+    pub const LEN: usize = 8 + 32 + 1 + 32;
 }
 
 #[derive(Pod, Zeroable, PartialEq, Copy, Clone)]
@@ -27,16 +43,81 @@ impl From<DomainProgram> for AuthorizedProgram {
 
 pub type DomainRecordInner<'a> = resizable_account_array::ResizableAccountArray<'a, DomainProgram>;
 
+/// Returns the header of a domain record, domain records without a header have the default header.
+/// This doesn't check the address of `domain_record`.
+pub fn get_domain_record_header(domain_record: &AccountInfo) -> Result<DomainRecordHeader> {
+    let data = domain_record.try_borrow_data()?;
+    let (header, _) =
+        split_domain_record(&data).map_err(|_| DomainRegistryError::InvalidDomainRecordHeader)?;
+    Ok(header)
+}
+
+impl<'a> DomainRecordInner<'a> {
+    /// Loads a domain record, whose `DomainProgram` entries may be preceded by a header
+    pub fn load_domain_record(acc_info: AccountInfo<'a>, payer: AccountInfo<'a>) -> Result<Self> {
+        let offset = {
+            let data = acc_info.try_borrow_data()?;
+            let (_, programs) = split_domain_record(&data)
+                .map_err(|_| DomainRegistryError::InvalidDomainRecordHeader)?;
+            data.len() - programs.len()
+        };
+        Ok(Self::load(acc_info, payer, offset))
+    }
+
+    pub fn header(&self) -> Result<DomainRecordHeader> {
+        get_domain_record_header(&self.acc_info)
+    }
+
+    pub fn set_header(&mut self, header: &DomainRecordHeader) -> Result<()> {
+        self.set_prefix(&header.to_prefix())
+    }
+
+    /// Checks that `signer` can manage this domain, either as the registry authority or as the admin of the domain
+    pub fn check_authority(&self, config: &Config, signer: Pubkey) -> Result<()> {
+        require!(
+            signer == config.authority || self.header()?.admin == Some(signer),
+            DomainRegistryError::Unauthorized
+        );
+        Ok(())
+    }
+}
+
+/// The hashes of the domains whose domain record contains a program, stored in a PDA per program
+pub type ProgramDomainsInner<'a> = resizable_account_array::ResizableAccountArray<'a, [u8; 32]>;
+
+impl<'a> ProgramDomainsInner<'a> {
+    pub fn load_program_domains(acc_info: AccountInfo<'a>, payer: AccountInfo<'a>) -> Self {
+        Self::load(acc_info, payer, 0)
+    }
+
+    /// Adds `domain_hash` unless the program is already indexed under it
+    pub fn insert(&mut self, domain_hash: [u8; 32]) -> Result<()> {
+        if !self.contains(domain_hash)? {
+            self.push(domain_hash)?;
+        }
+        Ok(())
+    }
+
+    /// Removes `domain_hash` if the program is indexed under it. Programs added before the index existed may not be.
+    pub fn remove(&mut self, domain_hash: [u8; 32]) -> Result<()> {
+        if let Some(index) = self.position(|hash| *hash == domain_hash)? {
+            self.swap_remove(index)?;
+        }
+        Ok(())
+    }
+}
+
 mod resizable_account_array {
     use super::*;
 
-    /// A account that contains a dynamic array of `T` and dynamically resizes using funds from the `payer` account.
+    /// A account that contains a dynamic array of `T`, after a prefix of `offset` bytes, and dynamically resizes using funds from the `payer` account.
     pub struct ResizableAccountArray<'a, T>
     where
         T: Pod + Zeroable + PartialEq,
     {
         pub acc_info: AccountInfo<'a>,
         pub payer: AccountInfo<'a>,
+        pub offset: usize,
         pub _phantom: PhantomData<T>,
     }
 
@@ -44,10 +125,11 @@ mod resizable_account_array {
     where
         T: Pod + Zeroable + PartialEq,
     {
-        pub fn load(acc_info: AccountInfo<'a>, payer: AccountInfo<'a>) -> Self {
+        pub fn load(acc_info: AccountInfo<'a>, payer: AccountInfo<'a>, offset: usize) -> Self {
             Self {
                 acc_info,
                 payer,
+                offset,
                 _phantom: PhantomData,
             }
         }
@@ -70,7 +152,7 @@ mod resizable_account_array {
             // swap the element at index `index` with the last element
             {
                 let mut data = self.acc_info.try_borrow_mut_data()?;
-                let elements = bytemuck::cast_slice_mut::<_, T>(&mut data);
+                let elements = bytemuck::cast_slice_mut::<_, T>(self.elements_mut(&mut data)?);
                 if index < elements.len() {
                     elements.swap(index, elements.len() - 1);
                 } else {
@@ -87,11 +169,13 @@ mod resizable_account_array {
 
         pub fn contains(&self, value: T) -> Result<bool> {
             let data = self.acc_info.try_borrow_data()?;
-            Ok(bytemuck::cast_slice(&data).contains(&value))
+            Ok(bytemuck::cast_slice(self.elements(&data)?).contains(&value))
         }
         pub fn position(&self, predicate: impl Fn(&T) -> bool) -> Result<Option<usize>> {
             let data = self.acc_info.try_borrow_data()?;
-            Ok(bytemuck::cast_slice(&data).iter().position(predicate))
+            Ok(bytemuck::cast_slice(self.elements(&data)?)
+                .iter()
+                .position(predicate))
         }
 
         pub fn to_vec<U>(&self) -> Result<Vec<U>>
@@ -99,19 +183,47 @@ mod resizable_account_array {
             U: From<T>,
         {
             let data = self.acc_info.try_borrow_data()?;
-            Ok(bytemuck::cast_slice(&data)
+            Ok(bytemuck::cast_slice(self.elements(&data)?)
                 .iter()
                 .map(|item: &T| (*item).into())
                 .collect())
         }
 
+        /// Replaces the bytes that precede the array, moving the elements after the new prefix
+        pub fn set_prefix(&mut self, prefix: &[u8]) -> Result<()> {
+            let elements = {
+                let data = self.acc_info.try_borrow_data()?;
+                self.elements(&data)?.to_vec()
+            };
+            self.acc_info
+                .realloc(prefix.len() + elements.len(), false)?;
+            {
+                let mut data = self.acc_info.try_borrow_mut_data()?;
+                let (new_prefix, new_elements) = data.split_at_mut(prefix.len());
+                new_prefix.copy_from_slice(prefix);
+                new_elements.copy_from_slice(&elements);
+            }
+            self.offset = prefix.len();
+            self.adjust_rent_if_needed()?;
+            Ok(())
+        }
+
+        fn elements<'b>(&self, data: &'b [u8]) -> Result<&'b [u8]> {
+            data.get(self.offset..)
+                .ok_or(ProgramError::AccountDataTooSmall.into())
+        }
+
+        fn elements_mut<'b>(&self, data: &'b mut [u8]) -> Result<&'b mut [u8]> {
+            data.get_mut(self.offset..)
+                .ok_or(ProgramError::AccountDataTooSmall.into())
+        }
+
         fn extend(&mut self) -> Result<()> {
             self.acc_info
                 .realloc(self.acc_info.data_len() + size_of::<T>(), false)?;
             self.adjust_rent_if_needed()?;
             Ok(())
         }
-
         fn adjust_rent_if_needed(&mut self) -> Result<()> {
             let rent = Rent::get()?;
             let amount_required = if self.acc_info.data_is_empty() {
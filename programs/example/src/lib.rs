@@ -6,7 +6,7 @@ use anchor_lang::solana_program::program::invoke_signed;
 use anchor_spl::associated_token::get_associated_token_address;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 use fogo_sessions_sdk::session::is_session;
-use fogo_sessions_sdk::token::instruction::transfer_checked;
+use fogo_sessions_sdk::token::instruction::{append_domain_record, transfer_checked};
 use fogo_sessions_sdk::{session::Session, token::PROGRAM_SIGNER_SEED};
 
 declare_id!("Examtz9qAwhxcADNFodNA2QpxK7SM9bCHyiaUvWvFBM3");
@@ -16,10 +16,17 @@ pub mod example {
     use super::*;
     pub fn example_transfer(ctx: Context<ExampleTransfer>, amount: u64) -> Result<()> {
         // Extract the user public key from the signing account
-        let user = Session::extract_user_from_signer_or_session(
-            &ctx.accounts.signer_or_session,
-            &crate::ID,
-        )
+        let user = match ctx.accounts.domain_record.as_ref() {
+            Some(domain_record) => Session::extract_user_from_signer_or_session_with_domain_record(
+                &ctx.accounts.signer_or_session,
+                &crate::ID,
+                domain_record,
+            ),
+            None => Session::extract_user_from_signer_or_session(
+                &ctx.accounts.signer_or_session,
+                &crate::ID,
+            ),
+        }
         .map_err(ProgramError::from)?;
 
         // Check that user_token_account is the user's associated token account
@@ -28,7 +35,7 @@ pub mod example {
             ctx.accounts.user_token_account.key()
         );
 
-        let instruction = transfer_checked(
+        let mut instruction = transfer_checked(
             ctx.accounts.token_program.key,
             &ctx.accounts.user_token_account.key(),
             &ctx.accounts.mint.key(),
@@ -42,6 +49,9 @@ pub mod example {
             amount,
             ctx.accounts.mint.decimals,
         )?;
+        if let Some(domain_record) = ctx.accounts.domain_record.as_ref() {
+            append_domain_record(&mut instruction, domain_record.key);
+        }
 
         let is_session = is_session(&ctx.accounts.signer_or_session);
 
@@ -83,4 +93,7 @@ pub struct ExampleTransfer<'info> {
     #[account(mut, token::mint = mint)]
     pub sink: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
+    /// The domain record of the session's domain. It's needed for domain-scoped sessions and also rejects programs removed from the domain of other sessions.
+    /// CHECK: we check it's the domain record of the session's domain in `extract_user_from_signer_or_session_with_domain_record`
+    pub domain_record: Option<AccountInfo<'info>>,
 }
@@ -286,7 +286,7 @@ impl<'info> BridgeNttTokens<'info> {
             fee_symbol_or_mint,
         } = ntt_message;
 
-        if from_chain_id.chain_id != expected_chain_id {
+        if !from_chain_id.matches(&expected_chain_id) {
             return err!(IntentTransferError::ChainIdMismatch);
         }
 
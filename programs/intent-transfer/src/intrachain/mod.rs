@@ -1,2 +1,2 @@
-mod message;
+pub mod message;
 pub mod processor;
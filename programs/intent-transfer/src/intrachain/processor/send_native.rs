@@ -104,7 +104,7 @@ impl<'info> SendNative<'info> {
         } = Intent::load(sysvar_instructions.as_ref())
             .map_err(Into::<IntentTransferError>::into)?;
 
-        if chain_id.chain_id != expected_chain_id {
+        if !chain_id.matches(&expected_chain_id) {
             return err!(IntentTransferError::ChainIdMismatch);
         }
 
@@ -8,7 +8,7 @@ pub mod bridge;
 pub mod config;
 mod error;
 mod fees;
-mod intrachain;
+pub mod intrachain;
 mod nonce;
 mod verify;
 
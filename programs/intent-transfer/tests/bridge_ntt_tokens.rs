@@ -1,5 +1,7 @@
 use anchor_lang::{
-    solana_program::{ed25519_program, instruction::Instruction, pubkey::Pubkey, sysvar},
+    solana_program::{
+        bpf_loader_upgradeable, ed25519_program, instruction::Instruction, pubkey::Pubkey, sysvar,
+    },
     system_program, AnchorSerialize, Discriminator, InstructionData, ToAccountMetas,
 };
 use anchor_spl::{associated_token, token_2022::spl_token_2022::try_ui_amount_into_amount};
@@ -154,16 +156,24 @@ fn test_bridge_ntt_tokens_with_mock_wh() {
 
     let chain_id_value = "solana".to_string();
 
+    helpers::set_upgrade_authority(&mut svm, &chain_id::ID, &payer.pubkey());
     let set_chain_id_ix = Instruction {
         program_id: chain_id::ID,
         accounts: chain_id::accounts::Set {
             chain_id_account,
             sponsor: payer.pubkey(),
+            program_data: Pubkey::find_program_address(
+                &[chain_id::ID.as_ref()],
+                &bpf_loader_upgradeable::ID,
+            )
+            .0,
             system_program: system_program::ID,
         }
         .to_account_metas(None),
         data: chain_id::instruction::Set {
             chain_id: chain_id_value.clone(),
+            numeric_id: 1,
+            authority: payer.pubkey(),
         }
         .data(),
     };
@@ -1,5 +1,9 @@
-use anchor_lang::{prelude::Pubkey, solana_program::system_instruction};
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{bpf_loader_upgradeable, system_instruction},
+};
 use litesvm::{types::TransactionResult, LiteSVM};
+use solana_account::Account;
 use solana_instruction::Instruction;
 use solana_keypair::Keypair;
 use solana_signer::Signer;
@@ -13,6 +17,29 @@ use spl_token::{
     solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack},
 };
 
+/// Creates the program data account of `program_id` with `upgrade_authority`, for programs that check their upgrade authority
+pub fn set_upgrade_authority(svm: &mut LiteSVM, program_id: &Pubkey, upgrade_authority: &Pubkey) {
+    // The bincode encoding of `UpgradeableLoaderState::ProgramData { slot: 0, upgrade_authority_address: Some(upgrade_authority) }`
+    let data = [
+        3u32.to_le_bytes().as_slice(),
+        &0u64.to_le_bytes(),
+        &[1],
+        upgrade_authority.as_ref(),
+    ]
+    .concat();
+    svm.set_account(
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID).0,
+        Account {
+            lamports: svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: bpf_loader_upgradeable::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+}
+
 pub fn generate_and_fund_key(svm: &mut litesvm::LiteSVM) -> Keypair {
     let keypair = Keypair::new();
     let pubkey = keypair.pubkey();
@@ -1,18 +1,27 @@
 use crate::error::SessionManagerError;
 use anchor_lang::prelude::*;
 use chrono::{DateTime, FixedOffset};
+use fogo_sessions_sdk::domain_registry::DomainRecordHeader;
 
-const MAX_SESSION_DURATION: i64 = 8 * 24 * 60 * 60; // 8 days
+pub const MAX_SESSION_DURATION: i64 = 8 * 24 * 60 * 60; // 8 days
 
-/// Checks that the expiration is not too far in the future and returns the expiration as a Unix timestamp in seconds
-pub fn check_expiration(expiration: DateTime<FixedOffset>) -> Result<i64> {
+/// Checks that the session duration is within the bounds set by the domain, which can't exceed `MAX_SESSION_DURATION`, and returns the expiration as a Unix timestamp in seconds
+pub fn check_expiration(
+    expiration: DateTime<FixedOffset>,
+    domain_record_header: &DomainRecordHeader,
+) -> Result<i64> {
     let timestamp = expiration.timestamp();
-    if timestamp
-        > Clock::get()?
-            .unix_timestamp
-            .saturating_add(MAX_SESSION_DURATION)
-    {
+    let now = Clock::get()?.unix_timestamp;
+    let max_session_duration = domain_record_header
+        .max_session_duration
+        .map_or(MAX_SESSION_DURATION, |max| max.min(MAX_SESSION_DURATION));
+    if timestamp > now.saturating_add(max_session_duration) {
         return err!(SessionManagerError::SessionTooLong);
     }
+    if let Some(min_session_duration) = domain_record_header.min_session_duration {
+        if timestamp < now.saturating_add(min_session_duration) {
+            return err!(SessionManagerError::SessionTooShort);
+        }
+    }
     Ok(timestamp)
 }
@@ -44,6 +44,40 @@ pub enum SessionManagerError {
     SessionIsLive,
     #[msg("The provided expiration is too far in the future")]
     SessionTooLong,
+    #[msg("The signer of the intent is not the user of the session")]
+    UserMismatch,
+    #[msg("The domain in the signed intent doesn't match the domain of the session")]
+    DomainMismatch,
+    #[msg("The new expiration must be later than the current expiration of the session")]
+    ExpirationNotExtended,
+    #[msg("This session was revoked")]
+    SessionIsRevoked,
+    #[msg("This session has expired")]
+    SessionIsExpired,
+    #[msg("The account is not owned by a supported token program")]
+    InvalidTokenProgram,
+    #[msg("The program to remove is not authorized in this session")]
+    ProgramNotInSession,
+    #[msg("The mint to revoke is not authorized in this session")]
+    MintNotInSession,
+    #[msg("Sessions without limits can't be restricted")]
+    UnlimitedSessionCannotBeRestricted,
+    #[msg("Rate-limited tokens are not supported in this session version")]
+    RateLimitNotSupported,
+    #[msg("The provided mint is not rate-limited in this session")]
+    MintNotRateLimited,
+    #[msg(
+        "The user sessions account provided is not the user sessions account of the session's user"
+    )]
+    UserSessionsMismatch,
+    #[msg("Domain-scoped sessions are not supported in this session version")]
+    DomainScopeNotSupported,
+    #[msg("The programs of domain-scoped sessions can't be restricted")]
+    DomainScopedSessionCannotBeRestricted,
+    #[msg("The provided expiration is shorter than the minimum session duration of the domain")]
+    SessionTooShort,
+    #[msg("The tokens section of the intent doesn't match the tokens of the session")]
+    TokensMismatch,
 }
 
 impl From<IntentError<<Message as TryFrom<Vec<u8>>>::Error>> for SessionManagerError {
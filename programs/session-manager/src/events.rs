@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use fogo_sessions_sdk::session::DomainHash;
+
+/// A token the session key was allowed to spend when the session started
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct TokenLimit {
+    pub mint: Pubkey,
+    /// The amount of tokens in base units, for rate-limited tokens this is the amount allowed per period
+    pub amount: u64,
+    /// The length of a period in seconds, only for rate-limited tokens
+    pub period: Option<i64>,
+}
+
+#[event]
+pub struct SessionStarted {
+    pub session: Pubkey,
+    pub user: Pubkey,
+    pub sponsor: Pubkey,
+    pub domain_hash: DomainHash,
+    pub expiration: i64,
+    /// The tokens the session key is allowed to spend, `None` if it is allowed to spend any token
+    pub token_limits: Option<Vec<TokenLimit>>,
+}
+
+/// Emitted when an active session is revoked, revoking an already revoked session doesn't emit this event
+#[event]
+pub struct SessionRevoked {
+    pub session: Pubkey,
+    pub user: Pubkey,
+}
+
+#[event]
+pub struct SessionClosed {
+    pub session: Pubkey,
+    pub user: Pubkey,
+    pub sponsor: Pubkey,
+    /// The mints whose delegations to the session key were revoked
+    pub revoked_mints: Vec<Pubkey>,
+    /// The rent of the session account, refunded to the sponsor
+    pub refunded_rent: u64,
+}
+
+/// Emitted instead of `SessionStarted` and `SessionClosed` when a session is moved to a new session key, the new session keeps the token limits of the old one
+#[event]
+pub struct SessionKeyRotated {
+    pub old_session: Pubkey,
+    pub new_session: Pubkey,
+    pub user: Pubkey,
+    pub sponsor: Pubkey,
+    pub domain_hash: DomainHash,
+    pub expiration: i64,
+}
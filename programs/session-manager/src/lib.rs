@@ -2,28 +2,50 @@
 #![allow(deprecated)] // warning: use of deprecated method `anchor_lang::prelude::AccountInfo::<'a>::realloc`: Use AccountInfo::resize() instead
 
 use crate::error::SessionManagerError;
-use crate::message::{Message, Tokens};
-use crate::token::approve::convert_remaning_accounts_and_token_limits_to_pending_approvals;
-use crate::token::revoke::convert_remaining_accounts_and_mints_to_revoke_to_pending_revocations;
+use crate::events::{SessionClosed, SessionKeyRotated, SessionRevoked, SessionStarted, TokenLimit};
+use crate::message::{Message, Programs, RevocationMessage, Tokens};
+use crate::token::approve::{
+    convert_remaining_accounts_and_delegations_to_pending_approvals,
+    convert_remaning_accounts_and_token_limits_to_pending_approvals, get_native_allowance,
+    get_rate_limits,
+};
+use crate::token::revoke::{
+    convert_remaining_accounts_and_mints_to_revoke_to_pending_revocations, revoke_delegations,
+};
+use crate::user_sessions::{remove_from_user_sessions, split_user_sessions};
 use anchor_lang::solana_program::borsh0_10::get_instance_packed_len;
 use anchor_lang::{prelude::*, solana_program::sysvar::instructions};
-use anchor_spl::token::Token;
-use domain_registry::{domain::Domain, state::DomainRecordInner};
-use fogo_sessions_sdk::session::ActiveSessionInfoWithDomainHash;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_interface::TokenInterface;
+use domain_registry::{
+    domain::Domain,
+    state::{get_domain_record_header, DomainRecordInner},
+};
+use fogo_sessions_sdk::domain_registry::DomainRecordHeader;
+use fogo_sessions_sdk::session::intent::{
+    DOMAIN_SCOPE_MINOR_VERSION, RATE_LIMITS_MINOR_VERSION, SUPPORTED_MINOR_VERSIONS,
+};
+use fogo_sessions_sdk::session::{
+    ActiveSessionInfo, AuthorizedPrograms, AuthorizedTokens, AuthorizedTokensWithMints,
+    NativeAllowance, RevokedSessionInfo, Session, SessionInfo, V2, V3, V4, V5,
+};
 use fogo_sessions_sdk::session::{
-    ActiveSessionInfo, AuthorizedProgram, AuthorizedPrograms, AuthorizedTokens,
-    AuthorizedTokensWithMints, RevokedSessionInfo, Session, SessionInfo, V2, V3, V4,
+    ActiveSessionInfoWithDomainHash, ActiveSessionInfoWithDomainHashAndNativeAllowance,
+    ActiveSessionInfoWithRateLimits, V6,
 };
 use solana_intents::Intent;
 use solana_intents::Version;
 
 declare_id!("SesswvJ7puvAgpyqp7N8HnjNnvpnS8447tKNF3sPgbC");
 
-mod clock;
+pub mod clock;
 pub mod error;
-mod message;
+pub mod events;
+pub mod message;
+mod restrict;
 mod system_program;
 mod token;
+mod user_sessions;
 
 const SESSION_SETTER_SEED: &[u8] = b"session_setter";
 
@@ -31,6 +53,7 @@ const SESSION_SETTER_SEED: &[u8] = b"session_setter";
 pub mod session_manager {
     use super::*;
 
+    /// The user sessions account of the user can be passed as the first remaining account, before the token accounts, to add the session to the index of the user's sessions.
     #[instruction(discriminator = [0])]
     pub fn start_session<'info>(
         ctx: Context<'_, '_, '_, 'info, StartSession<'info>>,
@@ -45,42 +68,90 @@ pub mod session_manager {
                     expires,
                     session_key,
                     tokens,
+                    programs,
                     extra,
                 },
         } = Intent::load(&ctx.accounts.sysvar_instructions)
             .map_err(Into::<SessionManagerError>::into)?;
-        ctx.accounts.check_chain_id(chain_id)?;
-        ctx.accounts.check_session_key(session_key)?;
-
-        let expiration = clock::check_expiration(expires)?;
-
-        let authorized_tokens_with_mints = match tokens {
-            Tokens::Specific(tokens) => {
-                let pending_approvals =
-                    convert_remaning_accounts_and_token_limits_to_pending_approvals(
-                        ctx.remaining_accounts,
-                        tokens,
-                        &signer,
+        check_chain_id(&ctx.accounts.chain_id, &chain_id)?;
+        check_session_key(&ctx.accounts.session.key(), session_key)?;
+        require!(
+            SUPPORTED_MINOR_VERSIONS.contains(&minor),
+            SessionManagerError::InvalidVersion
+        );
+        let (user_sessions, remaining_accounts) =
+            split_user_sessions(ctx.remaining_accounts, &signer);
+
+        let domain_record = ctx.accounts.load_domain_record(&domain)?;
+        let expiration = clock::check_expiration(expires, &domain_record.header()?)?;
+
+        let (authorized_tokens_with_mints, native_allowance, rate_limits, token_limits) =
+            match tokens {
+                Tokens::Specific(tokens) => {
+                    let pending_approvals =
+                        convert_remaning_accounts_and_token_limits_to_pending_approvals(
+                            remaining_accounts,
+                            tokens,
+                            &signer,
+                        )?;
+                    let authorized_tokens_with_mints = AuthorizedTokensWithMints::Specific(
+                        pending_approvals.iter().map(|p| p.mint()).collect(),
+                    );
+                    let native_allowance = get_native_allowance(&pending_approvals)?;
+                    let rate_limits =
+                        get_rate_limits(&pending_approvals, Clock::get()?.unix_timestamp);
+                    let token_limits = pending_approvals
+                        .iter()
+                        .map(|pending_approval| TokenLimit {
+                            mint: pending_approval.mint(),
+                            amount: pending_approval.amount,
+                            period: pending_approval.period.map(|period| period.as_seconds()),
+                        })
+                        .collect();
+                    ctx.accounts.approve_tokens(
+                        pending_approvals,
+                        remaining_accounts,
+                        ctx.bumps.session_setter,
                     )?;
-                let authorized_tokens_with_mints = AuthorizedTokensWithMints::Specific(
-                    pending_approvals.iter().map(|p| p.mint()).collect(),
+                    (
+                        authorized_tokens_with_mints,
+                        native_allowance,
+                        rate_limits,
+                        Some(token_limits),
+                    )
+                }
+                Tokens::All => (
+                    AuthorizedTokensWithMints::All,
+                    NativeAllowance::Unlimited,
+                    vec![],
+                    None,
+                ),
+            };
+        // Rate limits are only stored from V6 onwards, earlier versions would silently turn them into lifetime limits
+        require!(
+            minor >= RATE_LIMITS_MINOR_VERSION || rate_limits.is_empty(),
+            SessionManagerError::RateLimitNotSupported
+        );
+
+        let authorized_programs = match programs {
+            Programs::Registered => AuthorizedPrograms::Specific(domain_record.to_vec()?),
+            Programs::Domain => {
+                // Domain-scoped sessions are authorized against the domain record at the time of use, which is found through the domain hash
+                require!(
+                    minor >= DOMAIN_SCOPE_MINOR_VERSION,
+                    SessionManagerError::DomainScopeNotSupported
                 );
-                ctx.accounts
-                    .approve_tokens(pending_approvals, ctx.bumps.session_setter)?;
-                authorized_tokens_with_mints
+                AuthorizedPrograms::Domain
             }
-            Tokens::All => AuthorizedTokensWithMints::All,
         };
 
-        let program_domains = ctx.accounts.get_domain_programs(&domain)?;
-
         let session = match minor {
             1 => Session {
                 sponsor: ctx.accounts.sponsor.key(),
                 major,
                 session_info: SessionInfo::V1(ActiveSessionInfo {
                     user: signer,
-                    authorized_programs: AuthorizedPrograms::Specific(program_domains),
+                    authorized_programs,
                     authorized_tokens: authorized_tokens_with_mints.as_ref().clone(),
                     extra: extra.into(),
                     expiration,
@@ -91,7 +162,7 @@ pub mod session_manager {
                 major,
                 session_info: SessionInfo::V2(V2::Active(ActiveSessionInfo {
                     user: signer,
-                    authorized_programs: AuthorizedPrograms::Specific(program_domains),
+                    authorized_programs,
                     authorized_tokens: authorized_tokens_with_mints.as_ref().clone(),
                     extra: extra.into(),
                     expiration,
@@ -102,7 +173,7 @@ pub mod session_manager {
                 major,
                 session_info: SessionInfo::V3(V3::Active(ActiveSessionInfo {
                     user: signer,
-                    authorized_programs: AuthorizedPrograms::Specific(program_domains),
+                    authorized_programs,
                     authorized_tokens: authorized_tokens_with_mints,
                     extra: extra.into(),
                     expiration,
@@ -115,16 +186,60 @@ pub mod session_manager {
                     domain_hash: domain.get_domain_hash(),
                     active_session_info: ActiveSessionInfo {
                         user: signer,
-                        authorized_programs: AuthorizedPrograms::Specific(program_domains),
+                        authorized_programs,
                         authorized_tokens: authorized_tokens_with_mints,
                         extra: extra.into(),
                         expiration,
                     },
                 })),
             },
+            5 => Session {
+                sponsor: ctx.accounts.sponsor.key(),
+                major,
+                session_info: SessionInfo::V5(V5::Active(
+                    ActiveSessionInfoWithDomainHashAndNativeAllowance {
+                        domain_hash: domain.get_domain_hash(),
+                        native_allowance,
+                        active_session_info: ActiveSessionInfo {
+                            user: signer,
+                            authorized_programs,
+                            authorized_tokens: authorized_tokens_with_mints,
+                            extra: extra.into(),
+                            expiration,
+                        },
+                    },
+                )),
+            },
+            6 => Session {
+                sponsor: ctx.accounts.sponsor.key(),
+                major,
+                session_info: SessionInfo::V6(V6::Active(ActiveSessionInfoWithRateLimits {
+                    domain_hash: domain.get_domain_hash(),
+                    native_allowance,
+                    active_session_info: ActiveSessionInfo {
+                        user: signer,
+                        authorized_programs,
+                        authorized_tokens: authorized_tokens_with_mints,
+                        extra: extra.into(),
+                        expiration,
+                    },
+                    rate_limits,
+                })),
+            },
             _ => return err!(SessionManagerError::InvalidVersion),
         };
         ctx.accounts.initialize_and_store_session(&session)?;
+        if let Some(user_sessions) = user_sessions {
+            ctx.accounts.add_to_user_sessions(user_sessions, &signer)?;
+        }
+        emit!(SessionStarted {
+            session: ctx.accounts.session.key(),
+            user: signer,
+            sponsor: ctx.accounts.sponsor.key(),
+            domain_hash: domain.get_domain_hash(),
+            expiration,
+            token_limits,
+        });
         Ok(())
     }
 
@@ -132,94 +247,457 @@ pub mod session_manager {
     pub fn revoke_session<'info>(
         ctx: Context<'_, '_, '_, 'info, RevokeSession<'info>>,
     ) -> Result<()> {
-        match &ctx.accounts.session.session_info {
-            SessionInfo::Invalid => return err!(SessionManagerError::InvalidVersion),
-            SessionInfo::V1(_) => return err!(SessionManagerError::InvalidVersion),
-            SessionInfo::V2(V2::Active(active_session_info)) => {
-                ctx.accounts.session.session_info =
-                    SessionInfo::V2(V2::Revoked(active_session_info.expiration));
-            }
-            SessionInfo::V2(V2::Revoked(_)) => {} // Idempotent
-            SessionInfo::V3(V3::Active(active_session_info)) => {
-                ctx.accounts.session.session_info =
-                    SessionInfo::V3(V3::Revoked(RevokedSessionInfo {
-                        user: active_session_info.user,
-                        expiration: active_session_info.expiration,
-                        authorized_tokens_with_mints: active_session_info.authorized_tokens.clone(),
-                    }));
-            }
-            SessionInfo::V3(V3::Revoked(_)) => {} // Idempotent
-            SessionInfo::V4(V4::Active(active_session_info)) => {
-                ctx.accounts.session.session_info =
-                    SessionInfo::V4(V4::Revoked(RevokedSessionInfo {
-                        user: active_session_info.as_ref().user,
-                        expiration: active_session_info.as_ref().expiration,
-                        authorized_tokens_with_mints: active_session_info
-                            .as_ref()
-                            .authorized_tokens
-                            .clone(),
-                    }));
-            }
-            SessionInfo::V4(V4::Revoked(_)) => {} // Idempotent
+        if let Some(user) = revoke_session_info(&mut ctx.accounts.session.session_info)? {
+            emit!(SessionRevoked {
+                session: ctx.accounts.session.key(),
+                user,
+            });
         }
         ctx.accounts.reallocate_and_refund_rent()?;
         Ok(())
     }
 
+    /// The user sessions account of the session's user can be passed as the first remaining account, before the token accounts, to remove the session from the index of the user's sessions.
     #[instruction(discriminator = [2])]
     pub fn close_session<'info>(
         ctx: Context<'_, '_, '_, 'info, CloseSession<'info>>,
     ) -> Result<()> {
-        let (user, mints_to_revoke) = match &ctx.accounts.session.session_info {
-            // V3 sessions can be all be closed
-            SessionInfo::V3(V3::Active(ActiveSessionInfo {
-                authorized_tokens: authorized_tokens_with_mints,
+        let (user, mints_to_revoke) =
+            get_user_and_mints_to_revoke(&ctx.accounts.session.session_info)?;
+        let (user_sessions, remaining_accounts) = split_user_sessions(ctx.remaining_accounts, user);
+        let pending_revocations =
+            convert_remaining_accounts_and_mints_to_revoke_to_pending_revocations(
+                remaining_accounts,
+                mints_to_revoke,
                 user,
+                &ctx.accounts.session.key(),
+            )?;
+        ctx.accounts.revoke_tokens(
+            pending_revocations,
+            remaining_accounts,
+            ctx.bumps.session_setter,
+        )?;
+        if let Some(user_sessions) = user_sessions {
+            ctx.accounts
+                .remove_from_user_sessions(user_sessions, user)?;
+        }
+        // The session account is closed by Anchor after this instruction, which refunds all of its lamports to the sponsor
+        emit!(SessionClosed {
+            session: ctx.accounts.session.key(),
+            user: *user,
+            sponsor: ctx.accounts.sponsor.key(),
+            revoked_mints: mints_to_revoke.to_vec(),
+            refunded_rent: ctx.accounts.session.to_account_info().lamports(),
+        });
+        Ok(())
+    }
+
+    /// Extends the expiration of a live V4, V5 or V6 session in place, keeping its authorized programs and token delegations.
+    /// The user needs to sign a new intent for the same session key. The tokens and extra sections of the intent are ignored.
+    /// Like when starting a session, the new expiration needs to be within the session duration limits of the domain record.
+    #[instruction(discriminator = [3])]
+    pub fn extend_session<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExtendSession<'info>>,
+    ) -> Result<()> {
+        let Intent {
+            signer,
+            message:
+                Message {
+                    version: Version { minor, .. },
+                    chain_id,
+                    domain,
+                    expires,
+                    session_key,
+                    ..
+                },
+        } = Intent::load(&ctx.accounts.sysvar_instructions)
+            .map_err(Into::<SessionManagerError>::into)?;
+        check_chain_id(&ctx.accounts.chain_id, &chain_id)?;
+        check_session_key(&ctx.accounts.session.key(), session_key)?;
+
+        let expiration = clock::check_expiration(
+            expires,
+            &load_domain_record_header(&ctx.accounts.domain_registry, &domain)?,
+        )?;
+
+        let (domain_hash, active_session_info) = match &mut ctx.accounts.session.session_info {
+            SessionInfo::V4(V4::Active(ActiveSessionInfoWithDomainHash {
+                domain_hash,
+                active_session_info,
+            })) => {
+                require_eq!(minor, 4, SessionManagerError::InvalidVersion);
+                (domain_hash, active_session_info)
+            }
+            SessionInfo::V5(V5::Active(ActiveSessionInfoWithDomainHashAndNativeAllowance {
+                domain_hash,
+                active_session_info,
                 ..
-            }))
-            | SessionInfo::V3(V3::Revoked(RevokedSessionInfo {
-                authorized_tokens_with_mints,
-                user,
+            })) => {
+                require_eq!(minor, 5, SessionManagerError::InvalidVersion);
+                (domain_hash, active_session_info)
+            }
+            SessionInfo::V6(V6::Active(ActiveSessionInfoWithRateLimits {
+                domain_hash,
+                active_session_info,
                 ..
-            }))
-            | SessionInfo::V4(V4::Active(ActiveSessionInfoWithDomainHash {
-                active_session_info:
-                    ActiveSessionInfo {
-                        authorized_tokens: authorized_tokens_with_mints,
-                        user,
+            })) => {
+                require_eq!(minor, 6, SessionManagerError::InvalidVersion);
+                (domain_hash, active_session_info)
+            }
+            SessionInfo::V4(V4::Revoked(_))
+            | SessionInfo::V5(V5::Revoked(_))
+            | SessionInfo::V6(V6::Revoked(_)) => {
+                return err!(SessionManagerError::SessionIsRevoked)
+            }
+            _ => return err!(SessionManagerError::InvalidVersion),
+        };
+        require_keys_eq!(
+            active_session_info.user,
+            signer,
+            SessionManagerError::UserMismatch
+        );
+        require!(
+            *domain_hash == domain.get_domain_hash(),
+            SessionManagerError::DomainMismatch
+        );
+        require_gt!(
+            expiration,
+            active_session_info.expiration,
+            SessionManagerError::ExpirationNotExtended
+        );
+        active_session_info.expiration = expiration;
+        Ok(())
+    }
+
+    /// Removes some programs and/or tokens from a live session while leaving the rest of the session untouched.
+    /// The token delegations of the mints in `mints_to_revoke` are revoked, the caller needs to provide the user associated token accounts in the same order as `mints_to_revoke` in the remaining accounts.
+    #[instruction(discriminator = [4])]
+    pub fn restrict_session<'info>(
+        ctx: Context<'_, '_, '_, 'info, RestrictSession<'info>>,
+        programs_to_remove: Vec<Pubkey>,
+        mints_to_revoke: Vec<Pubkey>,
+    ) -> Result<()> {
+        let user = ctx.accounts.user.key();
+        let (active_session_info, native_allowance, rate_limits) =
+            match &mut ctx.accounts.session.session_info {
+                SessionInfo::V3(V3::Active(active_session_info))
+                | SessionInfo::V4(V4::Active(ActiveSessionInfoWithDomainHash {
+                    active_session_info,
+                    ..
+                })) => (active_session_info, None, None),
+                SessionInfo::V5(V5::Active(
+                    ActiveSessionInfoWithDomainHashAndNativeAllowance {
+                        active_session_info,
+                        native_allowance,
                         ..
                     },
-                ..
-            }))
-            | SessionInfo::V4(V4::Revoked(RevokedSessionInfo {
-                authorized_tokens_with_mints,
-                user,
-                ..
-            })) => match &authorized_tokens_with_mints {
-                AuthorizedTokensWithMints::Specific(mints) => (user, mints),
-                AuthorizedTokensWithMints::All => (user, &vec![]),
-            },
-            // V2 and V1 sessions can only be closed if they don't have token limits
-            SessionInfo::V2(V2::Active(active_session_info))
-            | SessionInfo::V1(active_session_info) => match active_session_info.authorized_tokens {
-                AuthorizedTokens::Specific => {
-                    return Err(error!(SessionManagerError::InvalidVersion))
+                )) => (active_session_info, Some(native_allowance), None),
+                SessionInfo::V6(V6::Active(ActiveSessionInfoWithRateLimits {
+                    active_session_info,
+                    native_allowance,
+                    rate_limits,
+                    ..
+                })) => (
+                    active_session_info,
+                    Some(native_allowance),
+                    Some(rate_limits),
+                ),
+                SessionInfo::V2(V2::Revoked(_))
+                | SessionInfo::V3(V3::Revoked(_))
+                | SessionInfo::V4(V4::Revoked(_))
+                | SessionInfo::V5(V5::Revoked(_))
+                | SessionInfo::V6(V6::Revoked(_)) => {
+                    return err!(SessionManagerError::SessionIsRevoked)
                 }
-                AuthorizedTokens::All => (&active_session_info.user, &vec![]),
-            },
-            SessionInfo::V2(V2::Revoked(_)) | SessionInfo::Invalid => {
-                return Err(error!(SessionManagerError::InvalidVersion))
+                SessionInfo::V1(_) | SessionInfo::V2(V2::Active(_)) | SessionInfo::Invalid => {
+                    return err!(SessionManagerError::InvalidVersion)
+                }
+            };
+        require_keys_eq!(
+            active_session_info.user,
+            user,
+            SessionManagerError::UserMismatch
+        );
+        restrict::remove_authorized_programs(
+            &mut active_session_info.authorized_programs,
+            &programs_to_remove,
+        )?;
+        restrict::remove_authorized_mints(
+            &mut active_session_info.authorized_tokens,
+            &mints_to_revoke,
+        )?;
+        if let Some(native_allowance) = native_allowance {
+            if mints_to_revoke.contains(&native_mint::ID) {
+                *native_allowance = NativeAllowance::Limited(0);
             }
-        };
+        }
+        if let Some(rate_limits) = rate_limits {
+            rate_limits.retain(|rate_limit| !mints_to_revoke.contains(&rate_limit.mint));
+        }
+
         let pending_revocations =
             convert_remaining_accounts_and_mints_to_revoke_to_pending_revocations(
                 ctx.remaining_accounts,
-                mints_to_revoke,
-                user,
+                &mints_to_revoke,
+                &user,
                 &ctx.accounts.session.key(),
             )?;
+        ctx.accounts.revoke_tokens(
+            pending_revocations,
+            ctx.remaining_accounts,
+            ctx.bumps.session_setter,
+        )?;
+        reallocate_and_refund_rent(&ctx.accounts.session, &ctx.accounts.sponsor)?;
+        Ok(())
+    }
+
+    /// Revokes a session with an intent signed by the session's user instead of the session key.
+    /// This allows a wallet to revoke its sessions from any device, for example if the session key was lost or compromised.
+    #[instruction(discriminator = [5])]
+    pub fn revoke_session_with_intent<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevokeSessionWithIntent<'info>>,
+    ) -> Result<()> {
+        let Intent {
+            signer,
+            message:
+                RevocationMessage {
+                    version: Version { major: _, minor },
+                    chain_id,
+                    session_key,
+                },
+        } = Intent::load(&ctx.accounts.sysvar_instructions)
+            .map_err(Into::<SessionManagerError>::into)?;
+        require_eq!(minor, 1, SessionManagerError::InvalidVersion);
+        check_chain_id(&ctx.accounts.chain_id, &chain_id)?;
+        check_session_key(&ctx.accounts.session.key(), session_key)?;
+
+        let user = match &ctx.accounts.session.session_info {
+            SessionInfo::Invalid | SessionInfo::V1(_) => {
+                return err!(SessionManagerError::InvalidVersion)
+            }
+            SessionInfo::V2(V2::Revoked(_)) => return Ok(()), // Idempotent
+            SessionInfo::V2(V2::Active(active_session_info)) => active_session_info.user,
+            SessionInfo::V3(V3::Active(active_session_info)) => active_session_info.user,
+            SessionInfo::V4(V4::Active(active_session_info)) => active_session_info.as_ref().user,
+            SessionInfo::V5(V5::Active(active_session_info)) => active_session_info.as_ref().user,
+            SessionInfo::V6(V6::Active(active_session_info)) => active_session_info.as_ref().user,
+            SessionInfo::V3(V3::Revoked(revoked_session_info))
+            | SessionInfo::V4(V4::Revoked(revoked_session_info))
+            | SessionInfo::V5(V5::Revoked(revoked_session_info))
+            | SessionInfo::V6(V6::Revoked(revoked_session_info)) => revoked_session_info.user,
+        };
+        require_keys_eq!(user, signer, SessionManagerError::UserMismatch);
+
+        if revoke_session_info(&mut ctx.accounts.session.session_info)?.is_some() {
+            emit!(SessionRevoked {
+                session: ctx.accounts.session.key(),
+                user,
+            });
+        }
+        reallocate_and_refund_rent(&ctx.accounts.session, &ctx.accounts.sponsor)?;
+        Ok(())
+    }
+
+    /// Resets the delegation of a rate-limited token to the amount allowed per period once the current period has elapsed, it is a no-op otherwise.
+    /// This instruction doesn't require any signer, programs spending rate-limited tokens on behalf of a session are expected to invoke it through the SDK before spending.
+    #[instruction(discriminator = [6])]
+    pub fn refresh_rate_limit<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefreshRateLimit<'info>>,
+    ) -> Result<()> {
+        let mint = ctx.accounts.mint.key();
+        let (user, native_allowance, rate_limits) = match &mut ctx.accounts.session.session_info {
+            SessionInfo::V6(V6::Active(ActiveSessionInfoWithRateLimits {
+                native_allowance,
+                active_session_info,
+                rate_limits,
+                ..
+            })) => (active_session_info.user, native_allowance, rate_limits),
+            SessionInfo::V2(V2::Revoked(_))
+            | SessionInfo::V3(V3::Revoked(_))
+            | SessionInfo::V4(V4::Revoked(_))
+            | SessionInfo::V5(V5::Revoked(_))
+            | SessionInfo::V6(V6::Revoked(_)) => {
+                return err!(SessionManagerError::SessionIsRevoked)
+            }
+            _ => return err!(SessionManagerError::InvalidVersion),
+        };
+        let rate_limit = rate_limits
+            .iter_mut()
+            .find(|rate_limit| rate_limit.mint == mint)
+            .ok_or(error!(SessionManagerError::MintNotRateLimited))?;
+        let Some(next_period_start) = rate_limit.next_period_start(Clock::get()?.unix_timestamp)
+        else {
+            return Ok(());
+        };
+        rate_limit.current_period_start = next_period_start;
+        let amount = rate_limit.amount_per_period;
+        if mint == native_mint::ID {
+            *native_allowance = NativeAllowance::Limited(amount);
+        }
+
         ctx.accounts
-            .revoke_tokens(pending_revocations, ctx.bumps.session_setter)?;
+            .approve_token(&user, amount, ctx.bumps.session_setter)
+    }
+
+    /// Closes `number_of_sessions` expired sessions in one instruction, refunding the rent of each session to its sponsor.
+    /// For each session, the caller needs to provide in the remaining accounts: the session account, its sponsor, the user sessions account of its user and then the user associated token accounts in the same order as the mints in the `authorized_tokens` section of the session account.
+    /// If some token accounts belong to a token program other than `token_program`, that token program needs to be provided after all the sessions.
+    #[instruction(discriminator = [7])]
+    pub fn close_sessions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseSessions<'info>>,
+        number_of_sessions: u8,
+    ) -> Result<()> {
+        let mut accounts = ctx.remaining_accounts;
+        for _ in 0..number_of_sessions {
+            let [session_account, sponsor, user_sessions, rest @ ..] = accounts else {
+                return err!(SessionManagerError::MissingAccount);
+            };
+            let session = Account::<Session>::try_from(session_account)?;
+            require!(!session.is_live()?, SessionManagerError::SessionIsLive);
+            require_keys_eq!(
+                session.sponsor,
+                sponsor.key(),
+                SessionManagerError::SponsorMismatch
+            );
+
+            let (user, mints_to_revoke) = get_user_and_mints_to_revoke(&session.session_info)?;
+            let (token_accounts, rest) = (
+                rest.get(..mints_to_revoke.len())
+                    .ok_or(error!(SessionManagerError::MissingAccount))?,
+                rest.get(mints_to_revoke.len()..).unwrap_or_default(),
+            );
+            let pending_revocations =
+                convert_remaining_accounts_and_mints_to_revoke_to_pending_revocations(
+                    token_accounts,
+                    mints_to_revoke,
+                    user,
+                    &session.key(),
+                )?;
+            revoke_delegations(
+                pending_revocations,
+                &ctx.accounts.token_program,
+                &ctx.accounts.session_setter,
+                ctx.remaining_accounts,
+                ctx.bumps.session_setter,
+            )?;
+            remove_from_user_sessions(user_sessions, sponsor, &session.key(), user)?;
+            emit!(SessionClosed {
+                session: session.key(),
+                user: *user,
+                sponsor: sponsor.key(),
+                revoked_mints: mints_to_revoke.to_vec(),
+                refunded_rent: session_account.lamports(),
+            });
+            session.close(sponsor.clone())?;
+
+            accounts = rest;
+        }
+        Ok(())
+    }
+
+    /// Moves a live V4, V5 or V6 session to a new session key, keeping its authorized programs, token limits and extra fields, and closes the old session account.
+    /// The user needs to sign a new intent for the new session key with the same version as the session and a tokens section designating the tokens of the session in the same order. The extra section of the intent is ignored, its expiration caps the expiration of the new session.
+    /// The token delegations of the old session key are moved to the new one with their remaining amounts capped by the amounts of the intent, the caller needs to provide the accounts of each token in the remaining accounts, as when starting a session.
+    #[instruction(discriminator = [8])]
+    pub fn rotate_session_key<'info>(
+        ctx: Context<'_, '_, '_, 'info, RotateSessionKey<'info>>,
+    ) -> Result<()> {
+        let Intent {
+            signer,
+            message:
+                Message {
+                    version: Version { minor, .. },
+                    chain_id,
+                    domain,
+                    expires,
+                    session_key,
+                    tokens,
+                    ..
+                },
+        } = Intent::load(&ctx.accounts.sysvar_instructions)
+            .map_err(Into::<SessionManagerError>::into)?;
+        check_chain_id(&ctx.accounts.chain_id, &chain_id)?;
+        check_session_key(&ctx.accounts.new_session.key(), session_key)?;
+
+        let expiration = clock::check_expiration(
+            expires,
+            &load_domain_record_header(&ctx.accounts.domain_registry, &domain)?,
+        )?;
+
+        let mut session = Session::clone(&ctx.accounts.session);
+        let (domain_hash, active_session_info) = match &mut session.session_info {
+            SessionInfo::V4(V4::Active(ActiveSessionInfoWithDomainHash {
+                domain_hash,
+                active_session_info,
+            })) => {
+                require_eq!(minor, 4, SessionManagerError::InvalidVersion);
+                (domain_hash, active_session_info)
+            }
+            SessionInfo::V5(V5::Active(ActiveSessionInfoWithDomainHashAndNativeAllowance {
+                domain_hash,
+                active_session_info,
+                ..
+            })) => {
+                require_eq!(minor, 5, SessionManagerError::InvalidVersion);
+                (domain_hash, active_session_info)
+            }
+            SessionInfo::V6(V6::Active(ActiveSessionInfoWithRateLimits {
+                domain_hash,
+                active_session_info,
+                ..
+            })) => {
+                require_eq!(minor, 6, SessionManagerError::InvalidVersion);
+                (domain_hash, active_session_info)
+            }
+            SessionInfo::V4(V4::Revoked(_))
+            | SessionInfo::V5(V5::Revoked(_))
+            | SessionInfo::V6(V6::Revoked(_)) => {
+                return err!(SessionManagerError::SessionIsRevoked)
+            }
+            _ => return err!(SessionManagerError::InvalidVersion),
+        };
+        require_keys_eq!(
+            active_session_info.user,
+            signer,
+            SessionManagerError::UserMismatch
+        );
+        require!(
+            *domain_hash == domain.get_domain_hash(),
+            SessionManagerError::DomainMismatch
+        );
+        active_session_info.expiration = active_session_info.expiration.min(expiration);
+        let event = SessionKeyRotated {
+            old_session: ctx.accounts.session.key(),
+            new_session: ctx.accounts.new_session.key(),
+            user: signer,
+            sponsor: ctx.accounts.sponsor.key(),
+            domain_hash: *domain_hash,
+            expiration: active_session_info.expiration,
+        };
+
+        let (tokens, mints_to_move) = match (tokens, &active_session_info.authorized_tokens) {
+            (Tokens::Specific(tokens), AuthorizedTokensWithMints::Specific(mints)) => {
+                (tokens, mints.as_slice())
+            }
+            (Tokens::All, AuthorizedTokensWithMints::All) => (vec![], [].as_slice()),
+            _ => return err!(SessionManagerError::TokensMismatch),
+        };
+        let pending_approvals = convert_remaining_accounts_and_delegations_to_pending_approvals(
+            ctx.remaining_accounts,
+            tokens,
+            mints_to_move,
+            &signer,
+            &ctx.accounts.session.key(),
+        )?;
+        ctx.accounts.approve_tokens(
+            pending_approvals,
+            ctx.remaining_accounts,
+            ctx.bumps.session_setter,
+        )?;
+
+        ctx.accounts.initialize_and_store_session(&session)?;
+        ctx.accounts.replace_in_user_sessions(&signer)?;
+        emit!(event);
         Ok(())
     }
 }
@@ -235,12 +713,12 @@ pub struct StartSession<'info> {
     /// CHECK: we check the address of this account
     #[account(address = instructions::ID)]
     pub sysvar_instructions: AccountInfo<'info>,
-    /// CHECK: We will do the checks in the function in `get_domain_programs`
+    /// CHECK: We will do the checks in the function in `load_domain_record`
     pub domain_registry: AccountInfo<'info>,
     /// CHECK: this is just a signer for token program CPIs
     #[account(seeds = [SESSION_SETTER_SEED], bump)]
     pub session_setter: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
@@ -254,6 +732,21 @@ pub struct RevokeSession<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct RevokeSessionWithIntent<'info> {
+    #[account(seeds = [chain_id::SEED], seeds::program = chain_id::ID, bump)]
+    pub chain_id: Account<'info, chain_id::ChainId>,
+    #[account(mut)]
+    pub session: Account<'info, Session>,
+    /// CHECK: we check it against the session's sponsor
+    #[account(mut, constraint = session.sponsor == sponsor.key() @ SessionManagerError::SponsorMismatch)]
+    pub sponsor: AccountInfo<'info>,
+    /// CHECK: we check the address of this account
+    #[account(address = instructions::ID)]
+    pub sysvar_instructions: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct CloseSession<'info> {
     #[account(mut, close = sponsor, constraint = !session.is_live()? @ SessionManagerError::SessionIsLive)]
@@ -265,76 +758,352 @@ pub struct CloseSession<'info> {
     /// CHECK: this is just a signer for token program CPIs
     #[account(seeds = [SESSION_SETTER_SEED], bump)]
     pub session_setter: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSessions<'info> {
+    /// CHECK: this is just a signer for token program CPIs
+    #[account(seeds = [SESSION_SETTER_SEED], bump)]
+    pub session_setter: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RotateSessionKey<'info> {
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+    #[account(seeds = [chain_id::SEED], seeds::program = chain_id::ID, bump)]
+    pub chain_id: Account<'info, chain_id::ChainId>,
+    #[account(
+        mut,
+        close = sponsor,
+        constraint = session.is_live()? @ SessionManagerError::SessionIsExpired,
+        constraint = session.sponsor == sponsor.key() @ SessionManagerError::SponsorMismatch
+    )]
+    pub session: Account<'info, Session>,
+    #[account(mut)]
+    pub new_session: Signer<'info>,
+    /// CHECK: we check it's the user sessions PDA of the session's user in `replace_in_user_sessions`
+    #[account(mut)]
+    pub user_sessions: AccountInfo<'info>,
+    /// CHECK: we check the address of this account
+    #[account(address = instructions::ID)]
+    pub sysvar_instructions: AccountInfo<'info>,
+    /// CHECK: We will do the checks in the function in `load_domain_record_header`
+    pub domain_registry: AccountInfo<'info>,
+    /// CHECK: this is just a signer for token program CPIs
+    #[account(seeds = [SESSION_SETTER_SEED], bump)]
+    pub session_setter: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct ExtendSession<'info> {
+    #[account(seeds = [chain_id::SEED], seeds::program = chain_id::ID, bump)]
+    pub chain_id: Account<'info, chain_id::ChainId>,
+    #[account(mut, constraint = session.is_live()? @ SessionManagerError::SessionIsExpired)]
+    pub session: Account<'info, Session>,
+    /// CHECK: we check the address of this account
+    #[account(address = instructions::ID)]
+    pub sysvar_instructions: AccountInfo<'info>,
+    /// CHECK: We will do the checks in the function in `load_domain_record_header`
+    pub domain_registry: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RestrictSession<'info> {
+    pub user: Signer<'info>,
+    #[account(mut, constraint = session.is_live()? @ SessionManagerError::SessionIsExpired)]
+    pub session: Account<'info, Session>,
+    /// CHECK: we check it against the session's sponsor
+    #[account(mut, constraint = session.sponsor == sponsor.key() @ SessionManagerError::SponsorMismatch)]
+    pub sponsor: AccountInfo<'info>,
+    /// CHECK: this is just a signer for token program CPIs
+    #[account(seeds = [SESSION_SETTER_SEED], bump)]
+    pub session_setter: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshRateLimit<'info> {
+    #[account(mut, constraint = session.is_live()? @ SessionManagerError::SessionIsExpired)]
+    pub session: Account<'info, Session>,
+    /// CHECK: this is just a signer for token program CPIs
+    #[account(seeds = [SESSION_SETTER_SEED], bump)]
+    pub session_setter: AccountInfo<'info>,
+    /// CHECK: we check it's the associated token account of the user in `approve_token`
+    #[account(mut)]
+    pub user_token_account: AccountInfo<'info>,
+    /// CHECK: the token program checks this is the mint of `user_token_account`
+    pub mint: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
 impl<'info> StartSession<'info> {
     pub fn initialize_and_store_session(&self, session: &Session) -> Result<()> {
-        system_program::initialize_account(
+        initialize_and_store_session(&self.sponsor, &self.session, &self.system_program, session)
+    }
+
+    pub fn load_domain_record(&self, domain: &Domain) -> Result<DomainRecordInner<'info>> {
+        check_domain_record(&self.domain_registry, domain)?;
+
+        DomainRecordInner::load_domain_record(
+            self.domain_registry.to_account_info(),
+            self.sponsor.to_account_info(),
+        )
+    }
+}
+
+impl<'info> RotateSessionKey<'info> {
+    pub fn initialize_and_store_session(&self, session: &Session) -> Result<()> {
+        initialize_and_store_session(
             &self.sponsor,
-            &self.session,
+            &self.new_session,
             &self.system_program,
-            &crate::ID,
-            &Rent::get()?,
-            8 + get_instance_packed_len(&session)? as u64,
-        )?;
-
-        let mut data = self.session.try_borrow_mut_data()?;
-        let dst: &mut [u8] = &mut data;
-        let mut writer = anchor_lang::__private::BpfWriter::new(dst); // This is the writer that Anchor uses internally
-        session.try_serialize(&mut writer)?;
+            session,
+        )
+    }
+}
 
-        Ok(())
+impl<'info> RevokeSession<'info> {
+    pub fn reallocate_and_refund_rent(&self) -> Result<()> {
+        reallocate_and_refund_rent(&self.session, &self.sponsor)
     }
+}
 
-    pub fn check_session_key(&self, session_key: Pubkey) -> Result<()> {
-        if self.session.key() != session_key {
-            return err!(SessionManagerError::SessionKeyMismatch);
-        }
-        Ok(())
+/// Checks that the intent is for the session account `session`
+fn check_session_key(session: &Pubkey, session_key: Pubkey) -> Result<()> {
+    if *session != session_key {
+        return err!(SessionManagerError::SessionKeyMismatch);
     }
+    Ok(())
+}
 
-    pub fn check_chain_id(&self, chain_id: String) -> Result<()> {
-        if self.chain_id.chain_id != chain_id {
-            return err!(SessionManagerError::ChainIdMismatch);
-        }
-        Ok(())
+/// Checks that the chain id of the intent designates the chain of `chain_id_account`
+fn check_chain_id(chain_id_account: &chain_id::ChainId, chain_id: &str) -> Result<()> {
+    if !chain_id_account.matches(chain_id) {
+        return err!(SessionManagerError::ChainIdMismatch);
     }
+    Ok(())
+}
 
-    pub fn get_domain_programs(&self, domain: &Domain) -> Result<Vec<AuthorizedProgram>> {
-        require_eq!(
-            self.domain_registry.key(),
-            domain.get_domain_record_address(),
-            SessionManagerError::DomainRecordMismatch
-        );
+/// Checks that `domain_record` is the domain record of `domain`
+fn check_domain_record(domain_record: &AccountInfo, domain: &Domain) -> Result<()> {
+    require_eq!(
+        domain_record.key(),
+        domain.get_domain_record_address(),
+        SessionManagerError::DomainRecordMismatch
+    );
+    Ok(())
+}
 
-        let domain_record = DomainRecordInner::load(
-            self.domain_registry.to_account_info(),
-            self.sponsor.to_account_info(),
-        );
-        domain_record.to_vec::<AuthorizedProgram>()
+/// Returns the header of `domain_record` after checking that it's the domain record of `domain`
+fn load_domain_record_header(
+    domain_record: &AccountInfo,
+    domain: &Domain,
+) -> Result<DomainRecordHeader> {
+    check_domain_record(domain_record, domain)?;
+    get_domain_record_header(domain_record)
+}
+
+/// Creates the account of a new session, paid by the sponsor, and stores `session` in it
+fn initialize_and_store_session<'info>(
+    sponsor: &AccountInfo<'info>,
+    session_account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    session: &Session,
+) -> Result<()> {
+    system_program::initialize_account(
+        sponsor,
+        session_account,
+        system_program,
+        &crate::ID,
+        &Rent::get()?,
+        8 + get_instance_packed_len(&session)? as u64,
+        &[],
+    )?;
+
+    let mut data = session_account.try_borrow_mut_data()?;
+    let dst: &mut [u8] = &mut data;
+    let mut writer = anchor_lang::__private::BpfWriter::new(dst); // This is the writer that Anchor uses internally
+    session.try_serialize(&mut writer)?;
+
+    Ok(())
+}
+
+/// Returns the user of a session and the mints whose delegations need to be revoked when closing it
+fn get_user_and_mints_to_revoke(session_info: &SessionInfo) -> Result<(&Pubkey, &[Pubkey])> {
+    match session_info {
+        // V3 sessions can be all be closed
+        SessionInfo::V3(V3::Active(ActiveSessionInfo {
+            authorized_tokens: authorized_tokens_with_mints,
+            user,
+            ..
+        }))
+        | SessionInfo::V3(V3::Revoked(RevokedSessionInfo {
+            authorized_tokens_with_mints,
+            user,
+            ..
+        }))
+        | SessionInfo::V4(V4::Active(ActiveSessionInfoWithDomainHash {
+            active_session_info:
+                ActiveSessionInfo {
+                    authorized_tokens: authorized_tokens_with_mints,
+                    user,
+                    ..
+                },
+            ..
+        }))
+        | SessionInfo::V4(V4::Revoked(RevokedSessionInfo {
+            authorized_tokens_with_mints,
+            user,
+            ..
+        }))
+        | SessionInfo::V5(V5::Active(ActiveSessionInfoWithDomainHashAndNativeAllowance {
+            active_session_info:
+                ActiveSessionInfo {
+                    authorized_tokens: authorized_tokens_with_mints,
+                    user,
+                    ..
+                },
+            ..
+        }))
+        | SessionInfo::V5(V5::Revoked(RevokedSessionInfo {
+            authorized_tokens_with_mints,
+            user,
+            ..
+        }))
+        | SessionInfo::V6(V6::Active(ActiveSessionInfoWithRateLimits {
+            active_session_info:
+                ActiveSessionInfo {
+                    authorized_tokens: authorized_tokens_with_mints,
+                    user,
+                    ..
+                },
+            ..
+        }))
+        | SessionInfo::V6(V6::Revoked(RevokedSessionInfo {
+            authorized_tokens_with_mints,
+            user,
+            ..
+        })) => match &authorized_tokens_with_mints {
+            AuthorizedTokensWithMints::Specific(mints) => Ok((user, mints)),
+            AuthorizedTokensWithMints::All => Ok((user, &[])),
+        },
+        // V2 and V1 sessions can only be closed if they don't have token limits
+        SessionInfo::V2(V2::Active(active_session_info)) | SessionInfo::V1(active_session_info) => {
+            match active_session_info.authorized_tokens {
+                AuthorizedTokens::Specific => {
+                    err!(SessionManagerError::InvalidVersion)
+                }
+                AuthorizedTokens::All => Ok((&active_session_info.user, &[])),
+            }
+        }
+        SessionInfo::V2(V2::Revoked(_)) | SessionInfo::Invalid => {
+            err!(SessionManagerError::InvalidVersion)
+        }
     }
 }
 
-impl<'info> RevokeSession<'info> {
-    pub fn reallocate_and_refund_rent(&self) -> Result<()> {
-        let new_len = 8 + get_instance_packed_len::<Session>(&self.session)?;
-        self.session.to_account_info().realloc(new_len, false)?;
-
-        let new_rent = Rent::get()?.minimum_balance(new_len);
-        let current_rent = self.session.to_account_info().lamports();
-
-        if new_rent < current_rent {
-            **self.session.to_account_info().try_borrow_mut_lamports()? = new_rent;
-            **self.sponsor.try_borrow_mut_lamports()? = self
-                .sponsor
-                .lamports()
-                .checked_add(current_rent.saturating_sub(new_rent))
-                .ok_or(ProgramError::ArithmeticOverflow)?;
+/// Transitions an active session to its revoked state and returns its user, revoking an already revoked session is a no-op and returns `None`
+fn revoke_session_info(session_info: &mut SessionInfo) -> Result<Option<Pubkey>> {
+    let user = match session_info {
+        SessionInfo::Invalid => return err!(SessionManagerError::InvalidVersion),
+        SessionInfo::V1(_) => return err!(SessionManagerError::InvalidVersion),
+        SessionInfo::V2(V2::Active(active_session_info)) => {
+            let user = active_session_info.user;
+            *session_info = SessionInfo::V2(V2::Revoked(active_session_info.expiration));
+            user
         }
-        Ok(())
+        SessionInfo::V2(V2::Revoked(_)) => return Ok(None), // Idempotent
+        SessionInfo::V3(V3::Active(active_session_info)) => {
+            let user = active_session_info.user;
+            *session_info = SessionInfo::V3(V3::Revoked(RevokedSessionInfo {
+                user,
+                expiration: active_session_info.expiration,
+                authorized_tokens_with_mints: active_session_info.authorized_tokens.clone(),
+            }));
+            user
+        }
+        SessionInfo::V3(V3::Revoked(_)) => return Ok(None), // Idempotent
+        SessionInfo::V4(V4::Active(active_session_info)) => {
+            let user = active_session_info.as_ref().user;
+            *session_info = SessionInfo::V4(V4::Revoked(RevokedSessionInfo {
+                user,
+                expiration: active_session_info.as_ref().expiration,
+                authorized_tokens_with_mints: active_session_info
+                    .as_ref()
+                    .authorized_tokens
+                    .clone(),
+            }));
+            user
+        }
+        SessionInfo::V4(V4::Revoked(_)) => return Ok(None), // Idempotent
+        SessionInfo::V5(V5::Active(active_session_info)) => {
+            let user = active_session_info.as_ref().user;
+            *session_info = SessionInfo::V5(V5::Revoked(RevokedSessionInfo {
+                user,
+                expiration: active_session_info.as_ref().expiration,
+                authorized_tokens_with_mints: active_session_info
+                    .as_ref()
+                    .authorized_tokens
+                    .clone(),
+            }));
+            user
+        }
+        SessionInfo::V5(V5::Revoked(_)) => return Ok(None), // Idempotent
+        SessionInfo::V6(V6::Active(active_session_info)) => {
+            let user = active_session_info.as_ref().user;
+            *session_info = SessionInfo::V6(V6::Revoked(RevokedSessionInfo {
+                user,
+                expiration: active_session_info.as_ref().expiration,
+                authorized_tokens_with_mints: active_session_info
+                    .as_ref()
+                    .authorized_tokens
+                    .clone(),
+            }));
+            user
+        }
+        SessionInfo::V6(V6::Revoked(_)) => return Ok(None), // Idempotent
+    };
+    Ok(Some(user))
+}
+
+/// Shrinks the session account to the size of its current contents and refunds the excess rent to the sponsor
+fn reallocate_and_refund_rent<'info>(
+    session: &Account<'info, Session>,
+    sponsor: &AccountInfo<'info>,
+) -> Result<()> {
+    resize_and_refund_rent(
+        &session.to_account_info(),
+        sponsor,
+        8 + get_instance_packed_len::<Session>(session)?,
+    )
+}
+
+/// Resizes a program-owned account to `new_len` and refunds the excess rent to the sponsor
+fn resize_and_refund_rent<'info>(
+    account: &AccountInfo<'info>,
+    sponsor: &AccountInfo<'info>,
+    new_len: usize,
+) -> Result<()> {
+    account.realloc(new_len, false)?;
+
+    let new_rent = Rent::get()?.minimum_balance(new_len);
+    let current_rent = account.lamports();
+
+    if new_rent < current_rent {
+        **account.try_borrow_mut_lamports()? = new_rent;
+        **sponsor.try_borrow_mut_lamports()? = sponsor
+            .lamports()
+            .checked_add(current_rent.saturating_sub(new_rent))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
     }
+    Ok(())
 }
 
 #[cfg(test)]
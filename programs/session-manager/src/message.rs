@@ -3,9 +3,13 @@ use anchor_lang::prelude::Pubkey;
 use anchor_spl::token::spl_token::try_ui_amount_into_amount;
 use chrono::{DateTime, FixedOffset};
 use domain_registry::domain::Domain;
+use fogo_sessions_sdk::session::intent::{
+    is_reserved_key, DOMAIN_PROGRAMS_VALUE, MESSAGE_PREFIX, PROGRAMS_KEY, RATE_LIMIT_SEPARATOR,
+    REVOCATION_MESSAGE_PREFIX, TOKENLESS_PERMISSIONS_VALUE, UNLIMITED_TOKEN_PERMISSIONS_VALUE,
+};
 use fogo_sessions_sdk::session::MAJOR;
 use nom::bytes::complete::take_while1;
-use nom::combinator::eof;
+use nom::combinator::{eof, success};
 use nom::error::FromExternalError;
 use nom::lib::std::fmt::Debug;
 use nom::multi::many0;
@@ -13,7 +17,7 @@ use nom::sequence::delimited;
 use nom::{
     bytes::complete::tag,
     character::complete::line_ending,
-    combinator::{map, map_opt, map_res},
+    combinator::{map, map_opt, map_res, opt},
     error::{Error, ParseError},
     multi::many1,
     sequence::preceded,
@@ -22,19 +26,6 @@ use nom::{
 use solana_intents::{key_value, key_value_with_key_type, tag_key_value, SymbolOrMint, Version};
 use std::{collections::HashMap, str::FromStr};
 
-const MESSAGE_PREFIX: &str = "Fogo Sessions:\nSigning this intent will allow this app to interact with your on-chain balances. Please make sure you trust this app and the domain in the message matches the domain of the current web application.\n";
-const UNLIMITED_TOKEN_PERMISSIONS_VALUE: &str = "this app may spend any amount of any token";
-const TOKENLESS_PERMISSIONS_VALUE: &str = "this app may not spend any tokens";
-
-const RESERVED_KEYS: [&str; 6] = [
-    "version",
-    "chain_id",
-    "domain",
-    "expires",
-    "session_key",
-    "tokens",
-];
-
 #[derive(Debug, PartialEq)]
 pub struct Message {
     pub version: Version,
@@ -43,6 +34,7 @@ pub struct Message {
     pub expires: DateTime<FixedOffset>,
     pub session_key: Pubkey,
     pub tokens: Tokens,
+    pub programs: Programs,
     pub extra: HashMap<String, String>,
 }
 
@@ -74,6 +66,107 @@ where
     map(
         delimited(
             (tag(MESSAGE_PREFIX), line_ending::<I, E>),
+            map_opt(tag_key_value::<_, Version, _, _>("version"), |version| {
+                if version.major == MAJOR {
+                    Some(version)
+                } else {
+                    None
+                }
+            })
+            .flat_map(|version: Version| {
+                (
+                    success(version),
+                    tag_key_value("chain_id"),
+                    map_res(tag_key_value::<_, String, _, _>("domain"), |domain| {
+                        Domain::new_checked(domain.as_str())
+                    }),
+                    tag_key_value::<_, DateTime<FixedOffset>, _, _>("expires"),
+                    tag_key_value("session_key"),
+                    tag_key_value("tokens"),
+                    map(
+                        opt(map_opt(
+                            tag_key_value::<_, String, _, _>(PROGRAMS_KEY),
+                            |programs| {
+                                (programs == DOMAIN_PROGRAMS_VALUE).then_some(Programs::Domain)
+                            },
+                        )),
+                        Option::unwrap_or_default,
+                    ),
+                    // The reserved keys depend on the version, `programs` is an extra key before it had its own section
+                    map_opt(many0(key_value::<I, String, _>), move |extra| {
+                        extra
+                            .into_iter()
+                            .try_fold(HashMap::new(), |mut m, (key, value)| {
+                                let key: String = key.parse_to()?;
+                                if is_reserved_key(&key, version.minor)
+                                    || m.insert(key, value).is_some()
+                                {
+                                    return None;
+                                }
+                                Some(m)
+                            })
+                    }),
+                )
+            }),
+            eof,
+        ),
+        |(version, chain_id, domain, expires, session_key, tokens, programs, extra)| Message {
+            version,
+            chain_id,
+            domain,
+            expires,
+            session_key,
+            tokens,
+            programs,
+            extra,
+        },
+    )
+    .parse(input)
+}
+
+/// The programs the session can interact with, all of them belong to the domain of the session
+#[derive(Debug, PartialEq, Default)]
+pub enum Programs {
+    /// The programs registered for the domain when the session starts
+    #[default]
+    Registered,
+    /// The programs registered for the domain whenever the session is used, including programs added after the session starts
+    Domain,
+}
+
+/// The message signed by the user's wallet to revoke one of their sessions without the session key
+#[derive(Debug, PartialEq)]
+pub struct RevocationMessage {
+    pub version: Version,
+    pub chain_id: String,
+    pub session_key: Pubkey,
+}
+
+impl TryFrom<Vec<u8>> for RevocationMessage {
+    type Error = Err<Error<Vec<u8>>>;
+
+    fn try_from(message: Vec<u8>) -> Result<Self, Self::Error> {
+        match revocation_message_v0.parse(message.as_slice()) {
+            Ok((_, message)) => Ok(message),
+            Err(e) => Err(Err::<Error<&[u8]>>::to_owned(e)),
+        }
+    }
+}
+
+fn revocation_message_v0<I, E>(input: I) -> IResult<I, RevocationMessage, E>
+where
+    I: Input,
+    I: ParseTo<Version>,
+    I: ParseTo<String>,
+    I: ParseTo<Pubkey>,
+    I: Offset,
+    I: for<'a> Compare<&'a str>,
+    <I as Input>::Item: AsChar,
+    E: ParseError<I>,
+{
+    map(
+        delimited(
+            (tag(REVOCATION_MESSAGE_PREFIX), line_ending::<I, E>),
             (
                 map_opt(tag_key_value::<_, Version, _, _>("version"), |version| {
                     if version.major == MAJOR {
@@ -83,36 +176,14 @@ where
                     }
                 }),
                 tag_key_value("chain_id"),
-                map_res(tag_key_value::<_, String, _, _>("domain"), |domain| {
-                    Domain::new_checked(domain.as_str())
-                }),
-                tag_key_value::<_, DateTime<FixedOffset>, _, _>("expires"),
                 tag_key_value("session_key"),
-                tag_key_value("tokens"),
-                map_opt(many0(key_value::<I, String, _>), |extra| {
-                    extra
-                        .into_iter()
-                        .try_fold(HashMap::new(), |mut m, (key, value)| {
-                            let key: String = key.parse_to()?;
-                            if RESERVED_KEYS.contains(&key.as_str())
-                                || m.insert(key, value).is_some()
-                            {
-                                return None;
-                            }
-                            Some(m)
-                        })
-                }),
             ),
             eof,
         ),
-        |(version, chain_id, domain, expires, session_key, tokens, extra)| Message {
+        |(version, chain_id, session_key)| RevocationMessage {
             version,
             chain_id,
-            domain,
-            expires,
             session_key,
-            tokens,
-            extra,
         },
     )
     .parse(input)
@@ -142,9 +213,44 @@ where
         .parse(input)
 }
 
+/// The period of a rate-limited token, e.g. `hour` in `-USDC: 10 per hour`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Period {
+    Minute,
+    Hour,
+    Day,
+    Week,
+}
+
+impl Period {
+    pub fn as_seconds(&self) -> i64 {
+        match self {
+            Period::Minute => 60,
+            Period::Hour => 60 * 60,
+            Period::Day => 24 * 60 * 60,
+            Period::Week => 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minute" => Ok(Period::Minute),
+            "hour" => Ok(Period::Hour),
+            "day" => Ok(Period::Day),
+            "week" => Ok(Period::Week),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The tokens section of the intent. Each token has a spend limit for the lifetime of the session, unless a period is specified in which case the limit applies to each period.
 #[derive(Debug, PartialEq)]
 pub enum Tokens {
-    Specific(Vec<(SymbolOrMint, UiTokenAmount)>),
+    Specific(Vec<(SymbolOrMint, UiTokenAmount, Option<Period>)>),
     All,
 }
 
@@ -159,7 +265,12 @@ impl FromStr for Tokens {
                 many1(map_res(
                     preceded(tag("-"), key_value_with_key_type(symbol_or_mint)),
                     |(key, value): (&str, String)| {
-                        key.parse().map(|token| (token, UiTokenAmount::new(value)))
+                        let (amount, period) = match value.split_once(RATE_LIMIT_SEPARATOR) {
+                            Some((amount, period)) => (amount.to_string(), Some(period.parse()?)),
+                            None => (value, None),
+                        };
+                        key.parse()
+                            .map(|token| (token, UiTokenAmount::new(amount), period))
                     },
                 )),
                 Tokens::Specific,
@@ -205,18 +316,51 @@ mod tests {
                 Tokens::Specific(vec![
                     (
                         SymbolOrMint::Symbol("foo".to_string()),
-                        UiTokenAmount::new("5467.672".to_string())
+                        UiTokenAmount::new("5467.672".to_string()),
+                        None
                     ),
                     (
                         SymbolOrMint::Mint(
                             Pubkey::from_str("So11111111111111111111111111111111111111112")
                                 .unwrap()
                         ),
-                        UiTokenAmount::new("766".to_string())
+                        UiTokenAmount::new("766".to_string()),
+                        None
                     )
                 ])
             )
         }
+
+        #[test]
+        fn test_rate_limited() {
+            assert_eq!(
+                "-USDC: 10 per hour\n-FOGO: 0.5 per week\n-SOL: 3"
+                    .parse::<Tokens>()
+                    .unwrap(),
+                Tokens::Specific(vec![
+                    (
+                        SymbolOrMint::Symbol("USDC".to_string()),
+                        UiTokenAmount::new("10".to_string()),
+                        Some(Period::Hour)
+                    ),
+                    (
+                        SymbolOrMint::Symbol("FOGO".to_string()),
+                        UiTokenAmount::new("0.5".to_string()),
+                        Some(Period::Week)
+                    ),
+                    (
+                        SymbolOrMint::Symbol("SOL".to_string()),
+                        UiTokenAmount::new("3".to_string()),
+                        None
+                    ),
+                ])
+            )
+        }
+
+        #[test]
+        fn test_invalid_period() {
+            assert!("-USDC: 10 per fortnight".parse::<Tokens>().is_err())
+        }
     }
 
     mod message {
@@ -247,6 +391,7 @@ mod tests {
                     session_key: Pubkey::from_str("AnDvGGfeXStwG8pfmp98nodbcdeYGNz8r6fPxjrvJxK5")
                         .unwrap(),
                     tokens: Tokens::All,
+                    programs: Programs::Registered,
                     extra: HashMap::new()
                 }
             );
@@ -280,16 +425,19 @@ mod tests {
                     tokens: Tokens::Specific(vec![
                         (
                             SymbolOrMint::Symbol("USDC.s".to_string()),
-                            UiTokenAmount::new("100".to_string())
+                            UiTokenAmount::new("100".to_string()),
+                            None
                         ),
                         (
                             SymbolOrMint::Mint(
                                 Pubkey::from_str("DFVMuhuS4hBfXsJE18EGVX9k75QMycUBNNLJi5bwADnu")
                                     .unwrap()
                             ),
-                            UiTokenAmount::new("200".to_string())
+                            UiTokenAmount::new("200".to_string()),
+                            None
                         ),
                     ]),
+                    programs: Programs::Registered,
                     extra: HashMap::from([
                         ("key1".to_string(), "value1".to_string()),
                         ("key2".to_string(), "value2".to_string()),
@@ -325,6 +473,96 @@ mod tests {
             ))
         }
 
+        #[test]
+        pub fn test_parse_domain_scoped_message() {
+            let message = indoc!(
+                "Fogo Sessions:
+                Signing this intent will allow this app to interact with your on-chain balances. Please make sure you trust this app and the domain in the message matches the domain of the current web application.
+
+                version: 0.5
+                chain_id: localnet
+                domain: https://app.xyz
+                expires: 2014-11-28T21:00:09+09:00
+                session_key: 2jKr1met2kCteHoTNtkTL51Sgw7rQKcF4YNdP5xfkPRB
+                tokens: this app may not spend any tokens
+                programs: this app may interact with any program of this domain, including programs added later
+                key1: value1");
+
+            let message = TryInto::<Message>::try_into(message.as_bytes().to_vec()).unwrap();
+            assert_eq!(message.programs, Programs::Domain);
+            assert_eq!(
+                message.extra.get("key1").map(String::as_str),
+                Some("value1")
+            );
+        }
+
+        #[test]
+        pub fn test_parse_message_with_invalid_programs() {
+            let message = indoc!(
+                "Fogo Sessions:
+                Signing this intent will allow this app to interact with your on-chain balances. Please make sure you trust this app and the domain in the message matches the domain of the current web application.
+
+                version: 0.5
+                chain_id: localnet
+                domain: https://app.xyz
+                expires: 2014-11-28T21:00:09+09:00
+                session_key: 2jKr1met2kCteHoTNtkTL51Sgw7rQKcF4YNdP5xfkPRB
+                tokens: this app may not spend any tokens
+                programs: this app may interact with any program");
+
+            let result = TryInto::<Message>::try_into(message.as_bytes().to_vec());
+            assert!(matches!(
+                result,
+                Err(Err::Error(Error {
+                    code: ErrorKind::MapOpt,
+                    input: _
+                }))
+            ))
+        }
+
+        #[test]
+        pub fn test_parse_message_normalizes_domain() {
+            let message = indoc!(
+                "Fogo Sessions:
+                Signing this intent will allow this app to interact with your on-chain balances. Please make sure you trust this app and the domain in the message matches the domain of the current web application.
+
+                version: 0.4
+                chain_id: localnet
+                domain: HTTPS://App.xyz:443/
+                expires: 2014-11-28T21:00:09+09:00
+                session_key: 2jKr1met2kCteHoTNtkTL51Sgw7rQKcF4YNdP5xfkPRB
+                tokens: this app may not spend any tokens");
+
+            let message = TryInto::<Message>::try_into(message.as_bytes().to_vec()).unwrap();
+            assert_eq!(
+                message.domain,
+                Domain::new_checked("https://app.xyz").unwrap()
+            );
+        }
+
+        #[test]
+        pub fn test_parse_message_with_invalid_domain() {
+            let message = indoc!(
+                "Fogo Sessions:
+                Signing this intent will allow this app to interact with your on-chain balances. Please make sure you trust this app and the domain in the message matches the domain of the current web application.
+
+                version: 0.4
+                chain_id: localnet
+                domain: https://app.xyz/login
+                expires: 2014-11-28T21:00:09+09:00
+                session_key: 2jKr1met2kCteHoTNtkTL51Sgw7rQKcF4YNdP5xfkPRB
+                tokens: this app may not spend any tokens");
+
+            let result = TryInto::<Message>::try_into(message.as_bytes().to_vec());
+            assert!(matches!(
+                result,
+                Err(Err::Error(Error {
+                    code: ErrorKind::MapRes,
+                    input: _
+                }))
+            ))
+        }
+
         #[test]
         pub fn test_parse_message_with_invalid_version() {
             let message = indoc!(
@@ -379,6 +617,43 @@ mod tests {
             ))
         }
 
+        #[test]
+        pub fn test_parse_message_with_programs_extra_key() {
+            let message = indoc!(
+                "Fogo Sessions:
+                Signing this intent will allow this app to interact with your on-chain balances. Please make sure you trust this app and the domain in the message matches the domain of the current web application.
+
+                version: 0.4
+                chain_id: localnet
+                domain: https://app.xyz
+                expires: 2014-11-28T21:00:09+09:00
+                session_key: 2jKr1met2kCteHoTNtkTL51Sgw7rQKcF4YNdP5xfkPRB
+                tokens: this app may not spend any tokens
+                programs: swap");
+
+            // `programs` is an extra key before version 0.5
+            let parsed = TryInto::<Message>::try_into(message.as_bytes().to_vec()).unwrap();
+            assert_eq!(parsed.programs, Programs::Registered);
+            assert_eq!(
+                parsed.extra,
+                HashMap::from([("programs".to_string(), "swap".to_string())])
+            );
+
+            let result = TryInto::<Message>::try_into(
+                message
+                    .replace("version: 0.4", "version: 0.5")
+                    .as_bytes()
+                    .to_vec(),
+            );
+            assert!(matches!(
+                result,
+                Err(Err::Error(Error {
+                    code: ErrorKind::MapOpt,
+                    input: _
+                }))
+            ))
+        }
+
         #[test]
         pub fn test_parse_message_with_unexpected_data_after_end() {
             let message = indoc!(
@@ -406,4 +681,270 @@ mod tests {
             );
         }
     }
+
+    mod revocation_message {
+        use super::super::*;
+        use indoc::indoc;
+        use nom::error::ErrorKind;
+
+        #[test]
+        fn test_parse() {
+            let message = indoc! {"
+                Fogo Sessions:
+                Signing this intent will revoke the session key below. Any app using this session key will no longer be able to interact with your on-chain balances.
+
+                version: 0.1
+                chain_id: localnet
+                session_key: AnDvGGfeXStwG8pfmp98nodbcdeYGNz8r6fPxjrvJxK5"};
+
+            assert_eq!(
+                TryInto::<RevocationMessage>::try_into(message.as_bytes().to_vec()).unwrap(),
+                RevocationMessage {
+                    version: Version { major: 0, minor: 1 },
+                    chain_id: "localnet".to_string(),
+                    session_key: Pubkey::from_str("AnDvGGfeXStwG8pfmp98nodbcdeYGNz8r6fPxjrvJxK5")
+                        .unwrap(),
+                }
+            );
+        }
+
+        #[test]
+        fn test_parse_session_message() {
+            let message = indoc! {"
+                Fogo Sessions:
+                Signing this intent will allow this app to interact with your on-chain balances. Please make sure you trust this app and the domain in the message matches the domain of the current web application.
+
+                version: 0.1
+                chain_id: localnet
+                domain: http://localhost:3000
+                expires: 2025-07-17T17:30:15.033Z
+                session_key: AnDvGGfeXStwG8pfmp98nodbcdeYGNz8r6fPxjrvJxK5
+                tokens: this app may spend any amount of any token"};
+
+            assert!(matches!(
+                TryInto::<RevocationMessage>::try_into(message.as_bytes().to_vec()),
+                Err(Err::Error(Error {
+                    code: ErrorKind::Tag,
+                    input: _
+                }))
+            ));
+        }
+
+        #[test]
+        fn test_parse_with_unexpected_data_after_end() {
+            let message = indoc! {"
+                Fogo Sessions:
+                Signing this intent will revoke the session key below. Any app using this session key will no longer be able to interact with your on-chain balances.
+
+                version: 0.1
+                chain_id: localnet
+                session_key: AnDvGGfeXStwG8pfmp98nodbcdeYGNz8r6fPxjrvJxK5
+                key1: value1"};
+
+            assert!(matches!(
+                TryInto::<RevocationMessage>::try_into(message.as_bytes().to_vec()),
+                Err(Err::Error(Error {
+                    code: ErrorKind::Eof,
+                    input: _
+                }))
+            ));
+        }
+    }
+
+    mod builder {
+        use super::super::*;
+        use fogo_sessions_sdk::client::message::{
+            Message as MessageBuilder, Period as BuilderPeriod, Token, TokenLimit,
+            Tokens as BuilderTokens,
+        };
+        use fogo_sessions_sdk::session::intent::RESERVED_KEYS;
+        use indoc::indoc;
+        use proptest::prelude::*;
+
+        fn parse(message: &MessageBuilder) -> Message {
+            TryInto::<Message>::try_into(message.to_string().into_bytes()).unwrap()
+        }
+
+        #[test]
+        fn test_render_matches_parser_fixture() {
+            let message = MessageBuilder::new(
+                1,
+                "localnet",
+                "https://app.xyz",
+                "2014-11-28T21:00:09+09:00",
+                Pubkey::from_str("2jKr1met2kCteHoTNtkTL51Sgw7rQKcF4YNdP5xfkPRB").unwrap(),
+            )
+            .with_token(Token::Symbol("USDC.s".to_string()), "100")
+            .with_token(
+                Token::Mint(
+                    Pubkey::from_str("DFVMuhuS4hBfXsJE18EGVX9k75QMycUBNNLJi5bwADnu").unwrap(),
+                ),
+                "200",
+            )
+            .with_extra("key1", "value1");
+
+            assert_eq!(
+                message.to_string(),
+                indoc! {"
+                    Fogo Sessions:
+                    Signing this intent will allow this app to interact with your on-chain balances. Please make sure you trust this app and the domain in the message matches the domain of the current web application.
+
+                    version: 0.1
+                    chain_id: localnet
+                    domain: https://app.xyz
+                    expires: 2014-11-28T21:00:09+09:00
+                    session_key: 2jKr1met2kCteHoTNtkTL51Sgw7rQKcF4YNdP5xfkPRB
+                    tokens:
+                    -USDC.s: 100
+                    -DFVMuhuS4hBfXsJE18EGVX9k75QMycUBNNLJi5bwADnu: 200
+                    key1: value1"}
+            );
+        }
+
+        #[test]
+        fn test_render_unlimited_and_tokenless() {
+            let message = MessageBuilder::new(
+                5,
+                "localnet",
+                "http://localhost:3000",
+                "2025-07-17T17:30:15.033Z",
+                Pubkey::new_unique(),
+            );
+            assert_eq!(parse(&message).tokens, Tokens::Specific(vec![]));
+            assert_eq!(parse(&message.with_unlimited_tokens()).tokens, Tokens::All);
+        }
+
+        fn period() -> impl Strategy<Value = BuilderPeriod> {
+            prop_oneof![
+                Just(BuilderPeriod::Minute),
+                Just(BuilderPeriod::Hour),
+                Just(BuilderPeriod::Day),
+                Just(BuilderPeriod::Week),
+            ]
+        }
+
+        fn token() -> impl Strategy<Value = Token> {
+            prop_oneof![
+                "[A-Z][A-Z0-9.]{0,9}".prop_map(Token::Symbol),
+                any::<[u8; 32]>().prop_map(|bytes| Token::Mint(Pubkey::new_from_array(bytes))),
+            ]
+        }
+
+        fn tokens() -> impl Strategy<Value = BuilderTokens> {
+            prop_oneof![
+                Just(BuilderTokens::All),
+                prop::collection::vec(
+                    (
+                        token(),
+                        "[0-9]{1,9}(\\.[0-9]{1,9})?",
+                        prop::option::of(period())
+                    )
+                        .prop_map(|(token, amount, period)| TokenLimit {
+                            token,
+                            amount,
+                            period
+                        }),
+                    0..5
+                )
+                .prop_map(BuilderTokens::Specific),
+            ]
+        }
+
+        fn extra() -> impl Strategy<Value = Vec<(String, String)>> {
+            prop::collection::hash_map("[a-z][a-z0-9_]{0,15}", "[a-zA-Z0-9 .:/_-]{0,30}", 0..5)
+                .prop_filter("extra keys can't be reserved", |extra| {
+                    extra
+                        .keys()
+                        .all(|key| !RESERVED_KEYS.contains(&key.as_str()))
+                })
+                .prop_map(|extra| extra.into_iter().collect())
+        }
+
+        fn message() -> impl Strategy<Value = MessageBuilder> {
+            (
+                1..=6u8,
+                "[a-z0-9-]{1,20}",
+                "https://[a-z]{1,20}\\.(com|xyz|io)",
+                0..4_000_000_000i64,
+                any::<[u8; 32]>(),
+                tokens(),
+                any::<bool>(),
+                extra(),
+            )
+                .prop_map(
+                    |(
+                        minor,
+                        chain_id,
+                        domain,
+                        expires,
+                        session_key,
+                        tokens,
+                        domain_scoped,
+                        extra,
+                    )| {
+                        MessageBuilder {
+                            minor,
+                            chain_id,
+                            domain,
+                            expires: DateTime::from_timestamp(expires, 0).unwrap().to_rfc3339(),
+                            session_key: Pubkey::new_from_array(session_key),
+                            tokens,
+                            domain_scoped,
+                            extra,
+                        }
+                    },
+                )
+        }
+
+        fn into_parsed_tokens(tokens: BuilderTokens) -> Tokens {
+            match tokens {
+                BuilderTokens::All => Tokens::All,
+                BuilderTokens::Specific(token_limits) => Tokens::Specific(
+                    token_limits
+                        .into_iter()
+                        .map(
+                            |TokenLimit {
+                                 token,
+                                 amount,
+                                 period,
+                             }| {
+                                (
+                                    match token {
+                                        Token::Symbol(symbol) => SymbolOrMint::Symbol(symbol),
+                                        Token::Mint(mint) => SymbolOrMint::Mint(mint),
+                                    },
+                                    UiTokenAmount::new(amount),
+                                    period.map(|period| period.to_string().parse().unwrap()),
+                                )
+                            },
+                        )
+                        .collect(),
+                ),
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn test_round_trip(message in message()) {
+                let parsed = parse(&message);
+                prop_assert_eq!(
+                    parsed,
+                    Message {
+                        version: Version { major: 0, minor: message.minor },
+                        chain_id: message.chain_id,
+                        domain: Domain::new_checked(&message.domain).unwrap(),
+                        expires: DateTime::parse_from_rfc3339(&message.expires).unwrap(),
+                        session_key: message.session_key,
+                        tokens: into_parsed_tokens(message.tokens),
+                        programs: if message.domain_scoped {
+                            Programs::Domain
+                        } else {
+                            Programs::Registered
+                        },
+                        extra: message.extra.into_iter().collect(),
+                    }
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,147 @@
+use crate::error::SessionManagerError;
+use anchor_lang::prelude::*;
+use fogo_sessions_sdk::session::{AuthorizedPrograms, AuthorizedTokensWithMints};
+
+/// Removes `programs_to_remove` from the programs the session is allowed to interact with.
+/// Sessions that are allowed to interact with all programs or with every program of their domain can't be restricted.
+pub fn remove_authorized_programs(
+    authorized_programs: &mut AuthorizedPrograms,
+    programs_to_remove: &[Pubkey],
+) -> Result<()> {
+    if programs_to_remove.is_empty() {
+        return Ok(());
+    }
+    match authorized_programs {
+        AuthorizedPrograms::Specific(programs) => {
+            programs_to_remove.iter().try_for_each(|program_id| {
+                let index = programs
+                    .iter()
+                    .position(|program| program.program_id == *program_id)
+                    .ok_or(error!(SessionManagerError::ProgramNotInSession))?;
+                programs.remove(index);
+                Ok(())
+            })
+        }
+        AuthorizedPrograms::All => err!(SessionManagerError::UnlimitedSessionCannotBeRestricted),
+        AuthorizedPrograms::Domain => {
+            err!(SessionManagerError::DomainScopedSessionCannotBeRestricted)
+        }
+    }
+}
+
+/// Removes `mints_to_revoke` from the tokens the session is allowed to interact with.
+/// The order of the remaining mints is preserved, since callers of `close_session` need to provide the associated token accounts in that order.
+/// Sessions that are allowed to spend any token don't hold delegations and can't be restricted.
+pub fn remove_authorized_mints(
+    authorized_tokens: &mut AuthorizedTokensWithMints,
+    mints_to_revoke: &[Pubkey],
+) -> Result<()> {
+    if mints_to_revoke.is_empty() {
+        return Ok(());
+    }
+    match authorized_tokens {
+        AuthorizedTokensWithMints::Specific(mints) => {
+            mints_to_revoke.iter().try_for_each(|mint_to_revoke| {
+                let index = mints
+                    .iter()
+                    .position(|mint| mint == mint_to_revoke)
+                    .ok_or(error!(SessionManagerError::MintNotInSession))?;
+                mints.remove(index);
+                Ok(())
+            })
+        }
+        AuthorizedTokensWithMints::All => {
+            err!(SessionManagerError::UnlimitedSessionCannotBeRestricted)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fogo_sessions_sdk::session::AuthorizedProgram;
+
+    fn authorized_program(program_id: Pubkey) -> AuthorizedProgram {
+        AuthorizedProgram {
+            program_id,
+            signer_pda: Pubkey::new_unique(),
+        }
+    }
+
+    #[test]
+    fn test_remove_authorized_programs() {
+        let programs = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut authorized_programs = AuthorizedPrograms::Specific(
+            programs.iter().copied().map(authorized_program).collect(),
+        );
+
+        remove_authorized_programs(&mut authorized_programs, &[programs[0], programs[2]]).unwrap();
+
+        assert!(matches!(
+            authorized_programs,
+            AuthorizedPrograms::Specific(remaining)
+                if remaining.iter().map(|p| p.program_id).eq([programs[1]])
+        ));
+    }
+
+    #[test]
+    fn test_remove_authorized_programs_not_in_session() {
+        let mut authorized_programs =
+            AuthorizedPrograms::Specific(vec![authorized_program(Pubkey::new_unique())]);
+
+        assert_eq!(
+            remove_authorized_programs(&mut authorized_programs, &[Pubkey::new_unique()])
+                .unwrap_err(),
+            error!(SessionManagerError::ProgramNotInSession)
+        );
+    }
+
+    #[test]
+    fn test_remove_authorized_programs_all() {
+        assert_eq!(
+            remove_authorized_programs(&mut AuthorizedPrograms::All, &[Pubkey::new_unique()])
+                .unwrap_err(),
+            error!(SessionManagerError::UnlimitedSessionCannotBeRestricted)
+        );
+        remove_authorized_programs(&mut AuthorizedPrograms::All, &[]).unwrap();
+        assert_eq!(
+            remove_authorized_programs(&mut AuthorizedPrograms::Domain, &[Pubkey::new_unique()])
+                .unwrap_err(),
+            error!(SessionManagerError::DomainScopedSessionCannotBeRestricted)
+        );
+    }
+
+    #[test]
+    fn test_remove_authorized_mints_preserves_order() {
+        let mints = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut authorized_tokens = AuthorizedTokensWithMints::Specific(mints.to_vec());
+
+        remove_authorized_mints(&mut authorized_tokens, &[mints[1]]).unwrap();
+
+        assert!(matches!(
+            authorized_tokens,
+            AuthorizedTokensWithMints::Specific(remaining)
+                if remaining == vec![mints[0], mints[2], mints[3]]
+        ));
+    }
+
+    #[test]
+    fn test_remove_authorized_mints_twice() {
+        let mint = Pubkey::new_unique();
+        let mut authorized_tokens = AuthorizedTokensWithMints::Specific(vec![mint]);
+
+        assert_eq!(
+            remove_authorized_mints(&mut authorized_tokens, &[mint, mint]).unwrap_err(),
+            error!(SessionManagerError::MintNotInSession)
+        );
+    }
+}
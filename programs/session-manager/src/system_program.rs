@@ -7,16 +7,18 @@ pub fn initialize_account<'a, 'info>(
     program_owner: &Pubkey,
     rent: &Rent,
     space: u64,
+    signer_seeds: &[&[&[u8]]],
 ) -> Result<()> {
     let current_lamports = **new_account.try_borrow_lamports()?;
     if current_lamports == 0 {
         system_program::create_account(
-            CpiContext::new(
+            CpiContext::new_with_signer(
                 system_program.to_account_info(),
                 system_program::CreateAccount {
                     from: payer.to_account_info(),
                     to: new_account.to_account_info(),
                 },
+                signer_seeds,
             ),
             rent.minimum_balance(usize::try_from(space).expect("usize is u64 in sbf programs")),
             space,
@@ -41,20 +43,22 @@ pub fn initialize_account<'a, 'info>(
             )?;
         }
         system_program::allocate(
-            CpiContext::new(
+            CpiContext::new_with_signer(
                 system_program.to_account_info(),
                 system_program::Allocate {
                     account_to_allocate: new_account.to_account_info(),
                 },
+                signer_seeds,
             ),
             space,
         )?;
         system_program::assign(
-            CpiContext::new(
+            CpiContext::new_with_signer(
                 system_program.to_account_info(),
                 system_program::Assign {
                     account_to_assign: new_account.to_account_info(),
                 },
+                signer_seeds,
             ),
             program_owner,
         )
@@ -1,12 +1,15 @@
 use crate::error::SessionManagerError;
-use crate::message::UiTokenAmount;
-use crate::{StartSession, SESSION_SETTER_SEED};
+use crate::message::{Period, UiTokenAmount};
+use crate::token::{find_token_program, TOKEN_PROGRAM_IDS};
+use crate::{RefreshRateLimit, RotateSessionKey, StartSession, SESSION_SETTER_SEED};
 use anchor_lang::prelude::*;
-use anchor_spl::token::approve_checked;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_spl::token::spl_token::native_mint;
 use anchor_spl::{
-    associated_token::get_associated_token_address,
-    token::{ApproveChecked, Mint},
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{approve_checked, ApproveChecked, Mint, TokenAccount},
 };
+use fogo_sessions_sdk::session::{NativeAllowance, RateLimit};
 use mpl_token_metadata::accounts::Metadata;
 use solana_intents::SymbolOrMint;
 
@@ -15,6 +18,7 @@ pub struct PendingApproval<'a, 'info> {
     pub mint_account: &'a AccountInfo<'info>,
     pub amount: u64,
     pub mint_decimals: u8,
+    pub period: Option<Period>,
 }
 
 impl<'a, 'info> PendingApproval<'a, 'info> {
@@ -23,61 +27,73 @@ impl<'a, 'info> PendingApproval<'a, 'info> {
     }
 }
 
+/// Takes the user associated token account and the mint account of a token of the intent from `accounts_iter`, and the metadata account if the token is designated by its symbol.
+/// Checks the mint account corresponds to the symbol or mint address of the intent.
+fn next_user_and_mint_accounts<'a, 'info>(
+    accounts_iter: &mut std::slice::Iter<'a, AccountInfo<'info>>,
+    symbol_or_mint: &SymbolOrMint,
+) -> Result<(&'a AccountInfo<'info>, &'a AccountInfo<'info>)> {
+    let user_account = accounts_iter
+        .next()
+        .ok_or(error!(SessionManagerError::MissingAccount))?;
+    let mint_account = accounts_iter
+        .next()
+        .ok_or(error!(SessionManagerError::MissingAccount))?;
+    match symbol_or_mint {
+        SymbolOrMint::Symbol(symbol) => {
+            let metadata_account = accounts_iter
+                .next()
+                .ok_or(error!(SessionManagerError::MissingAccount))?;
+
+            require_eq!(
+                metadata_account.key(),
+                Metadata::find_pda(&mint_account.key()).0,
+                SessionManagerError::MetadataMismatch
+            );
+            let metadata = Metadata::try_from(metadata_account)?;
+            require_eq!(
+                &metadata.symbol,
+                &format!("{symbol:\0<10}"),
+                SessionManagerError::SymbolMismatch
+            ); // Symbols in the metadata account are padded to 10 characters
+        }
+        SymbolOrMint::Mint(mint) => {
+            require_eq!(*mint, mint_account.key(), SessionManagerError::MintMismatch);
+        }
+    }
+    Ok((user_account, mint_account))
+}
+
 /// Resolve the pending approvals from the remaining accounts and the tokens section of the intent.
 /// In the token section of the intent, tokens are designated by their symbol or mint address. If the mint address is provided, the caller needs to provide the user associated token account and the mint account.
 /// If the symbol is provided, additionally to those two accounts, the caller needs to provide the metadata account for the mint which we use to check the mint account corresponds to the symbol.
 /// This behavior means that signing an intent with the symbol "SOL" means delegating your token account for a token who has metadata symbol "SOL".
 /// Although there can be multiple tokens with the same symbol, the worst case scenario is that you're delegating the token with the most value among them, which is probably what you want.
+/// Mints can be owned by either the legacy token program or Token-2022, the user associated token account is derived with the token program that owns the mint.
+/// For rate-limited tokens, the amount is the amount allowed per period.
 pub fn convert_remaning_accounts_and_token_limits_to_pending_approvals<'a, 'info>(
     accounts: &'a [AccountInfo<'info>],
-    tokens: Vec<(SymbolOrMint, UiTokenAmount)>,
+    tokens: Vec<(SymbolOrMint, UiTokenAmount, Option<Period>)>,
     user: &Pubkey,
 ) -> Result<Vec<PendingApproval<'a, 'info>>> {
     let mut accounts_iter = accounts.iter();
     tokens
         .into_iter()
-        .map(|(symbol_or_mint, ui_token_amount)| {
-            let (user_account, mint_account) = match symbol_or_mint {
-                SymbolOrMint::Symbol(symbol) => {
-                    let user_account = accounts_iter
-                        .next()
-                        .ok_or(error!(SessionManagerError::MissingAccount))?;
-                    let mint_account = accounts_iter
-                        .next()
-                        .ok_or(error!(SessionManagerError::MissingAccount))?;
-                    let metadata_account = accounts_iter
-                        .next()
-                        .ok_or(error!(SessionManagerError::MissingAccount))?;
-
-                    require_eq!(
-                        metadata_account.key(),
-                        Metadata::find_pda(&mint_account.key()).0,
-                        SessionManagerError::MetadataMismatch
-                    );
-                    let metadata = Metadata::try_from(metadata_account)?;
-                    require_eq!(
-                        &metadata.symbol,
-                        &format!("{symbol:\0<10}"),
-                        SessionManagerError::SymbolMismatch
-                    ); // Symbols in the metadata account are padded to 10 characters
-                    (user_account, mint_account)
-                }
-                SymbolOrMint::Mint(mint) => {
-                    let user_account = accounts_iter
-                        .next()
-                        .ok_or(error!(SessionManagerError::MissingAccount))?;
-                    let mint_account = accounts_iter
-                        .next()
-                        .ok_or(error!(SessionManagerError::MissingAccount))?;
-
-                    require_eq!(mint, mint_account.key(), SessionManagerError::MintMismatch);
-                    (user_account, mint_account)
-                }
-            };
+        .map(|(symbol_or_mint, ui_token_amount, period)| {
+            let (user_account, mint_account) =
+                next_user_and_mint_accounts(&mut accounts_iter, &symbol_or_mint)?;
 
+            require!(
+                TOKEN_PROGRAM_IDS.contains(mint_account.owner),
+                SessionManagerError::InvalidTokenProgram
+            );
             require_eq!(
                 user_account.key(),
-                get_associated_token_address(user, &mint_account.key()),
+                get_associated_token_address_with_program_id(
+                    user,
+                    &mint_account.key(),
+                    mint_account.owner
+                ),
                 SessionManagerError::AssociatedTokenAccountMismatch
             );
 
@@ -89,41 +105,194 @@ pub fn convert_remaning_accounts_and_token_limits_to_pending_approvals<'a, 'info
                 mint_account,
                 amount,
                 mint_decimals: mint_data.decimals,
+                period,
+            })
+        })
+        .collect()
+}
+
+/// Collects the delegations of `session_pubkey` that need to be moved to a new session key.
+/// `tokens` is the tokens section of the intent for the new session key, it needs to designate the mints in `mints` in the same order. `accounts` contains the accounts of each token in the same layout as when starting a session.
+/// Each delegation keeps its remaining amount, capped by the amount of the intent. Delegations that were already replaced by the user are skipped.
+pub fn convert_remaining_accounts_and_delegations_to_pending_approvals<'a, 'info>(
+    accounts: &'a [AccountInfo<'info>],
+    tokens: Vec<(SymbolOrMint, UiTokenAmount, Option<Period>)>,
+    mints: &[Pubkey],
+    user: &Pubkey,
+    session_pubkey: &Pubkey,
+) -> Result<Vec<PendingApproval<'a, 'info>>> {
+    require_eq!(
+        tokens.len(),
+        mints.len(),
+        SessionManagerError::TokensMismatch
+    );
+    let mut accounts_iter = accounts.iter();
+    tokens
+        .into_iter()
+        .zip(mints)
+        .map(|((symbol_or_mint, ui_token_amount, _), mint)| {
+            let (user_account, mint_account) =
+                next_user_and_mint_accounts(&mut accounts_iter, &symbol_or_mint)?;
+
+            require_eq!(*mint, mint_account.key(), SessionManagerError::MintMismatch);
+            require!(
+                TOKEN_PROGRAM_IDS.contains(mint_account.owner),
+                SessionManagerError::InvalidTokenProgram
+            );
+            require_eq!(
+                user_account.key(),
+                get_associated_token_address_with_program_id(user, mint, mint_account.owner),
+                SessionManagerError::AssociatedTokenAccountMismatch
+            );
+
+            let mint_data = Mint::try_deserialize(&mut mint_account.data.borrow().as_ref())?;
+            let amount = ui_token_amount.into_amount_internal(mint_data.decimals)?;
+            if user_account.owner != mint_account.owner {
+                return Ok(None);
+            }
+            let account_data =
+                TokenAccount::try_deserialize(&mut user_account.data.borrow().as_ref())?;
+            if account_data.delegate != COption::Some(*session_pubkey) {
+                return Ok(None);
+            }
+
+            Ok(Some(PendingApproval {
+                user_account,
+                mint_account,
+                amount: account_data.delegated_amount.min(amount),
+                mint_decimals: mint_data.decimals,
+                period: None,
+            }))
+        })
+        .filter_map(|result| result.transpose())
+        .collect()
+}
+
+/// Computes the native token allowance of a session from its pending approvals.
+/// The limit the user signed for the native mint also caps how many native tokens the session can move out of the user's wallet through the system program (e.g. to wrap them), so that a session can never drain the user's gas balance.
+/// If the native mint is not in the tokens section of the intent, the session can't move any native tokens.
+pub fn get_native_allowance(pending_approvals: &[PendingApproval]) -> Result<NativeAllowance> {
+    pending_approvals
+        .iter()
+        .filter(|pending_approval| pending_approval.mint() == native_mint::ID)
+        .try_fold(0u64, |total, pending_approval| {
+            total.checked_add(pending_approval.amount)
+        })
+        .map(NativeAllowance::Limited)
+        .ok_or(error!(SessionManagerError::AmountConversionFailed))
+}
+
+/// Computes the accounting records of the rate-limited tokens of a session from its pending approvals, the first period starts at `now`.
+pub fn get_rate_limits(pending_approvals: &[PendingApproval], now: i64) -> Vec<RateLimit> {
+    pending_approvals
+        .iter()
+        .filter_map(|pending_approval| {
+            pending_approval.period.map(|period| RateLimit {
+                mint: pending_approval.mint(),
+                amount_per_period: pending_approval.amount,
+                period: period.as_seconds(),
+                current_period_start: now,
             })
         })
         .collect()
 }
 
+/// Delegate token accounts to the session key.
+pub fn approve_delegations<'a, 'info>(
+    pending_approvals: Vec<PendingApproval<'a, 'info>>,
+    token_program: &AccountInfo<'info>,
+    session: &AccountInfo<'info>,
+    session_setter: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    session_setter_bump: u8,
+) -> Result<()> {
+    pending_approvals.into_iter().try_for_each(
+        |PendingApproval {
+             user_account,
+             mint_account,
+             amount,
+             mint_decimals,
+             ..
+         }| {
+            let cpi_accounts = ApproveChecked {
+                to: user_account.to_account_info(),
+                delegate: session.to_account_info(),
+                authority: session_setter.to_account_info(),
+                mint: mint_account.to_account_info(),
+            };
+            approve_checked(
+                CpiContext::new_with_signer(
+                    find_token_program(token_program, remaining_accounts, mint_account.owner)?,
+                    cpi_accounts,
+                    &[&[SESSION_SETTER_SEED, &[session_setter_bump]]],
+                ),
+                amount,
+                mint_decimals,
+            )
+        },
+    )
+}
+
 impl<'info> StartSession<'info> {
     /// Delegate token accounts to the session key.
     pub fn approve_tokens<'a>(
         &self,
         pending_approvals: Vec<PendingApproval<'a, 'info>>,
+        remaining_accounts: &[AccountInfo<'info>],
+        session_setter_bump: u8,
+    ) -> Result<()> {
+        approve_delegations(
+            pending_approvals,
+            &self.token_program,
+            &self.session,
+            &self.session_setter,
+            remaining_accounts,
+            session_setter_bump,
+        )
+    }
+}
+
+impl<'info> RotateSessionKey<'info> {
+    pub fn approve_tokens<'a>(
+        &self,
+        pending_approvals: Vec<PendingApproval<'a, 'info>>,
+        remaining_accounts: &[AccountInfo<'info>],
         session_setter_bump: u8,
     ) -> Result<()> {
-        pending_approvals.into_iter().try_for_each(
-            |PendingApproval {
-                 user_account,
-                 mint_account,
-                 amount,
-                 mint_decimals,
-             }| {
-                let cpi_accounts = ApproveChecked {
-                    to: user_account.to_account_info(),
-                    delegate: self.session.to_account_info(),
-                    authority: self.session_setter.to_account_info(),
-                    mint: mint_account.to_account_info(),
-                };
-                approve_checked(
-                    CpiContext::new_with_signer(
-                        self.token_program.to_account_info(),
-                        cpi_accounts,
-                        &[&[SESSION_SETTER_SEED, &[session_setter_bump]]],
-                    ),
-                    amount,
-                    mint_decimals,
-                )
-            },
+        approve_delegations(
+            pending_approvals,
+            &self.token_program,
+            &self.new_session,
+            &self.session_setter,
+            remaining_accounts,
+            session_setter_bump,
+        )
+    }
+}
+
+impl<'info> RefreshRateLimit<'info> {
+    /// Reset the delegation of the user's token account to `amount`.
+    pub fn approve_token(&self, user: &Pubkey, amount: u64, session_setter_bump: u8) -> Result<()> {
+        require_eq!(
+            self.user_token_account.key(),
+            get_associated_token_address_with_program_id(user, &self.mint.key(), self.mint.owner),
+            SessionManagerError::AssociatedTokenAccountMismatch
+        );
+        let mint_data = Mint::try_deserialize(&mut self.mint.data.borrow().as_ref())?;
+
+        approve_delegations(
+            vec![PendingApproval {
+                user_account: &self.user_token_account,
+                mint_account: &self.mint,
+                amount,
+                mint_decimals: mint_data.decimals,
+                period: None,
+            }],
+            &self.token_program,
+            &self.session.to_account_info(),
+            &self.session_setter,
+            &[],
+            session_setter_bump,
         )
     }
 }
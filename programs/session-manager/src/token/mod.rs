@@ -1,2 +1,31 @@
+use crate::error::SessionManagerError;
+use anchor_lang::prelude::*;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+
 pub mod approve;
 pub mod revoke;
+
+/// The token programs whose token accounts can be delegated to sessions
+pub const TOKEN_PROGRAM_IDS: [Pubkey; 2] = [spl_token::ID, spl_token_2022::ID];
+
+/// Returns the account of the token program with id `token_program_id`.
+/// Sessions can hold delegations of token accounts from both the legacy token program and Token-2022. The instruction's `token_program` account is used for the accounts it owns, if the session also involves accounts owned by the other token program, the caller needs to provide that program after the token accounts in the remaining accounts.
+pub fn find_token_program<'info>(
+    token_program: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    token_program_id: &Pubkey,
+) -> Result<AccountInfo<'info>> {
+    require!(
+        TOKEN_PROGRAM_IDS.contains(token_program_id),
+        SessionManagerError::InvalidTokenProgram
+    );
+    if token_program.key == token_program_id {
+        return Ok(token_program.clone());
+    }
+    remaining_accounts
+        .iter()
+        .find(|account| account.key == token_program_id)
+        .cloned()
+        .ok_or(error!(SessionManagerError::MissingAccount))
+}
@@ -1,9 +1,10 @@
 use crate::error::SessionManagerError;
-use crate::{CloseSession, SESSION_SETTER_SEED};
+use crate::token::{find_token_program, TOKEN_PROGRAM_IDS};
+use crate::{CloseSession, RestrictSession, SESSION_SETTER_SEED};
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::program_option::COption;
-use anchor_spl::associated_token::get_associated_token_address;
-use anchor_spl::token::{revoke, spl_token, Revoke, TokenAccount};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token_interface::{revoke, Revoke, TokenAccount};
 
 pub struct PendingRevocation<'a, 'info> {
     pub user_account: &'a AccountInfo<'info>,
@@ -12,6 +13,8 @@ pub struct PendingRevocation<'a, 'info> {
 /// Resolve the pending revocations from the remaining accounts and the mints to revoke.
 /// When closing a session, the session account is returned to the system program. We need to revoke all token delegations to the session key, otherwise the session key could still have power to spend tokens from the user accounts even if the session is expired or was revoked.
 /// The caller is reponsible for providing the user associated token accounts in the same order as the mints in the `authorized_tokens` section of the session account.
+/// The associated token accounts can belong to either the legacy token program or Token-2022.
+/// This is also used when restricting a live session, in which case `mints_to_revoke` are the mints the user wants to remove from the session.
 pub fn convert_remaining_accounts_and_mints_to_revoke_to_pending_revocations<'a, 'info>(
     accounts: &'a [AccountInfo<'info>],
     mints_to_revoke: &[Pubkey],
@@ -27,13 +30,19 @@ pub fn convert_remaining_accounts_and_mints_to_revoke_to_pending_revocations<'a,
         .iter()
         .zip(accounts.iter())
         .map(|(mint, user_account)| {
-            require_eq!(
-                user_account.key(),
-                get_associated_token_address(user, mint),
-                SessionManagerError::AssociatedTokenAccountMismatch
-            );
+            let token_program_id = TOKEN_PROGRAM_IDS
+                .iter()
+                .find(|token_program_id| {
+                    user_account.key()
+                        == get_associated_token_address_with_program_id(
+                            user,
+                            mint,
+                            token_program_id,
+                        )
+                })
+                .ok_or(error!(SessionManagerError::AssociatedTokenAccountMismatch))?;
 
-            if user_account.owner == &spl_token::ID {
+            if user_account.owner == token_program_id {
                 let account_data =
                     TokenAccount::try_deserialize(&mut user_account.data.borrow().as_ref())?;
                 if account_data.delegate == COption::Some(*session_pubkey) {
@@ -46,25 +55,61 @@ pub fn convert_remaining_accounts_and_mints_to_revoke_to_pending_revocations<'a,
         .collect()
 }
 
+/// Revoke token accounts from the session key.
+pub fn revoke_delegations<'a, 'info>(
+    pending_revocations: Vec<PendingRevocation<'a, 'info>>,
+    token_program: &AccountInfo<'info>,
+    session_setter: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    session_setter_bump: u8,
+) -> Result<()> {
+    pending_revocations
+        .into_iter()
+        .try_for_each(|PendingRevocation { user_account }| {
+            let cpi_accounts = Revoke {
+                source: user_account.to_account_info(),
+                authority: session_setter.to_account_info(),
+            };
+            revoke(CpiContext::new_with_signer(
+                find_token_program(token_program, remaining_accounts, user_account.owner)?,
+                cpi_accounts,
+                &[&[SESSION_SETTER_SEED, &[session_setter_bump]]],
+            ))
+        })
+}
+
 impl<'info> CloseSession<'info> {
     /// Revoke token accounts from the session key.
     pub fn revoke_tokens<'a>(
         &self,
         pending_revocations: Vec<PendingRevocation<'a, 'info>>,
+        remaining_accounts: &[AccountInfo<'info>],
+        session_setter_bump: u8,
+    ) -> Result<()> {
+        revoke_delegations(
+            pending_revocations,
+            &self.token_program,
+            &self.session_setter,
+            remaining_accounts,
+            session_setter_bump,
+        )
+    }
+}
+
+impl<'info> RestrictSession<'info> {
+    /// Revoke token accounts from the session key.
+    pub fn revoke_tokens<'a>(
+        &self,
+        pending_revocations: Vec<PendingRevocation<'a, 'info>>,
+        remaining_accounts: &[AccountInfo<'info>],
         session_setter_bump: u8,
     ) -> Result<()> {
-        pending_revocations
-            .into_iter()
-            .try_for_each(|PendingRevocation { user_account }| {
-                let cpi_accounts = Revoke {
-                    source: user_account.to_account_info(),
-                    authority: self.session_setter.to_account_info(),
-                };
-                revoke(CpiContext::new_with_signer(
-                    self.token_program.to_account_info(),
-                    cpi_accounts,
-                    &[&[SESSION_SETTER_SEED, &[session_setter_bump]]],
-                ))
-            })
+        revoke_delegations(
+            pending_revocations,
+            &self.token_program,
+            &self.session_setter,
+            remaining_accounts,
+            session_setter_bump,
+        )
     }
 }
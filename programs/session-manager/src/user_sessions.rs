@@ -0,0 +1,242 @@
+use crate::error::SessionManagerError;
+use crate::{resize_and_refund_rent, system_program, CloseSession, RotateSessionKey, StartSession};
+use anchor_lang::solana_program::borsh0_10::get_instance_packed_len;
+use anchor_lang::{prelude::*, system_program as system_program_cpi};
+use fogo_sessions_sdk::session::user_sessions::{
+    UserSessions, MAX_USER_SESSIONS, USER_SESSIONS_SEED,
+};
+
+fn find_user_sessions_address(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[USER_SESSIONS_SEED, user.as_ref()], &crate::ID)
+}
+
+fn store_user_sessions(account: &AccountInfo, user_sessions: &UserSessions) -> Result<()> {
+    let mut data = account.try_borrow_mut_data()?;
+    let dst: &mut [u8] = &mut data;
+    let mut writer = anchor_lang::__private::BpfWriter::new(dst);
+    user_sessions.try_serialize(&mut writer)?;
+    Ok(())
+}
+
+/// Splits the user sessions account of `user` off the front of `remaining_accounts`, if it was passed there.
+/// The user sessions account is optional, so that callers that don't know about the index keep working: their first remaining account is a token account, which is never the user sessions account.
+pub fn split_user_sessions<'a, 'info>(
+    remaining_accounts: &'a [AccountInfo<'info>],
+    user: &Pubkey,
+) -> (Option<&'a AccountInfo<'info>>, &'a [AccountInfo<'info>]) {
+    match remaining_accounts.split_first() {
+        Some((user_sessions, rest))
+            if user_sessions.key() == find_user_sessions_address(user).0 =>
+        {
+            (Some(user_sessions), rest)
+        }
+        _ => (None, remaining_accounts),
+    }
+}
+
+impl<'info> StartSession<'info> {
+    /// Adds the session to the index of the sessions of `user`, creating the index if it doesn't exist yet. The sponsor pays for the rent of the new entry.
+    /// The index is informational, so the session isn't added to it if it already contains `MAX_USER_SESSIONS` sessions.
+    pub fn add_to_user_sessions(
+        &self,
+        user_sessions_account: &AccountInfo<'info>,
+        user: &Pubkey,
+    ) -> Result<()> {
+        let (address, bump) = find_user_sessions_address(user);
+        require_keys_eq!(
+            user_sessions_account.key(),
+            address,
+            SessionManagerError::UserSessionsMismatch
+        );
+
+        let user_sessions = if user_sessions_account.owner == &crate::ID {
+            let mut user_sessions =
+                UserSessions::try_deserialize(&mut user_sessions_account.data.borrow().as_ref())?;
+            if user_sessions.sessions.len() >= MAX_USER_SESSIONS {
+                return Ok(());
+            }
+            user_sessions.sessions.push(self.session.key());
+
+            let new_len = 8 + get_instance_packed_len(&user_sessions)?;
+            let required_lamports = Rent::get()?
+                .minimum_balance(new_len)
+                .saturating_sub(user_sessions_account.lamports());
+            if required_lamports > 0 {
+                system_program_cpi::transfer(
+                    CpiContext::new(
+                        self.system_program.to_account_info(),
+                        system_program_cpi::Transfer {
+                            from: self.sponsor.to_account_info(),
+                            to: user_sessions_account.clone(),
+                        },
+                    ),
+                    required_lamports,
+                )?;
+            }
+            user_sessions_account.realloc(new_len, false)?;
+            user_sessions
+        } else {
+            let user_sessions = UserSessions {
+                user: *user,
+                rent_payer: self.sponsor.key(),
+                sessions: vec![self.session.key()],
+            };
+            system_program::initialize_account(
+                &self.sponsor,
+                user_sessions_account,
+                &self.system_program,
+                &crate::ID,
+                &Rent::get()?,
+                8 + get_instance_packed_len(&user_sessions)? as u64,
+                &[&[USER_SESSIONS_SEED, user.as_ref(), &[bump]]],
+            )?;
+            user_sessions
+        };
+
+        store_user_sessions(user_sessions_account, &user_sessions)
+    }
+}
+
+impl<'info> CloseSession<'info> {
+    pub fn remove_from_user_sessions(
+        &self,
+        user_sessions_account: &AccountInfo<'info>,
+        user: &Pubkey,
+    ) -> Result<()> {
+        remove_from_user_sessions(
+            user_sessions_account,
+            &self.sponsor,
+            &self.session.key(),
+            user,
+        )
+    }
+}
+
+impl<'info> RotateSessionKey<'info> {
+    /// Replaces the old session with the new one in the index of the sessions of `user`. The entry keeps its place so the index doesn't need to be resized.
+    /// Sessions started before the index existed are not in it, in which case this is a no-op.
+    pub fn replace_in_user_sessions(&self, user: &Pubkey) -> Result<()> {
+        require_keys_eq!(
+            self.user_sessions.key(),
+            find_user_sessions_address(user).0,
+            SessionManagerError::UserSessionsMismatch
+        );
+        if self.user_sessions.owner != &crate::ID {
+            return Ok(());
+        }
+
+        let mut user_sessions =
+            UserSessions::try_deserialize(&mut self.user_sessions.data.borrow().as_ref())?;
+        let Some(indexed_session) = user_sessions
+            .sessions
+            .iter_mut()
+            .find(|indexed_session| **indexed_session == self.session.key())
+        else {
+            return Ok(());
+        };
+        *indexed_session = self.new_session.key();
+        store_user_sessions(&self.user_sessions, &user_sessions)
+    }
+}
+
+/// Removes `session` from the index of the sessions of `user` and refunds the rent of its entry to the sponsor.
+/// The index is closed once it's empty if `sponsor` paid for its creation, otherwise the empty index stays for the next sessions of the user.
+/// Sessions started before the index existed are not in it, in which case this is a no-op.
+pub fn remove_from_user_sessions<'info>(
+    user_sessions_account: &AccountInfo<'info>,
+    sponsor: &AccountInfo<'info>,
+    session: &Pubkey,
+    user: &Pubkey,
+) -> Result<()> {
+    require_keys_eq!(
+        user_sessions_account.key(),
+        find_user_sessions_address(user).0,
+        SessionManagerError::UserSessionsMismatch
+    );
+    if user_sessions_account.owner != &crate::ID {
+        return Ok(());
+    }
+
+    let mut user_sessions =
+        UserSessions::try_deserialize(&mut user_sessions_account.data.borrow().as_ref())?;
+    let Some(index) = user_sessions
+        .sessions
+        .iter()
+        .position(|indexed_session| indexed_session == session)
+    else {
+        return Ok(());
+    };
+    user_sessions.sessions.remove(index);
+
+    if user_sessions.sessions.is_empty() && user_sessions.rent_payer == sponsor.key() {
+        let lamports = user_sessions_account.lamports();
+        **user_sessions_account.try_borrow_mut_lamports()? = 0;
+        **sponsor.try_borrow_mut_lamports()? = sponsor
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        user_sessions_account.assign(&system_program_cpi::ID);
+        user_sessions_account.realloc(0, false)?;
+        return Ok(());
+    }
+
+    resize_and_refund_rent(
+        user_sessions_account,
+        sponsor,
+        8 + get_instance_packed_len(&user_sessions)?,
+    )?;
+    store_user_sessions(user_sessions_account, &user_sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_user_sessions() {
+        let user = Pubkey::new_unique();
+        let (user_sessions_address, token_account_address) =
+            (find_user_sessions_address(&user).0, Pubkey::new_unique());
+        let (mut user_sessions_lamports, mut token_account_lamports) = (0, 0);
+        let (mut user_sessions_data, mut token_account_data) = (vec![], vec![]);
+        let user_sessions = AccountInfo::new(
+            &user_sessions_address,
+            false,
+            true,
+            &mut user_sessions_lamports,
+            &mut user_sessions_data,
+            &crate::ID,
+            false,
+            0,
+        );
+        let token_account = AccountInfo::new(
+            &token_account_address,
+            false,
+            true,
+            &mut token_account_lamports,
+            &mut token_account_data,
+            &crate::ID,
+            false,
+            0,
+        );
+
+        let accounts = [user_sessions.clone(), token_account.clone()];
+        let (split, rest) = split_user_sessions(&accounts, &user);
+        assert_eq!(
+            split.map(|account| account.key),
+            Some(&user_sessions_address)
+        );
+        assert_eq!(rest.len(), 1);
+
+        // Callers that don't pass the user sessions account start with the token accounts
+        let accounts = [token_account];
+        let (split, rest) = split_user_sessions(&accounts, &user);
+        assert!(split.is_none());
+        assert_eq!(rest.len(), 1);
+
+        // The user sessions account of another user is a token account as far as this user is concerned
+        assert!(split_user_sessions(&[user_sessions], &Pubkey::new_unique())
+            .0
+            .is_none());
+    }
+}
@@ -8,4 +8,12 @@ pub enum TollboothError {
     InvalidSource,
     #[msg("The destination token account is not the associated token account of the toll recipient for the domain")]
     InvalidDestination,
+    #[msg("The domain record provided is not the domain record of the domain")]
+    InvalidDomainRecord,
+    #[msg("The toll recipient provided is not the toll recipient of the domain, or of its spelling before normalization, with this recipient id")]
+    InvalidTollRecipient,
+    #[msg("The source token account is not the associated token account of the toll recipient")]
+    InvalidTollAccount,
+    #[msg("The signer is not the admin of the domain in the domain registry, or the registry authority if the domain doesn't have an admin")]
+    NotDomainAdmin,
 }
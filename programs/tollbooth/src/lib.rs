@@ -1,23 +1,28 @@
 #![allow(unexpected_cfgs)] // warning: unexpected `cfg` condition value: `anchor-debug`
-#![allow(
-    clippy::used_underscore_binding,
-    reason = "_recipient_id is not used in the program logic"
-)]
 
 use crate::error::TollboothError;
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
 use anchor_lang::solana_program::program::invoke_signed;
-use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::associated_token::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+};
 use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{self, TokenInterface, TransferChecked};
+use fogo_sessions_sdk::domain_registry::{
+    get_config_address, get_domain_hash, get_domain_record_address, get_domain_record_header,
+    Config, DOMAIN_REGISTRY_PROGRAM_ID,
+};
 use fogo_sessions_sdk::{
-    session::Session, token::instruction::transfer, token::PROGRAM_SIGNER_SEED,
+    session::Session,
+    token::instruction::{append_domain_record, transfer},
+    token::PROGRAM_SIGNER_SEED,
 };
 mod error;
 
 declare_id!("toLLShH3xqYgVZuNUotUgQNWZ3Ldwrq9qCp27sJBaDp");
 
 const TOLL_RECIPIENT_SEED: &[u8] = b"toll_recipient";
-const TOLL_RECIPIENT_ID: u8 = 0;
 
 #[program]
 pub mod tollbooth {
@@ -27,11 +32,17 @@ pub mod tollbooth {
     pub fn pay_toll<'info>(
         ctx: Context<'_, '_, '_, 'info, PayToll<'info>>,
         amount: u64,
-        _recipient_id: u8, // This field may be used in the future to enable multiple toll recipients for the same domain for better SVM parallelization
+        recipient_id: u8, // Each domain has up to 256 toll recipients, so that concurrent transactions can pay tolls to different token accounts for better SVM parallelization
     ) -> Result<()> {
         require_eq!(
             get_associated_token_address(
-                &ctx.accounts.session.get_user_checked(&crate::ID)?,
+                &match ctx.accounts.domain_record.as_ref() {
+                    Some(domain_record) => ctx
+                        .accounts
+                        .session
+                        .get_user_checked_with_domain_record(&crate::ID, domain_record)?,
+                    None => ctx.accounts.session.get_user_checked(&crate::ID)?,
+                },
                 &ctx.accounts.mint.key()
             ),
             ctx.accounts.source.key(),
@@ -43,7 +54,7 @@ pub mod tollbooth {
                 &Pubkey::find_program_address(
                     &[
                         TOLL_RECIPIENT_SEED,
-                        &[TOLL_RECIPIENT_ID],
+                        &[recipient_id],
                         ctx.accounts.session.get_domain_hash_checked()?.as_ref(),
                     ],
                     &crate::ID
@@ -55,7 +66,7 @@ pub mod tollbooth {
             TollboothError::InvalidDestination
         );
 
-        let instruction = transfer(
+        let mut instruction = transfer(
             ctx.accounts.token_program.key,
             &ctx.accounts.source.key(),
             &ctx.accounts.destination.key(),
@@ -63,6 +74,9 @@ pub mod tollbooth {
             Some(&ctx.accounts.program_signer.key()),
             amount,
         )?;
+        if let Some(domain_record) = ctx.accounts.domain_record.as_ref() {
+            append_domain_record(&mut instruction, domain_record.key);
+        }
         invoke_signed(
             &instruction,
             &ctx.accounts.to_account_infos(),
@@ -70,6 +84,77 @@ pub mod tollbooth {
         )?;
         Ok(())
     }
+
+    /// Transfers the tolls accumulated by toll recipient `recipient_id` of `domain` in `mint` to `destination`.
+    /// Signed by the admin of the domain in the domain registry, or by the registry authority if the domain doesn't have an admin.
+    /// Sessions started before domains were normalized paid their tolls to the toll recipients of the hash of `domain` as spelled in their intent, like `https://App.xyz/`. Passing that spelling as `domain` withdraws those tolls, with the admin of the canonical domain.
+    #[instruction(discriminator = [1])]
+    pub fn withdraw_tolls<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawTolls<'info>>,
+        domain: String,
+        recipient_id: u8,
+    ) -> Result<()> {
+        require_eq!(
+            ctx.accounts.domain_record.key(),
+            get_domain_record_address(&domain),
+            TollboothError::InvalidDomainRecord
+        );
+        let admin = match get_domain_record_header(&ctx.accounts.domain_record)
+            .and_then(|header| header.admin)
+        {
+            Some(admin) => admin,
+            None => Config::try_deserialize(&ctx.accounts.config.try_borrow_data()?)?.authority,
+        };
+        require_keys_eq!(
+            ctx.accounts.admin.key(),
+            admin,
+            TollboothError::NotDomainAdmin
+        );
+
+        let (domain_hash, toll_recipient, bump) = [
+            get_domain_hash(&domain),
+            hashv(&[domain.as_bytes()]).to_bytes(),
+        ]
+        .into_iter()
+        .map(|domain_hash| {
+            let (toll_recipient, bump) = Pubkey::find_program_address(
+                &[TOLL_RECIPIENT_SEED, &[recipient_id], domain_hash.as_ref()],
+                &crate::ID,
+            );
+            (domain_hash, toll_recipient, bump)
+        })
+        .find(|(_, toll_recipient, _)| toll_recipient == ctx.accounts.toll_recipient.key)
+        .ok_or(TollboothError::InvalidTollRecipient)?;
+        require_eq!(
+            get_associated_token_address_with_program_id(
+                &toll_recipient,
+                &ctx.accounts.mint.key(),
+                &ctx.accounts.token_program.key()
+            ),
+            ctx.accounts.source.key(),
+            TollboothError::InvalidTollAccount
+        );
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.source.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.toll_recipient.to_account_info(),
+                },
+                &[&[
+                    TOLL_RECIPIENT_SEED,
+                    &[recipient_id],
+                    domain_hash.as_ref(),
+                    &[bump],
+                ]],
+            ),
+            ctx.accounts.source.amount,
+            ctx.accounts.mint.decimals,
+        )
+    }
 }
 
 #[derive(Accounts)]
@@ -85,6 +170,29 @@ pub struct PayToll<'info> {
     pub destination: Account<'info, TokenAccount>,
     pub mint: Account<'info, Mint>,
     pub token_program: Program<'info, Token>,
+    /// The domain record of the session's domain, needed for domain-scoped sessions
+    /// CHECK: We check it's the domain record of the session's domain in `get_user_checked_with_domain_record`
+    pub domain_record: Option<AccountInfo<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTolls<'info> {
+    /// The admin of the domain, or the registry authority if the domain doesn't have an admin
+    pub admin: Signer<'info>,
+    /// CHECK: We check the address and read the admin of the domain in the handler
+    pub domain_record: AccountInfo<'info>,
+    /// CHECK: The config account of the domain registry, we read the registry authority in the handler
+    #[account(address = get_config_address(), owner = DOMAIN_REGISTRY_PROGRAM_ID)]
+    pub config: AccountInfo<'info>,
+    /// CHECK: We check the PDA derivation in the handler, since its seeds depend on the hash of `domain`
+    pub toll_recipient: AccountInfo<'info>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub source: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub destination: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, token_interface::Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[cfg(test)]
@@ -95,4 +203,13 @@ mod tests {
     fn test_program_id_matches_sdk() {
         assert_eq!(ID, fogo_sessions_sdk::tollbooth::TOLLBOOTH_PROGRAM_ID);
     }
+
+    #[test]
+    fn test_toll_recipient_matches_sdk() {
+        let domain_hash = [7; 32];
+        assert_eq!(
+            Pubkey::find_program_address(&[TOLL_RECIPIENT_SEED, &[42], &domain_hash], &ID).0,
+            fogo_sessions_sdk::tollbooth::get_toll_recipient_address(&domain_hash, 42)
+        );
+    }
 }
@@ -0,0 +1,283 @@
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{
+        hash::{hash, hashv},
+        instruction::Instruction,
+        program_option::COption,
+    },
+    AnchorSerialize, InstructionData, ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::get_associated_token_address,
+    token::spl_token::{
+        self,
+        solana_program::program_pack::Pack,
+        state::{Account as TokenAccount, AccountState, Mint},
+    },
+};
+use fogo_sessions_sdk::domain_registry::{
+    get_config_address, get_domain_hash, get_domain_record_address, DomainRecordHeader,
+    DOMAIN_REGISTRY_PROGRAM_ID,
+};
+use fogo_sessions_sdk::tollbooth::get_toll_recipient_address;
+use litesvm::{types::TransactionResult, LiteSVM};
+use solana_account::Account;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+const DOMAIN: &str = "https://app.xyz";
+const LEGACY_DOMAIN: &str = "https://App.xyz/";
+const TOLLS: u64 = 1_000;
+
+struct Setup {
+    svm: LiteSVM,
+    registry_authority: Keypair,
+    mint: Pubkey,
+    destination: Pubkey,
+}
+
+fn set_account(svm: &mut LiteSVM, address: Pubkey, owner: Pubkey, data: Vec<u8>) {
+    svm.set_account(
+        address,
+        Account {
+            lamports: svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        },
+    )
+    .unwrap();
+}
+
+fn set_token_account(svm: &mut LiteSVM, address: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint,
+        owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    }
+    .pack_into_slice(&mut data);
+    set_account(svm, address, spl_token::ID, data);
+}
+
+fn toll_recipient(recipient_id: u8) -> Pubkey {
+    get_toll_recipient_address(&get_domain_hash(DOMAIN), recipient_id)
+}
+
+fn legacy_toll_recipient(recipient_id: u8) -> Pubkey {
+    get_toll_recipient_address(&hashv(&[LEGACY_DOMAIN.as_bytes()]).to_bytes(), recipient_id)
+}
+
+/// Creates the domain registry config, the domain record of `DOMAIN` with `admin`, toll accounts holding `TOLLS` for toll recipients 0 and 3 and a toll account holding `TOLLS` for the toll recipient 0 of `LEGACY_DOMAIN`
+fn setup(admin: Option<Pubkey>) -> Setup {
+    let mut svm = LiteSVM::new();
+    svm.add_program_from_file(tollbooth::ID, "../../target/deploy/tollbooth.so")
+        .expect("Failed to load tollbooth program, run `cargo build-sbf` before this test");
+    let registry_authority = Keypair::new();
+    svm.airdrop(&registry_authority.pubkey(), 1_000_000_000)
+        .unwrap();
+
+    let mut config = hash(b"account:Config")
+        .to_bytes()
+        .get(..8)
+        .unwrap()
+        .to_vec();
+    (registry_authority.pubkey(), None::<Pubkey>)
+        .serialize(&mut config)
+        .unwrap();
+    set_account(
+        &mut svm,
+        get_config_address(),
+        DOMAIN_REGISTRY_PROGRAM_ID,
+        config,
+    );
+    set_account(
+        &mut svm,
+        get_domain_record_address(DOMAIN),
+        DOMAIN_REGISTRY_PROGRAM_ID,
+        DomainRecordHeader {
+            admin,
+            ..DomainRecordHeader::default()
+        }
+        .to_prefix(),
+    );
+
+    let mint = Pubkey::new_unique();
+    let mut data = vec![0; Mint::LEN];
+    Mint {
+        mint_authority: COption::None,
+        supply: 3 * TOLLS,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    set_account(&mut svm, mint, spl_token::ID, data);
+    for recipient_id in [0, 3] {
+        let toll_recipient = toll_recipient(recipient_id);
+        set_token_account(
+            &mut svm,
+            get_associated_token_address(&toll_recipient, &mint),
+            mint,
+            toll_recipient,
+            TOLLS,
+        );
+    }
+    let legacy_toll_recipient = legacy_toll_recipient(0);
+    set_token_account(
+        &mut svm,
+        get_associated_token_address(&legacy_toll_recipient, &mint),
+        mint,
+        legacy_toll_recipient,
+        TOLLS,
+    );
+    let destination = Pubkey::new_unique();
+    set_token_account(&mut svm, destination, mint, Pubkey::new_unique(), 0);
+
+    Setup {
+        svm,
+        registry_authority,
+        mint,
+        destination,
+    }
+}
+
+impl Setup {
+    fn generate_and_fund_key(&mut self) -> Keypair {
+        let keypair = Keypair::new();
+        self.svm.airdrop(&keypair.pubkey(), 1_000_000_000).unwrap();
+        keypair
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn withdraw_tolls(&mut self, signer: &Keypair, recipient_id: u8) -> TransactionResult {
+        self.withdraw_tolls_of(signer, DOMAIN, toll_recipient(recipient_id), recipient_id)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn withdraw_tolls_of(
+        &mut self,
+        signer: &Keypair,
+        domain: &str,
+        toll_recipient: Pubkey,
+        recipient_id: u8,
+    ) -> TransactionResult {
+        let instruction = Instruction {
+            program_id: tollbooth::ID,
+            accounts: tollbooth::accounts::WithdrawTolls {
+                admin: signer.pubkey(),
+                domain_record: get_domain_record_address(domain),
+                config: get_config_address(),
+                toll_recipient,
+                source: get_associated_token_address(&toll_recipient, &self.mint),
+                destination: self.destination,
+                mint: self.mint,
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: tollbooth::instruction::WithdrawTolls {
+                domain: domain.to_string(),
+                recipient_id,
+            }
+            .data(),
+        };
+        self.svm.expire_blockhash();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&signer.pubkey()),
+            &[signer],
+            self.svm.latest_blockhash(),
+        );
+        self.svm.send_transaction(transaction)
+    }
+
+    fn get_token_balance(&self, token_account: &Pubkey) -> u64 {
+        TokenAccount::unpack(&self.svm.get_account(token_account).unwrap().data)
+            .unwrap()
+            .amount
+    }
+
+    fn get_tolls(&self, recipient_id: u8) -> u64 {
+        self.get_token_balance(&get_associated_token_address(
+            &toll_recipient(recipient_id),
+            &self.mint,
+        ))
+    }
+}
+
+#[test]
+fn test_withdraw_tolls_by_domain_admin() {
+    let admin = Keypair::new();
+    let mut setup = setup(Some(admin.pubkey()));
+    setup.svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    // Only the admin can withdraw the tolls of a domain that has one
+    let registry_authority = setup.registry_authority.insecure_clone();
+    assert!(setup.withdraw_tolls(&registry_authority, 0).is_err());
+
+    setup.withdraw_tolls(&admin, 0).unwrap();
+    assert_eq!(setup.get_tolls(0), 0);
+    assert_eq!(setup.get_tolls(3), TOLLS);
+    assert_eq!(setup.get_token_balance(&setup.destination), TOLLS);
+
+    setup.withdraw_tolls(&admin, 3).unwrap();
+    assert_eq!(setup.get_tolls(3), 0);
+    assert_eq!(setup.get_token_balance(&setup.destination), 2 * TOLLS);
+}
+
+#[test]
+fn test_withdraw_tolls_by_registry_authority_without_domain_admin() {
+    let mut setup = setup(None);
+    let registry_authority = setup.registry_authority.insecure_clone();
+
+    setup.withdraw_tolls(&registry_authority, 3).unwrap();
+    assert_eq!(setup.get_tolls(0), TOLLS);
+    assert_eq!(setup.get_tolls(3), 0);
+    assert_eq!(setup.get_token_balance(&setup.destination), TOLLS);
+}
+
+#[test]
+fn test_withdraw_tolls_rejects_non_admin() {
+    for admin in [Some(Pubkey::new_unique()), None] {
+        let mut setup = setup(admin);
+        let other = setup.generate_and_fund_key();
+        for recipient_id in [0, 3] {
+            assert!(setup.withdraw_tolls(&other, recipient_id).is_err());
+            assert_eq!(setup.get_tolls(recipient_id), TOLLS);
+        }
+    }
+}
+
+#[test]
+fn test_withdraw_tolls_of_legacy_domain() {
+    let admin = Keypair::new();
+    let mut setup = setup(Some(admin.pubkey()));
+    setup.svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+    let legacy_toll_recipient = legacy_toll_recipient(0);
+
+    // The legacy toll recipient is derived from the spelling of the domain, not from the canonical domain
+    assert!(setup
+        .withdraw_tolls_of(&admin, DOMAIN, legacy_toll_recipient, 0)
+        .is_err());
+    let registry_authority = setup.registry_authority.insecure_clone();
+    assert!(setup
+        .withdraw_tolls_of(&registry_authority, LEGACY_DOMAIN, legacy_toll_recipient, 0)
+        .is_err());
+
+    setup
+        .withdraw_tolls_of(&admin, LEGACY_DOMAIN, legacy_toll_recipient, 0)
+        .unwrap();
+    assert_eq!(
+        setup.get_token_balance(&get_associated_token_address(
+            &legacy_toll_recipient,
+            &setup.mint
+        )),
+        0
+    );
+    assert_eq!(setup.get_tolls(0), TOLLS);
+    assert_eq!(setup.get_token_balance(&setup.destination), TOLLS);
+}
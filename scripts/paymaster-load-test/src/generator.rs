@@ -1,17 +1,13 @@
 use crate::config::ValidityType;
 use anyhow::Result;
-use chain_id::ID as CHAIN_ID_PID;
-use fogo_sessions_sdk::domain_registry::get_domain_record_address;
-use fogo_sessions_sdk::session::SESSION_MANAGER_ID;
+use fogo_sessions_sdk::client::message::Message;
+use fogo_sessions_sdk::client::start_session;
 use rand::random;
 use solana_compute_budget_interface::ComputeBudgetInstruction;
 use solana_hash::Hash;
 use solana_keypair::Keypair;
 use solana_message::{v0, VersionedMessage};
-use solana_program::{
-    ed25519_program,
-    instruction::{AccountMeta, Instruction},
-};
+use solana_program::instruction::Instruction;
 use solana_pubkey::Pubkey;
 use solana_signer::Signer;
 use solana_transaction::versioned::VersionedTransaction;
@@ -206,21 +202,29 @@ impl TransactionGenerator {
         let expires_iso =
             (OffsetDateTime::now_utc() + Duration::from_secs(3600)).format(&Rfc3339)?;
 
-        let message_bytes =
-            build_intent_message(&self.chain_id, &self.domain, &expires_iso, &session_pubkey);
-        let intent_ix = build_ed25519_verification_ix(&self.user_signer, message_bytes);
-
-        let domain_record_pda = get_domain_record_address(&self.domain);
-
-        let accounts =
-            gather_start_session_accounts(sponsor_pubkey, session_pubkey, domain_record_pda);
-        let start_session_ix = Instruction {
-            program_id: SESSION_MANAGER_ID,
-            accounts,
-            data: vec![0u8],
-        };
-
-        Ok((vec![intent_ix, start_session_ix], session_keypair))
+        let message_bytes = Message::new(
+            3,
+            self.chain_id.as_str(),
+            self.domain.as_str(),
+            expires_iso,
+            session_pubkey,
+        )
+        .with_unlimited_tokens()
+        .to_string()
+        .into_bytes();
+        let signature = self.user_signer.sign_message(&message_bytes);
+
+        let instructions = start_session(
+            &sponsor_pubkey,
+            &session_pubkey,
+            &self.user_signer.pubkey(),
+            &self.domain,
+            &[],
+            &message_bytes,
+            &signature.into(),
+        );
+
+        Ok((instructions, session_keypair))
     }
 }
 
@@ -235,63 +239,3 @@ fn sign_transaction(tx: &mut VersionedTransaction, signers: &[&Keypair]) {
         }
     }
 }
-
-fn build_intent_message(
-    chain_id: &str,
-    domain: &str,
-    expires_iso: &str,
-    session_key: &Pubkey,
-) -> Vec<u8> {
-    const HEADER: &str = "Fogo Sessions:\nSigning this intent will allow this app to interact with your on-chain balances. Please make sure you trust this app and the domain in the message matches the domain of the current web application.";
-    const MAJOR: &str = "0";
-    const MINOR: &str = "3";
-    const TOKENS: &str = "this app may spend any amount of any token";
-    let body = format!(
-        "version: {MAJOR}.{MINOR}\nchain_id: {chain_id}\ndomain: {domain}\nexpires: {expires_iso}\nsession_key: {session_key}\ntokens: {TOKENS}",
-    );
-
-    format!("{HEADER}\n\n{body}").into_bytes()
-}
-
-fn build_ed25519_verification_ix(user_signer: &Keypair, message_bytes: Vec<u8>) -> Instruction {
-    let signature = user_signer.sign_message(&message_bytes);
-    let mut data = Vec::new();
-    data.push(1u8); // num sigs
-    data.push(0u8); // padding
-    data.extend_from_slice(&48u16.to_le_bytes()); // sig offset
-    data.extend_from_slice(&u16::MAX.to_le_bytes()); // sig idx
-    data.extend_from_slice(&16u16.to_le_bytes()); // pubkey offset
-    data.extend_from_slice(&u16::MAX.to_le_bytes()); // pubkey idx
-    data.extend_from_slice(&112u16.to_le_bytes()); // msg offset
-    data.extend_from_slice(&(message_bytes.len() as u16).to_le_bytes()); // msg length
-    data.extend_from_slice(&u16::MAX.to_le_bytes()); // msg idx
-    data.extend_from_slice(&user_signer.pubkey().to_bytes());
-    data.extend_from_slice(signature.as_ref());
-    data.extend_from_slice(&message_bytes);
-    Instruction {
-        program_id: ed25519_program::id(),
-        accounts: vec![],
-        data,
-    }
-}
-
-fn gather_start_session_accounts(
-    sponsor: Pubkey,
-    session_pubkey: Pubkey,
-    domain_record_pda: Pubkey,
-) -> Vec<AccountMeta> {
-    let (chain_id_pda, _chain_bump) = Pubkey::find_program_address(&[b"chain_id"], &CHAIN_ID_PID);
-    let (session_setter_pda, _setter_bump) =
-        Pubkey::find_program_address(&[b"session_setter"], &SESSION_MANAGER_ID);
-    let sysvar_instructions_id: Pubkey = solana_program::sysvar::instructions::ID;
-    vec![
-        AccountMeta::new(sponsor, true),
-        AccountMeta::new_readonly(chain_id_pda, false),
-        AccountMeta::new(session_pubkey, true),
-        AccountMeta::new_readonly(sysvar_instructions_id, false),
-        AccountMeta::new_readonly(domain_record_pda, false),
-        AccountMeta::new_readonly(session_setter_pda, false),
-        AccountMeta::new_readonly(spl_token::id(), false),
-        AccountMeta::new_readonly(solana_program::system_program::ID, false),
-    ]
-}
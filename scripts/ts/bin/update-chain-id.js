@@ -0,0 +1,4 @@
+#!/usr/bin/env node
+import { main } from "../dist/update-chain-id.js";
+
+main();
@@ -8,14 +8,100 @@ import { anchorOptions, createAnchorProvider } from "./anchor-options.js";
 
 type AnchorArgs = Parameters<typeof createAnchorProvider>[0];
 
-// Remove this if we fix the @fogo/sessions-sdk import issue
-export const getDomainRecordAddress = (domain: string) => {
-  const hash = sha256(new TextEncoder().encode(domain));
-  return PublicKey.findProgramAddressSync(
-    [Buffer.from("domain-record"), hash],
+// Remove these if we fix the @fogo/sessions-sdk import issue
+
+// The longest host allowed in a domain, as in DNS
+const MAX_HOST_LENGTH = 253;
+// The longest label allowed in the host of a domain, as in DNS
+const MAX_LABEL_LENGTH = 63;
+const DEFAULT_PORTS: Partial<Record<string, number>> = {
+  http: 80,
+  https: 443,
+};
+
+// Unlike `toLowerCase`, this never turns a non-ASCII character into an ASCII one, like the domain registry
+const toAsciiLowerCase = (value: string) =>
+  value.replace(/[A-Z]/g, (char) => char.toLowerCase());
+
+const parsePort = (port: string) => {
+  if (!/^\d+$/.test(port)) {
+    return undefined;
+  }
+  const value = Number.parseInt(port, 10);
+  return value > 0 && value <= 65_535 ? value : undefined;
+};
+
+const isValidHost = (host: string) =>
+  host.length <= MAX_HOST_LENGTH &&
+  host
+    .split(".")
+    .every(
+      (label) =>
+        label.length > 0 &&
+        label.length <= MAX_LABEL_LENGTH &&
+        !label.startsWith("-") &&
+        !label.endsWith("-") &&
+        /^[a-z\d-]+$/.test(label),
+    );
+
+/**
+ * Returns the canonical form of a web origin (`scheme://host[:port]`), which identifies its domain record, like the domain registry does.
+ * The canonical form has a lowercase scheme and host, no trailing slash and no port if it's the default port of the scheme.
+ * Returns `undefined` if `domain` isn't a web origin with an ASCII host, internationalized domain names need to be punycode-encoded.
+ */
+export const normalizeDomain = (domain: string) => {
+  const schemeEnd = domain.indexOf("://");
+  if (schemeEnd === -1) {
+    return;
+  }
+  const scheme = toAsciiLowerCase(domain.slice(0, schemeEnd));
+  const defaultPort = DEFAULT_PORTS[scheme];
+  if (defaultPort === undefined) {
+    return;
+  }
+
+  const rest = domain.slice(schemeEnd + 3);
+  const authority = rest.endsWith("/") ? rest.slice(0, -1) : rest;
+  if (/[/?#@]/.test(authority)) {
+    return;
+  }
+  const portStart = authority.lastIndexOf(":");
+  const host = toAsciiLowerCase(
+    portStart === -1 ? authority : authority.slice(0, portStart),
+  );
+  const port =
+    portStart === -1 ? defaultPort : parsePort(authority.slice(portStart + 1));
+  if (port === undefined || !isValidHost(host)) {
+    return;
+  }
+
+  return port === defaultPort
+    ? `${scheme}://${host}`
+    : `${scheme}://${host}:${port.toString()}`;
+};
+
+/**
+ * Returns the hash that identifies `domain` on chain, after normalizing it like the domain registry does.
+ * Domains that can't be normalized are hashed as they are, the domain registry never creates records for them.
+ */
+export const getDomainHash = (domain: string) =>
+  sha256(new TextEncoder().encode(normalizeDomain(domain) ?? domain));
+
+export const getDomainRecordAddress = (domain: string) =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from("domain-record"), getDomainHash(domain)],
     new PublicKey(DomainRegistryIdl.address),
   )[0];
-};
+
+// Domain records may start with a header: a discriminator, the length of the header as a little-endian u32 and the header itself
+const DOMAIN_RECORD_HEADER_DISCRIMINATOR = Buffer.from([
+  92, 194, 234, 129, 71, 32, 52, 179,
+]);
+
+export const getDomainRecordProgramsOffset = (data: Buffer) =>
+  data.subarray(0, 8).equals(DOMAIN_RECORD_HEADER_DISCRIMINATOR)
+    ? 12 + data.readUInt32LE(8)
+    : 0;
 
 export const main = async (argv: string[] = hideBin(process.argv)) =>
   yargs(argv)
@@ -66,15 +152,56 @@ export const main = async (argv: string[] = hideBin(process.argv)) =>
       (args) => handleRemove(args),
     )
     .command(
-      "update-authority <new-authority>",
-      "Update the authority of the domain registry",
+      "index <domain> <program-id>",
+      "Add the given domain to the index of domains of a program that was added to it before the index existed",
+      (y) =>
+        y
+          .positional("domain", {
+            demandOption: true,
+            description: "Domain the program is registered for",
+            type: "string",
+          })
+          .positional("program-id", {
+            demandOption: true,
+            description: "Program ID to index",
+            type: "string",
+          }),
+      (args) => handleIndex(args),
+    )
+    .command(
+      "migrate-domain-record <legacy-domain>",
+      "Move a domain record registered before domains were normalized, under a non-canonical spelling of its domain, to the domain record of the canonical domain",
+      (y) =>
+        y.positional("legacy-domain", {
+          demandOption: true,
+          description:
+            "Domain as it was registered, for example with a trailing slash",
+          type: "string",
+        }),
+      (args) => handleMigrateDomainRecord(args),
+    )
+    .command(
+      "propose-authority <new-authority>",
+      "Propose a new authority for the domain registry, it needs to accept before it becomes the authority",
       (y) =>
         y.positional("new-authority", {
           demandOption: true,
-          description: "New authority for the domain registry",
+          description: "Proposed authority for the domain registry",
           type: "string",
         }),
-      (args) => handleUpdateAuthority(args),
+      (args) => handleProposeAuthority(args),
+    )
+    .command(
+      "accept-authority",
+      "Accept the authority of the domain registry with the proposed authority's wallet",
+      (y) => y,
+      (args) => handleAcceptAuthority(args),
+    )
+    .command(
+      "migrate-config",
+      "Grow the config account of the domain registry to its current size",
+      (y) => y,
+      (args) => handleMigrateConfig(args),
     )
     .demandCommand(1, "Please specify a command")
     .strict()
@@ -114,7 +241,11 @@ const handleList = async (args: { domain: string } & AnchorArgs) => {
 
   if (domainRecordData) {
     const programs = [];
-    for (let i = 0; i < domainRecordData.length; i += 64) {
+    for (
+      let i = getDomainRecordProgramsOffset(domainRecordData);
+      i < domainRecordData.length;
+      i += 64
+    ) {
       programs.push(new PublicKey(domainRecordData.subarray(i, i + 32)));
     }
     // biome-ignore lint/suspicious/noConsole: need to print warning for user
@@ -146,9 +277,100 @@ const handleRemove = async (
     .rpc();
 };
 
-const handleUpdateAuthority = async (
+const handleIndex = async (
+  args: {
+    domain: string;
+    "program-id": string;
+  } & AnchorArgs,
+) => {
+  const program = new DomainRegistryProgram(createAnchorProvider(args));
+
+  await program.methods
+    .indexProgram(args.domain)
+    .accounts({
+      payer: program.provider.publicKey,
+      domainRecord: getDomainRecordAddress(args.domain),
+      programId: new PublicKey(args["program-id"]),
+    })
+    .rpc();
+};
+
+const handleMigrateDomainRecord = async (
+  args: { legacyDomain: string } & AnchorArgs,
+) => {
+  const program = new DomainRegistryProgram(createAnchorProvider(args));
+  const [legacyDomainRecord] = PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("domain-record"),
+      sha256(new TextEncoder().encode(args.legacyDomain)),
+    ],
+    program.programId,
+  );
+  const { data: legacyDomainRecordData } =
+    (await program.provider.connection.getAccountInfo(legacyDomainRecord)) ?? {
+      data: undefined,
+    };
+  if (!legacyDomainRecordData) {
+    // biome-ignore lint/suspicious/noConsole: need to print warning for user
+    console.log(`No domain record found for domain "${args.legacyDomain}"`);
+    return;
+  }
+
+  // The program domains PDAs of the programs of the domain record, in order
+  const programDomains = [];
+  for (
+    let i = getDomainRecordProgramsOffset(legacyDomainRecordData);
+    i < legacyDomainRecordData.length;
+    i += 64
+  ) {
+    programDomains.push(
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("program-domains"),
+          legacyDomainRecordData.subarray(i, i + 32),
+        ],
+        program.programId,
+      )[0],
+    );
+  }
+
+  await program.methods
+    .migrateDomainRecord(args.legacyDomain)
+    .accounts({
+      domainRecord: getDomainRecordAddress(args.legacyDomain),
+      legacyDomainRecord,
+    })
+    .remainingAccounts(
+      programDomains.map((pubkey) => ({
+        isSigner: false,
+        isWritable: true,
+        pubkey,
+      })),
+    )
+    .rpc();
+};
+
+const handleProposeAuthority = async (
   args: { newAuthority: string } & AnchorArgs,
 ) => {
   const program = new DomainRegistryProgram(createAnchorProvider(args));
-  await program.methods.updateAuthority(new PublicKey(args.newAuthority)).rpc();
+  await program.methods
+    .proposeAuthority(new PublicKey(args.newAuthority))
+    .rpc();
+};
+
+const handleAcceptAuthority = async (args: AnchorArgs) => {
+  const program = new DomainRegistryProgram(createAnchorProvider(args));
+  await program.methods
+    .acceptAuthority()
+    .accounts({ pendingAuthority: program.provider.publicKey })
+    .rpc();
+};
+
+const handleMigrateConfig = async (args: AnchorArgs) => {
+  const program = new DomainRegistryProgram(createAnchorProvider(args));
+  await program.methods
+    .migrateConfig()
+    .accounts({ payer: program.provider.publicKey })
+    .rpc();
 };
@@ -1,4 +1,6 @@
 import { ChainIdProgram } from "@fogo/sessions-idls";
+import { PublicKey } from "@solana/web3.js";
+import BN from "bn.js";
 import yargs from "yargs";
 import { hideBin } from "yargs/helpers";
 
@@ -6,16 +8,31 @@ import { anchorOptions, createAnchorProvider } from "./anchor-options.js";
 
 export const main = async (argv: string[] = hideBin(process.argv)) => {
   const args = await yargs(argv)
-    .command("* <chain-id>", "Set the chain ID")
+    .command(
+      "* <chain-id> <numeric-id> <authority>",
+      "Set the chain ID, signed by the upgrade authority of the chain ID program",
+    )
     .options(anchorOptions)
     .positional("chain-id", {
       demandOption: true,
       description: "Chain ID to set as a string",
       type: "string",
     })
+    .positional("numeric-id", {
+      coerce: (numericId: number) => new BN(numericId),
+      demandOption: true,
+      description: "Numeric ID of the chain",
+      type: "number",
+    })
+    .positional("authority", {
+      coerce: (authority: string) => new PublicKey(authority),
+      demandOption: true,
+      description: "Authority of the chain ID",
+      type: "string",
+    })
     .parse();
 
   await new ChainIdProgram(createAnchorProvider(args)).methods
-    .set(args["chain-id"])
+    .set(args["chain-id"], args.numericId, args.authority)
     .rpc();
 };
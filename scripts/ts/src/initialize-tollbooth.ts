@@ -1,5 +1,4 @@
 import { DomainRegistryProgram, TollboothIdl } from "@fogo/sessions-idls";
-import { sha256 } from "@noble/hashes/sha2.js";
 import {
   createAssociatedTokenAccountIdempotentInstruction,
   getAssociatedTokenAddressSync,
@@ -10,20 +9,26 @@ import yargs from "yargs";
 import { hideBin } from "yargs/helpers";
 
 import { anchorOptions, createAnchorProvider } from "./anchor-options.js";
-import { getDomainRecordAddress } from "./domain-registry.js";
+import {
+  getDomainHash,
+  getDomainRecordAddress,
+  getDomainRecordProgramsOffset,
+} from "./domain-registry.js";
 
 const USDC_MINT = {
   mainnet: "uSd2czE61Evaf76RNbq4KPpXnkiL3irdzgLFUMe3NoG",
   testnet: "ELNbJ1RtERV2fjtuZjbTscDekWhVzkQ1LjmiPsxp5uND",
 } as const;
 
-const getDomainTollRecipientAddress = (domain: string) => {
-  const hash = sha256(new TextEncoder().encode(domain));
-  return PublicKey.findProgramAddressSync(
-    [Buffer.from("toll_recipient"), Buffer.from([0]), hash],
+const getDomainTollRecipientAddress = (domain: string, recipientId: number) =>
+  PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("toll_recipient"),
+      Buffer.from([recipientId]),
+      getDomainHash(domain),
+    ],
     new PublicKey(TollboothIdl.address),
   )[0];
-};
 
 const resolveDefaultMints = (url: string) => {
   const normalizedUrl = url.toLowerCase();
@@ -50,6 +55,18 @@ export const main = async (argv: string[] = hideBin(process.argv)) => {
       description: "Mint addresses or symbols (USDC, WSOL)",
       type: "string",
     })
+    .option("recipients", {
+      default: 1,
+      description:
+        "Number of toll recipients to initialize token accounts for, from 1 to 256",
+      type: "number",
+    })
+    .check(({ recipients }) => {
+      if (!Number.isInteger(recipients) || recipients < 1 || recipients > 256) {
+        throw new Error("recipients must be an integer from 1 to 256");
+      }
+      return true;
+    })
     .parse();
 
   const provider = createAnchorProvider(args);
@@ -61,7 +78,11 @@ export const main = async (argv: string[] = hideBin(process.argv)) => {
     await provider.connection.getAccountInfo(domainRecord);
   const programAlreadyAdded = domainRecordInfo?.data
     ? (() => {
-        for (let i = 0; i < domainRecordInfo.data.length; i += 64) {
+        for (
+          let i = getDomainRecordProgramsOffset(domainRecordInfo.data);
+          i < domainRecordInfo.data.length;
+          i += 64
+        ) {
           const entry = domainRecordInfo.data.subarray(i, i + 32);
           if (entry.length === 32 && new PublicKey(entry).equals(programId)) {
             return true;
@@ -97,19 +118,21 @@ export const main = async (argv: string[] = hideBin(process.argv)) => {
     ...resolveDefaultMints(provider.connection.rpcEndpoint),
   ];
 
-  const recipient = getDomainTollRecipientAddress(args.domain);
   const payer = provider.wallet.publicKey;
-  const instructions = mints.map((mint) =>
-    createAssociatedTokenAccountIdempotentInstruction(
-      payer,
-      getAssociatedTokenAddressSync(mint, recipient, true),
-      recipient,
-      mint,
-    ),
-  );
+  for (let recipientId = 0; recipientId < args.recipients; recipientId++) {
+    const recipient = getDomainTollRecipientAddress(args.domain, recipientId);
+    const instructions = mints.map((mint) =>
+      createAssociatedTokenAccountIdempotentInstruction(
+        payer,
+        getAssociatedTokenAddressSync(mint, recipient, true),
+        recipient,
+        mint,
+      ),
+    );
 
-  if (instructions.length > 0) {
-    const transaction = new Transaction().add(...instructions);
-    await provider.sendAndConfirm(transaction);
+    if (instructions.length > 0) {
+      const transaction = new Transaction().add(...instructions);
+      await provider.sendAndConfirm(transaction);
+    }
   }
 };
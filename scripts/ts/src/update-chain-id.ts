@@ -0,0 +1,50 @@
+import { ChainIdProgram } from "@fogo/sessions-idls";
+import { PublicKey } from "@solana/web3.js";
+import BN from "bn.js";
+import yargs from "yargs";
+import { hideBin } from "yargs/helpers";
+
+import { anchorOptions, createAnchorProvider } from "./anchor-options.js";
+
+export const main = async (argv: string[] = hideBin(process.argv)) => {
+  const args = await yargs(argv)
+    .command(
+      "* <chain-id> <numeric-id> <authority>",
+      "Update the chain ID with its authority or the upgrade authority of the chain ID program",
+    )
+    .options(anchorOptions)
+    .positional("chain-id", {
+      demandOption: true,
+      description: "Chain ID to set as a string",
+      type: "string",
+    })
+    .positional("numeric-id", {
+      coerce: (numericId: number) => new BN(numericId),
+      demandOption: true,
+      description: "Numeric ID of the chain",
+      type: "number",
+    })
+    .positional("authority", {
+      coerce: (authority: string) => new PublicKey(authority),
+      demandOption: true,
+      description: "New authority of the chain ID",
+      type: "string",
+    })
+    .option("upgrade-authority", {
+      default: false,
+      description:
+        "Sign as the upgrade authority of the chain ID program instead of the authority of the chain ID",
+      type: "boolean",
+    })
+    .parse();
+
+  const program = new ChainIdProgram(createAnchorProvider(args));
+  const builder = program.methods.update(
+    args["chain-id"],
+    args.numericId,
+    args.authority,
+  );
+  await (
+    args.upgradeAuthority ? builder : builder.accounts({ programData: null })
+  ).rpc();
+};
@@ -0,0 +1,89 @@
+import { DomainRegistryIdl, TollboothProgram } from "@fogo/sessions-idls";
+import { sha256 } from "@noble/hashes/sha2.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { PublicKey } from "@solana/web3.js";
+import yargs from "yargs";
+import { hideBin } from "yargs/helpers";
+
+import { anchorOptions, createAnchorProvider } from "./anchor-options.js";
+import { getDomainHash, getDomainRecordAddress } from "./domain-registry.js";
+
+export const main = async (argv: string[] = hideBin(process.argv)) => {
+  const args = await yargs(argv)
+    .command(
+      "* <domain> <mint> <destination>",
+      "Withdraw the tolls of a domain with the wallet of the admin of the domain, or of the registry authority if the domain doesn't have an admin",
+    )
+    .options(anchorOptions)
+    .positional("domain", {
+      demandOption: true,
+      description: "Domain to withdraw the tolls of",
+      type: "string",
+    })
+    .positional("mint", {
+      coerce: (mint: string) => new PublicKey(mint),
+      demandOption: true,
+      description: "Mint of the tolls to withdraw",
+      type: "string",
+    })
+    .positional("destination", {
+      coerce: (destination: string) => new PublicKey(destination),
+      demandOption: true,
+      description: "Token account to send the tolls to",
+      type: "string",
+    })
+    .option("recipient-id", {
+      default: 0,
+      description: "Toll recipient to withdraw from, from 0 to 255",
+      type: "number",
+    })
+    .option("legacy", {
+      default: false,
+      description:
+        "Withdraw the tolls paid by sessions started before domains were normalized, to the toll recipient of the domain as spelled",
+      type: "boolean",
+    })
+    .parse();
+
+  const program = new TollboothProgram(createAnchorProvider(args));
+  const [tollRecipient] = PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("toll_recipient"),
+      Buffer.from([args.recipientId]),
+      args.legacy
+        ? sha256(new TextEncoder().encode(args.domain))
+        : getDomainHash(args.domain),
+    ],
+    program.programId,
+  );
+  const [config] = PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    new PublicKey(DomainRegistryIdl.address),
+  );
+  // The tolls can be in mints of the token program or of Token-2022
+  const mintAccount = await program.provider.connection.getAccountInfo(
+    args.mint,
+  );
+  if (mintAccount === null) {
+    throw new Error(`Mint ${args.mint.toBase58()} not found`);
+  }
+
+  await program.methods
+    .withdrawTolls(args.domain, args.recipientId)
+    .accounts({
+      admin: program.provider.publicKey,
+      config,
+      destination: args.destination,
+      domainRecord: getDomainRecordAddress(args.domain),
+      mint: args.mint,
+      source: getAssociatedTokenAddressSync(
+        args.mint,
+        tollRecipient,
+        true,
+        mintAccount.owner,
+      ),
+      tokenProgram: mintAccount.owner,
+      tollRecipient,
+    })
+    .rpc();
+};
@@ -402,9 +402,13 @@ async fn sponsor_and_send_handler(
             )
         })?;
 
-    let transaction_to_validate =
-        TransactionToValidate::parse(&transaction, &state.chain_index, &state.fee_coefficients)
-            .await?;
+    let transaction_to_validate = TransactionToValidate::parse(
+        &transaction,
+        &state.chain_index,
+        &state.fee_coefficients,
+        &domain,
+    )
+    .await?;
     let (matched_variation_name, swap_into_fogo) = match domain_state
         .validate_transaction(
             &transaction_to_validate,
@@ -492,7 +492,7 @@ async fn get_matching_variations<'a>(
     let contextual_keys = contextual_keys_cache.get(domain).await?;
     for variation in tx_variations.values() {
         let matches = if let Ok(paymaster_transaction) =
-            TransactionToValidate::parse(transaction, chain_index, &HashMap::new()).await
+            TransactionToValidate::parse(transaction, chain_index, &HashMap::new(), domain).await
         {
             match variation {
                 ParsedTransactionVariation::V0(v0_variation) => {
@@ -1,36 +1,59 @@
 use anchor_lang::{AnchorDeserialize, Discriminator};
-use fogo_sessions_sdk::tollbooth::TOLLBOOTH_PROGRAM_ID;
+use fogo_sessions_sdk::domain_registry::get_domain_hash;
+use fogo_sessions_sdk::tollbooth::{get_toll_recipient_address, TOLLBOOTH_PROGRAM_ID};
 use reqwest::StatusCode;
 use solana_program::instruction::CompiledInstruction;
 use solana_pubkey::Pubkey;
 use solana_transaction::versioned::VersionedTransaction;
+use spl_associated_token_account::get_associated_token_address;
 use std::collections::HashMap;
 use tollbooth::{self, instruction::PayToll};
 
 use crate::{constraint::transaction::InstructionWithIndex, rpc::ChainIndex};
 
+const PAY_TOLL_INSTRUCTION_DESTINATION_INDEX: usize = 3;
 const PAY_TOLL_INSTRUCTION_MINT_INDEX: usize = 4;
 
+/// Sums the tolls that the transaction pays to the toll recipients of `domain`, weighted by the fee coefficient of their mint.
+/// Tolls paid to other destinations don't count towards the paymaster fee.
 pub async fn compute_paymaster_fees(
     transaction: &VersionedTransaction,
     chain_index: &ChainIndex,
     fee_coefficients: &HashMap<Pubkey, u64>,
+    domain: &str,
 ) -> Result<u64, (StatusCode, String)> {
+    let domain_hash = get_domain_hash(domain);
     let mut total_fee = 0u64;
     for (index, instruction) in transaction.message.instructions().iter().enumerate() {
         if instruction.program_id(transaction.message.static_account_keys())
             == &TOLLBOOTH_PROGRAM_ID
         {
-            let amount = parse_pay_toll_instruction(instruction)
+            let (amount, recipient_id) = parse_pay_toll_instruction(instruction)
                 .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
 
+            let instruction_with_index = InstructionWithIndex { index, instruction };
             let mint = chain_index
                 .resolve_instruction_account_pubkey(
                     &transaction.message,
-                    &InstructionWithIndex { index, instruction },
+                    &instruction_with_index,
                     PAY_TOLL_INSTRUCTION_MINT_INDEX,
                 )
                 .await?;
+            let destination = chain_index
+                .resolve_instruction_account_pubkey(
+                    &transaction.message,
+                    &instruction_with_index,
+                    PAY_TOLL_INSTRUCTION_DESTINATION_INDEX,
+                )
+                .await?;
+            if destination
+                != get_associated_token_address(
+                    &get_toll_recipient_address(&domain_hash, recipient_id),
+                    &mint,
+                )
+            {
+                continue;
+            }
 
             total_fee = total_fee
                 .saturating_add(amount.saturating_mul(*fee_coefficients.get(&mint).unwrap_or(&0)));
@@ -39,7 +62,8 @@ pub async fn compute_paymaster_fees(
     Ok(total_fee)
 }
 
-fn parse_pay_toll_instruction(instruction: &CompiledInstruction) -> anyhow::Result<u64> {
+/// Returns the amount and the recipient id of a `PayToll` instruction
+fn parse_pay_toll_instruction(instruction: &CompiledInstruction) -> anyhow::Result<(u64, u8)> {
     let discriminator = instruction
         .data
         .get(0..1)
@@ -50,7 +74,7 @@ fn parse_pay_toll_instruction(instruction: &CompiledInstruction) -> anyhow::Resu
     );
     let PayToll {
         amount,
-        _recipient_id: _,
+        recipient_id,
     } = tollbooth::instruction::PayToll::try_from_slice(
         instruction
             .data
@@ -58,5 +82,154 @@ fn parse_pay_toll_instruction(instruction: &CompiledInstruction) -> anyhow::Resu
             .expect("This branch only gets visited if instruction.data is not empty"),
     )
     .map_err(|_| anyhow::anyhow!("Failed to deserialize PayToll instruction"))?;
-    Ok(amount)
+    Ok((amount, recipient_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{InstructionData, ToAccountMetas};
+    use dashmap::DashMap;
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_message::{Message, VersionedMessage};
+    use solana_program::instruction::Instruction;
+    use std::sync::Arc;
+
+    const DOMAIN: &str = "https://app.xyz";
+    const FEE_COEFFICIENT: u64 = 3;
+
+    fn toll_destination(domain: &str, mint: &Pubkey, recipient_id: u8) -> Pubkey {
+        get_associated_token_address(
+            &get_toll_recipient_address(&get_domain_hash(domain), recipient_id),
+            mint,
+        )
+    }
+
+    fn pay_toll(mint: &Pubkey, destination: Pubkey, amount: u64, recipient_id: u8) -> Instruction {
+        Instruction {
+            program_id: TOLLBOOTH_PROGRAM_ID,
+            accounts: tollbooth::accounts::PayToll {
+                session: Pubkey::new_unique(),
+                program_signer: Pubkey::new_unique(),
+                source: Pubkey::new_unique(),
+                destination,
+                mint: *mint,
+                token_program: spl_token::ID,
+                domain_record: None,
+            }
+            .to_account_metas(None),
+            data: PayToll {
+                amount,
+                recipient_id,
+            }
+            .data(),
+        }
+    }
+
+    async fn compute_fees(mint: &Pubkey, instructions: &[Instruction]) -> u64 {
+        let transaction = VersionedTransaction {
+            signatures: vec![],
+            message: VersionedMessage::Legacy(Message::new(
+                instructions,
+                Some(&Pubkey::new_unique()),
+            )),
+        };
+        // The accounts of these transactions are all static, so the RPC is never called
+        let chain_index = ChainIndex {
+            rpc: Arc::new(RpcClient::new("http://localhost:8899".to_string())),
+            lookup_table_cache: DashMap::new(),
+        };
+        compute_paymaster_fees(
+            &transaction,
+            &chain_index,
+            &HashMap::from([(*mint, FEE_COEFFICIENT)]),
+            DOMAIN,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compute_paymaster_fees_for_recipient_0() {
+        let mint = Pubkey::new_unique();
+        assert_eq!(
+            compute_fees(
+                &mint,
+                &[pay_toll(&mint, toll_destination(DOMAIN, &mint, 0), 100, 0)]
+            )
+            .await,
+            100 * FEE_COEFFICIENT
+        );
+        // The toll recipients are derived from the canonical domain
+        assert_eq!(
+            compute_fees(
+                &mint,
+                &[pay_toll(
+                    &mint,
+                    toll_destination("HTTPS://App.xyz/", &mint, 0),
+                    100,
+                    0
+                )]
+            )
+            .await,
+            100 * FEE_COEFFICIENT
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compute_paymaster_fees_for_other_recipients() {
+        let mint = Pubkey::new_unique();
+        assert_eq!(
+            compute_fees(
+                &mint,
+                &[pay_toll(
+                    &mint,
+                    toll_destination(DOMAIN, &mint, 42),
+                    100,
+                    42
+                )]
+            )
+            .await,
+            100 * FEE_COEFFICIENT
+        );
+        assert_eq!(
+            compute_fees(
+                &mint,
+                &[
+                    pay_toll(&mint, toll_destination(DOMAIN, &mint, 0), 100, 0),
+                    pay_toll(&mint, toll_destination(DOMAIN, &mint, 255), 50, 255),
+                ]
+            )
+            .await,
+            150 * FEE_COEFFICIENT
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compute_paymaster_fees_ignores_foreign_destinations() {
+        let mint = Pubkey::new_unique();
+        for destination in [
+            Pubkey::new_unique(),
+            toll_destination("https://other.xyz", &mint, 0),
+            // The toll account of another recipient than the one in the instruction
+            toll_destination(DOMAIN, &mint, 1),
+            toll_destination(DOMAIN, &Pubkey::new_unique(), 0),
+        ] {
+            assert_eq!(
+                compute_fees(&mint, &[pay_toll(&mint, destination, 100, 0)]).await,
+                0
+            );
+        }
+        assert_eq!(
+            compute_fees(
+                &mint,
+                &[
+                    pay_toll(&mint, Pubkey::new_unique(), 1_000, 0),
+                    pay_toll(&mint, toll_destination(DOMAIN, &mint, 0), 100, 0),
+                ]
+            )
+            .await,
+            100 * FEE_COEFFICIENT
+        );
+    }
 }